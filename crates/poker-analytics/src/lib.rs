@@ -0,0 +1,381 @@
+//! Team reports over estimation history.
+//!
+//! Every room builds up a history of revealed rounds in the
+//! [`Store`](poker_store::Store). A [`Report`] sums up the history of one room,
+//! or of every room a team started from its template over the sprints: the
+//! estimates the team agreed on over time, how often the first round on a
+//! story already reached consensus, how many re-votes a story needs, and
+//! which voters tend to land far from the rest of the team.
+//!
+//! Rounds are grouped into stories by room and backlog story, or by room and
+//! title for ad-hoc rounds; an untitled ad-hoc round counts as a story of its
+//! own. Rounds revealed anonymously count towards every figure but the voter
+//! statistics.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use poker_core::{Card, RoomId, Story, StoryId, Timestamp};
+use poker_store::RoundRecord;
+use serde::{Deserialize, Serialize};
+
+/// Fewest numeric votes a round needs before anyone in it can be an outlier.
+pub const MIN_VOTES_FOR_OUTLIERS: usize = 3;
+
+/// What a history says about how a team estimates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    #[serde(flatten)]
+    pub scope: Scope,
+    /// Revealed rounds in the history.
+    pub rounds: usize,
+    /// Stories with at least one revealed round.
+    pub stories: usize,
+    /// Recorded estimates, oldest first.
+    pub estimates: Vec<EstimatePoint>,
+    /// Share of stories whose first round ended with everyone playing the same
+    /// card. `None` without any rounds.
+    pub first_round_consensus_rate: Option<f64>,
+    /// Rounds after the first that a story needed, on average.
+    pub average_revotes: Option<f64>,
+    /// Everyone who voted in a round that could have outliers, most frequent
+    /// outliers first.
+    pub voters: Vec<VoterStats>,
+}
+
+/// The history a [`Report`] covers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Scope {
+    Room {
+        room: RoomId,
+    },
+    /// Every room started from the template called `team`.
+    Team {
+        team: String,
+        rooms: Vec<RoomId>,
+    },
+}
+
+/// A final estimate and when it was recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimatePoint {
+    pub room: RoomId,
+    pub story: StoryId,
+    pub title: String,
+    pub key: Option<String>,
+    pub estimate: Card,
+    /// The estimate as a number, for numeric cards.
+    pub value: Option<f64>,
+    pub estimated_at: Timestamp,
+    /// Rounds it took to get there.
+    pub rounds: usize,
+}
+
+/// How often one voter's card was far from the rest of the team's.
+///
+/// A vote is an outlier when it is at least twice, or at most half, the
+/// median of its round. Only numeric votes in rounds with at least
+/// [`MIN_VOTES_FOR_OUTLIERS`] of them are counted, and not those in rounds
+/// whose median is 0, where every vote would be twice or half of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoterStats {
+    pub name: String,
+    pub votes: usize,
+    pub outliers: usize,
+    pub outlier_rate: f64,
+}
+
+/// Builds the report of `room` from its revealed rounds and, when the room
+/// still exists, its backlog.
+pub fn report(room: RoomId, rounds: &[RoundRecord], stories: &[Story]) -> Report {
+    let backlogs = [(room.clone(), stories.to_vec())];
+    build(Scope::Room { room }, rounds, &backlogs)
+}
+
+/// Builds the report of a team from the revealed rounds of all of its rooms
+/// and the backlogs of those that still exist.
+pub fn team_report(
+    team: String,
+    rounds: &[RoundRecord],
+    backlogs: &[(RoomId, Vec<Story>)],
+) -> Report {
+    let rooms: BTreeSet<RoomId> = rounds
+        .iter()
+        .map(|r| r.room.clone())
+        .chain(backlogs.iter().map(|(room, _)| room.clone()))
+        .collect();
+    let scope = Scope::Team {
+        team,
+        rooms: rooms.into_iter().collect(),
+    };
+    build(scope, rounds, backlogs)
+}
+
+fn build(scope: Scope, rounds: &[RoundRecord], backlogs: &[(RoomId, Vec<Story>)]) -> Report {
+    let mut rounds: Vec<&RoundRecord> = rounds.iter().collect();
+    rounds.sort_by_key(|r| r.revealed_at);
+
+    let groups = group_by_story(&rounds);
+    let first_round_consensus = groups.values().filter(|g| is_consensus(g[0])).count();
+    let revotes: usize = groups.values().map(|g| g.len() - 1).sum();
+
+    let mut estimates: Vec<EstimatePoint> = backlogs
+        .iter()
+        .flat_map(|(room, stories)| stories.iter().map(move |story| (room, story)))
+        .filter_map(|(room, story)| {
+            let estimate = story.estimate.clone()?;
+            Some(EstimatePoint {
+                room: room.clone(),
+                story: story.id,
+                title: story.title.clone(),
+                key: story.key.clone(),
+                value: estimate.value(),
+                estimate,
+                estimated_at: story.estimated_at?,
+                rounds: groups
+                    .get(&StoryKey::Backlog(room.clone(), story.id))
+                    .map_or(story.rounds.len(), Vec::len),
+            })
+        })
+        .collect();
+    estimates.sort_by_key(|e| e.estimated_at);
+
+    Report {
+        scope,
+        rounds: rounds.len(),
+        stories: groups.len(),
+        estimates,
+        first_round_consensus_rate: ratio(first_round_consensus, groups.len()),
+        average_revotes: ratio(revotes, groups.len()),
+        voters: voter_stats(&rounds),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum StoryKey {
+    Backlog(RoomId, StoryId),
+    AdHoc(RoomId, String),
+    Untitled(usize),
+}
+
+fn group_by_story<'a>(rounds: &[&'a RoundRecord]) -> BTreeMap<StoryKey, Vec<&'a RoundRecord>> {
+    let mut groups: BTreeMap<StoryKey, Vec<&RoundRecord>> = BTreeMap::new();
+    for (index, round) in rounds.iter().enumerate() {
+        let key = match (round.story_id, &round.story) {
+            (Some(id), _) => StoryKey::Backlog(round.room.clone(), id),
+            (None, Some(title)) => StoryKey::AdHoc(round.room.clone(), title.clone()),
+            (None, None) => StoryKey::Untitled(index),
+        };
+        groups.entry(key).or_default().push(round);
+    }
+    groups
+}
+
+fn is_consensus(round: &RoundRecord) -> bool {
+    round.summary.distribution.len() == 1
+}
+
+fn voter_stats(rounds: &[&RoundRecord]) -> Vec<VoterStats> {
+    let mut tally: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for round in rounds {
+        let values: Vec<(&str, f64)> = round
+            .votes
+            .iter()
+            .filter_map(|v| Some((v.name.as_str(), v.card.value()?)))
+            .collect();
+        if values.len() < MIN_VOTES_FOR_OUTLIERS {
+            continue;
+        }
+        let median = median(values.iter().map(|&(_, v)| v).collect());
+        if median == 0.0 {
+            continue;
+        }
+        for (name, value) in values {
+            let entry = tally.entry(name).or_default();
+            entry.0 += 1;
+            if value >= median * 2.0 || value <= median / 2.0 {
+                entry.1 += 1;
+            }
+        }
+    }
+    let mut voters: Vec<VoterStats> = tally
+        .into_iter()
+        .map(|(name, (votes, outliers))| VoterStats {
+            name: name.to_owned(),
+            votes,
+            outliers,
+            outlier_rate: outliers as f64 / votes as f64,
+        })
+        .collect();
+    voters.sort_by(|a, b| b.outlier_rate.total_cmp(&a.outlier_rate));
+    voters
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let n = values.len();
+    if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use poker_core::{Deck, ParticipantId, RecordedVote, Summary};
+
+    use super::*;
+
+    fn round(story: Option<u64>, title: &str, at: u64, votes: &[(&str, &str)]) -> RoundRecord {
+        let cards: Vec<Card> = votes.iter().map(|&(_, card)| Card::new(card)).collect();
+        RoundRecord {
+            room: RoomId::new("r"),
+            team: None,
+            story: Some(title.to_owned()),
+            story_id: story.map(StoryId),
+            revealed_at: Timestamp(at),
+            votes: votes
+                .iter()
+                .enumerate()
+                .map(|(i, &(name, card))| RecordedVote {
+                    participant: ParticipantId(i as u64 + 1),
+                    name: name.to_owned(),
+                    card: Card::new(card),
+                })
+                .collect(),
+            summary: Summary::new(&Deck::fibonacci(), &cards),
+        }
+    }
+
+    fn estimated(id: u64, title: &str, estimate: &str, at: u64) -> Story {
+        Story {
+            id: StoryId(id),
+            title: title.to_owned(),
+            key: None,
+            estimate: Some(Card::new(estimate)),
+            estimated_at: Some(Timestamp(at)),
+            rounds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn empty_history_has_no_rates() {
+        let report = report(RoomId::new("r"), &[], &[]);
+        assert_eq!(report.rounds, 0);
+        assert_eq!(report.first_round_consensus_rate, None);
+        assert_eq!(report.average_revotes, None);
+        assert!(report.voters.is_empty());
+    }
+
+    #[test]
+    fn consensus_and_revotes_are_counted_per_story() {
+        let rounds = [
+            round(Some(1), "Login", 10, &[("Ann", "3"), ("Bo", "8")]),
+            round(Some(1), "Login", 20, &[("Ann", "5"), ("Bo", "5")]),
+            round(Some(2), "Search", 30, &[("Ann", "2"), ("Bo", "2")]),
+            round(None, "Spike", 40, &[("Ann", "1"), ("Bo", "3")]),
+            round(None, "Spike", 50, &[("Ann", "2"), ("Bo", "3")]),
+            round(None, "Spike", 60, &[("Ann", "3"), ("Bo", "3")]),
+        ];
+        let report = report(RoomId::new("r"), &rounds, &[]);
+        assert_eq!(report.rounds, 6);
+        assert_eq!(report.stories, 3);
+        assert_eq!(report.first_round_consensus_rate, Some(1.0 / 3.0));
+        assert_eq!(report.average_revotes, Some(1.0));
+    }
+
+    #[test]
+    fn estimates_are_listed_oldest_first() {
+        let stories = [
+            estimated(2, "Search", "8", 200),
+            estimated(1, "Login", "5", 100),
+            Story {
+                estimate: None,
+                estimated_at: None,
+                ..estimated(3, "Cart", "1", 0)
+            },
+        ];
+        let rounds = [
+            round(Some(1), "Login", 10, &[("Ann", "3")]),
+            round(Some(1), "Login", 20, &[("Ann", "5")]),
+        ];
+        let report = report(RoomId::new("r"), &rounds, &stories);
+        let titles: Vec<_> = report.estimates.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, ["Login", "Search"]);
+        assert_eq!(report.estimates[0].value, Some(5.0));
+        assert_eq!(report.estimates[0].rounds, 2);
+    }
+
+    #[test]
+    fn outliers_are_far_from_the_median() {
+        let rounds = [
+            round(None, "A", 10, &[("Ann", "5"), ("Bo", "5"), ("Cy", "13")]),
+            round(None, "B", 20, &[("Ann", "3"), ("Bo", "3"), ("Cy", "5")]),
+            round(None, "C", 30, &[("Ann", "8"), ("Bo", "8"), ("Cy", "?")]),
+            round(None, "D", 40, &[("Ann", "1"), ("Bo", "20")]),
+            // Nobody is far from a median of 0.
+            round(None, "E", 50, &[("Ann", "0"), ("Bo", "0"), ("Cy", "1")]),
+        ];
+        let report = report(RoomId::new("r"), &rounds, &[]);
+        let cy = &report.voters[0];
+        assert_eq!((cy.name.as_str(), cy.votes, cy.outliers), ("Cy", 2, 1));
+        assert_eq!(cy.outlier_rate, 0.5);
+        assert!(report.voters[1..].iter().all(|v| v.outliers == 0));
+        assert_eq!(report.voters.len(), 3);
+    }
+
+    #[test]
+    fn anonymous_rounds_name_no_voters() {
+        let mut anonymous = round(Some(1), "Login", 10, &[("Ann", "1"), ("Bo", "1")]);
+        anonymous.votes.clear();
+        let report = report(RoomId::new("r"), &[anonymous], &[]);
+        assert_eq!(report.first_round_consensus_rate, Some(1.0));
+        assert!(report.voters.is_empty());
+    }
+
+    #[test]
+    fn team_reports_span_the_teams_rooms() {
+        let in_room = |room: &str, round: RoundRecord| RoundRecord {
+            room: RoomId::new(room),
+            team: Some("Web".into()),
+            ..round
+        };
+        // Story ids start over in every room.
+        let rounds = [
+            in_room(
+                "s1",
+                round(Some(1), "Login", 10, &[("Ann", "3"), ("Bo", "8")]),
+            ),
+            in_room(
+                "s1",
+                round(Some(1), "Login", 20, &[("Ann", "5"), ("Bo", "5")]),
+            ),
+            in_room(
+                "s2",
+                round(Some(1), "Search", 30, &[("Ann", "2"), ("Bo", "2")]),
+            ),
+        ];
+        let backlogs = [(RoomId::new("s2"), vec![estimated(1, "Search", "2", 40)])];
+        let report = team_report("Web".into(), &rounds, &backlogs);
+        assert_eq!(
+            report.scope,
+            Scope::Team {
+                team: "Web".into(),
+                rooms: vec![RoomId::new("s1"), RoomId::new("s2")],
+            }
+        );
+        assert_eq!(report.stories, 2);
+        assert_eq!(report.first_round_consensus_rate, Some(0.5));
+        assert_eq!(report.estimates[0].room, RoomId::new("s2"));
+        assert_eq!(report.estimates[0].rounds, 1);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["team"], "Web");
+        assert_eq!(serde_json::from_value::<Report>(json).unwrap(), report);
+    }
+}
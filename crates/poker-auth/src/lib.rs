@@ -0,0 +1,40 @@
+//! Access control for OpenPlanningPoker rooms.
+//!
+//! Private rooms are protected by a join passphrase, stored only as an
+//! Argon2 hash (see [`hash_passphrase`]). Organizations can additionally
+//! require everyone to log in through an OpenID Connect provider:
+//! [`OidcClient`] runs the authorization code flow and turns the provider's
+//! ID token into an [`Identity`] whose name is shown in rooms.
+//!
+//! With the `stub` feature, [`stub::StubProvider`] serves a minimal provider
+//! on localhost for tests and local development.
+
+mod oidc;
+mod passphrase;
+#[cfg(feature = "stub")]
+pub mod stub;
+
+pub use oidc::{Identity, LoginStart, OidcClient, OidcConfig};
+pub use passphrase::{hash_passphrase, verify_passphrase};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("identity provider request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("identity provider answered {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("invalid identity provider URL: {0}")]
+    InvalidUrl(String),
+    #[error("identity provider metadata is unusable: {0}")]
+    Discovery(String),
+    #[error("ID token rejected: {0}")]
+    IdToken(String),
+}
+
+pub type Result<T, E = AuthError> = std::result::Result<T, E>;
+
+/// A random URL-safe secret, e.g. for login states and nonces.
+pub fn random_secret() -> String {
+    use rand::distr::{Alphanumeric, SampleString};
+    Alphanumeric.sample_string(&mut rand::rng(), 32)
+}
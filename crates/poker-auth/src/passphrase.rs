@@ -0,0 +1,40 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+/// Hashes a room passphrase with Argon2id and a fresh salt, in the PHC
+/// string format that [`verify_passphrase`] reads.
+pub fn hash_passphrase(passphrase: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .expect("default Argon2 parameters accept any input")
+        .to_string()
+}
+
+/// Whether `candidate` matches a hash made by [`hash_passphrase`]. A
+/// malformed hash matches nothing.
+pub fn verify_passphrase(hash: &str, candidate: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(candidate.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_salted_and_verifiable() {
+        let first = hash_passphrase("open sesame");
+        let second = hash_passphrase("open sesame");
+        assert_ne!(first, second);
+        assert!(!first.contains("open sesame"));
+        assert!(verify_passphrase(&first, "open sesame"));
+        assert!(verify_passphrase(&second, "open sesame"));
+        assert!(!verify_passphrase(&first, "open sesame "));
+        assert!(!verify_passphrase("not a hash", "open sesame"));
+    }
+}
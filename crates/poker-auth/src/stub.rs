@@ -0,0 +1,166 @@
+//! A minimal OpenID Connect provider that logs everyone in as one user.
+//!
+//! It serves discovery, an authorization endpoint that redirects straight
+//! back with a code, and a token endpoint that trades the code for an ID
+//! token. Nothing is signed; it is only meant for tests and local
+//! development.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use crate::random_secret;
+
+/// The user every login resolves to.
+#[derive(Debug, Clone)]
+pub struct StubUser {
+    pub subject: String,
+    pub name: String,
+    pub email: String,
+}
+
+/// A running stub provider. It stops when the runtime does.
+pub struct StubProvider {
+    addr: SocketAddr,
+}
+
+struct Stub {
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    user: StubUser,
+    /// Issued codes and the nonce and redirect URL they were issued for.
+    codes: Mutex<HashMap<String, (String, String)>>,
+}
+
+impl StubProvider {
+    /// Serves a provider on a free localhost port that accepts one client.
+    pub async fn start(client_id: &str, client_secret: &str, user: StubUser) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = Arc::new(Stub {
+            issuer: format!("http://{addr}"),
+            client_id: client_id.to_owned(),
+            client_secret: client_secret.to_owned(),
+            user,
+            codes: Mutex::default(),
+        });
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .with_state(stub);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        StubProvider { addr }
+    }
+
+    pub fn issuer(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+async fn discovery(State(stub): State<Arc<Stub>>) -> Json<serde_json::Value> {
+    Json(json!({
+        "issuer": stub.issuer,
+        "authorization_endpoint": format!("{}/authorize", stub.issuer),
+        "token_endpoint": format!("{}/token", stub.issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["none"],
+        "token_endpoint_auth_methods_supported": ["client_secret_post"],
+    }))
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    client_id: String,
+    redirect_uri: String,
+    state: String,
+    nonce: String,
+}
+
+async fn authorize(State(stub): State<Arc<Stub>>, Query(query): Query<AuthorizeQuery>) -> Response {
+    if query.client_id != stub.client_id {
+        return (StatusCode::BAD_REQUEST, "unknown client").into_response();
+    }
+    let Ok(mut back) = Url::parse(&query.redirect_uri) else {
+        return (StatusCode::BAD_REQUEST, "invalid redirect_uri").into_response();
+    };
+    let code = random_secret();
+    stub.codes
+        .lock()
+        .unwrap()
+        .insert(code.clone(), (query.nonce, query.redirect_uri));
+    back.query_pairs_mut()
+        .append_pair("code", &code)
+        .append_pair("state", &query.state);
+    Redirect::to(back.as_str()).into_response()
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    client_secret: String,
+}
+
+async fn token(State(stub): State<Arc<Stub>>, Form(form): Form<TokenForm>) -> Response {
+    if form.client_id != stub.client_id || form.client_secret != stub.client_secret {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "invalid_client"})),
+        )
+            .into_response();
+    }
+    let Some((nonce, redirect_uri)) = stub.codes.lock().unwrap().remove(&form.code) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "invalid_grant"})),
+        )
+            .into_response();
+    };
+    if redirect_uri != form.redirect_uri {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "invalid_grant"})),
+        )
+            .into_response();
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let claims = json!({
+        "iss": stub.issuer,
+        "sub": stub.user.subject,
+        "aud": stub.client_id,
+        "iat": now,
+        "exp": now + 3600,
+        "nonce": nonce,
+        "name": stub.user.name,
+        "email": stub.user.email,
+    });
+    let encode = |v: serde_json::Value| URL_SAFE_NO_PAD.encode(v.to_string());
+    let id_token = format!("{}.{}.", encode(json!({"alg": "none"})), encode(claims));
+    Json(json!({
+        "access_token": random_secret(),
+        "token_type": "Bearer",
+        "expires_in": 3600,
+        "id_token": id_token,
+    }))
+    .into_response()
+}
@@ -0,0 +1,95 @@
+use poker_auth::stub::{StubProvider, StubUser};
+use poker_auth::{AuthError, OidcClient, OidcConfig};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+const REDIRECT: &str = "http://poker.test/auth/callback";
+
+async fn provider() -> StubProvider {
+    StubProvider::start(
+        "poker",
+        "s3cret",
+        StubUser {
+            subject: "u-42".into(),
+            name: "Alice Example".into(),
+            email: "alice@example.com".into(),
+        },
+    )
+    .await
+}
+
+/// Follows the authorization URL like a browser would, up to the redirect
+/// back to the application, and returns the code and state it carries.
+async fn authorize(url: &str) -> (String, String) {
+    let browser = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+    let response = browser.get(url).send().await.unwrap();
+    assert!(response.status().is_redirection());
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with(REDIRECT));
+    let back = Url::parse(location).unwrap();
+    let param = |name: &str| {
+        back.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    };
+    (param("code"), param("state"))
+}
+
+#[tokio::test]
+async fn authorization_code_flow_yields_identity() {
+    let idp = provider().await;
+    let client = OidcClient::discover(OidcConfig::new(idp.issuer(), "poker", "s3cret", REDIRECT))
+        .await
+        .unwrap();
+
+    let login = client.start_login();
+    let (code, state) = authorize(&login.url).await;
+    assert_eq!(state, login.state);
+
+    let identity = client.finish_login(&code, &login.nonce).await.unwrap();
+    assert_eq!(identity.subject, "u-42");
+    assert_eq!(identity.name, "Alice Example");
+    assert_eq!(identity.email.as_deref(), Some("alice@example.com"));
+
+    // Codes are single use.
+    let err = client.finish_login(&code, &login.nonce).await.unwrap_err();
+    assert!(matches!(err, AuthError::Status { status: 400, .. }));
+}
+
+#[tokio::test]
+async fn rejects_mismatched_nonce_and_wrong_secret() {
+    let idp = provider().await;
+    let client = OidcClient::discover(OidcConfig::new(idp.issuer(), "poker", "s3cret", REDIRECT))
+        .await
+        .unwrap();
+    let login = client.start_login();
+    let (code, _) = authorize(&login.url).await;
+    let err = client.finish_login(&code, "replayed").await.unwrap_err();
+    assert!(matches!(err, AuthError::IdToken(_)));
+
+    let impostor = OidcClient::discover(OidcConfig::new(idp.issuer(), "poker", "guess", REDIRECT))
+        .await
+        .unwrap();
+    let login = impostor.start_login();
+    let (code, _) = authorize(&login.url).await;
+    let err = impostor
+        .finish_login(&code, &login.nonce)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AuthError::Status { status: 401, .. }));
+}
+
+#[tokio::test]
+async fn discovery_requires_matching_issuer() {
+    let idp = provider().await;
+    let issuer = idp.issuer().replace("127.0.0.1", "localhost");
+    let err = OidcClient::discover(OidcConfig::new(issuer, "poker", "s3cret", REDIRECT))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, AuthError::Discovery(_)));
+}
@@ -0,0 +1,146 @@
+//! Messaging between the server instances of an OpenPlanningPoker cluster.
+//!
+//! Several servers can run behind one load balancer. Each room is hosted by
+//! exactly one of them; the others relay their clients' messages to it and
+//! its replies back, so everyone sees the same room no matter which instance
+//! they reached. A [`Broker`] carries those messages and keeps the directory
+//! of which instance hosts which room.
+//!
+//! [`LocalBroker`] connects servers in the same process, and is what a lone
+//! server uses. [`RedisBroker`] speaks the Redis protocol, so any
+//! Redis-compatible server (Redis, Valkey, KeyDB, …) can connect instances
+//! on different machines. With the `stub` feature, [`stub::StubRedis`]
+//! serves enough of that protocol for tests.
+
+mod local;
+mod redis;
+#[cfg(feature = "stub")]
+pub mod stub;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+pub use local::LocalBroker;
+pub use redis::RedisBroker;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BrokerError {
+    #[error("broker connection failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("broker answered with an error: {0}")]
+    Refused(String),
+    #[error("broker sent something unexpected: {0}")]
+    Protocol(String),
+    #[error("broker did not answer within {0:?}")]
+    TimedOut(Duration),
+    #[error("invalid broker URL: {0}")]
+    InvalidUrl(String),
+}
+
+pub type Result<T, E = BrokerError> = std::result::Result<T, E>;
+
+/// Messages published to a topic after subscribing. Closes when the broker
+/// connection is lost.
+pub type Subscription = mpsc::UnboundedReceiver<String>;
+
+/// Pub/sub plus a small key-value store with expiring keys.
+///
+/// Messages published by one caller to one topic arrive in the order they
+/// were published. Delivery is at most once: a subscriber that is not
+/// connected at the time misses the message.
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Delivers `payload` to every current subscriber of `topic`.
+    async fn publish(&self, topic: &str, payload: &str) -> Result<()>;
+
+    /// Starts receiving what is published to `topic`. Returns once the
+    /// subscription is in place.
+    async fn subscribe(&self, topic: &str) -> Result<Subscription>;
+
+    /// Stores `value` under `key` for `ttl`, replacing any previous value.
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()>;
+
+    /// Stores `value` under `key` for `ttl` unless the key is already set.
+    /// Returns whether it was stored.
+    async fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool>;
+
+    /// Makes `key` expire after `ttl` from now if it still holds `value`.
+    /// Returns whether it did; a key that expired or was taken over in the
+    /// meantime is left alone.
+    async fn renew(&self, key: &str, value: &str, ttl: Duration) -> Result<bool>;
+
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Returns whether the key was set.
+    async fn delete(&self, key: &str) -> Result<bool>;
+}
+
+#[cfg(test)]
+pub(crate) mod contract {
+    //! Behaviour every [`Broker`] implementation must share.
+
+    use super::*;
+
+    async fn next(subscription: &mut Subscription) -> String {
+        tokio::time::timeout(Duration::from_secs(5), subscription.recv())
+            .await
+            .expect("timed out waiting for a message")
+            .expect("subscription closed")
+    }
+
+    pub async fn messages_reach_every_subscriber_in_order(broker: &dyn Broker) {
+        let mut first = broker.subscribe("room").await.unwrap();
+        let mut second = broker.subscribe("room").await.unwrap();
+        let mut other = broker.subscribe("other").await.unwrap();
+        for payload in ["one", "two", "three"] {
+            broker.publish("room", payload).await.unwrap();
+        }
+        for subscription in [&mut first, &mut second] {
+            assert_eq!(next(subscription).await, "one");
+            assert_eq!(next(subscription).await, "two");
+            assert_eq!(next(subscription).await, "three");
+        }
+        broker.publish("other", "{\"x\": 1}").await.unwrap();
+        assert_eq!(next(&mut other).await, "{\"x\": 1}");
+
+        // Publishing to a topic nobody listens to is fine.
+        drop(first);
+        broker.publish("nobody", "hello").await.unwrap();
+    }
+
+    pub async fn keys_expire_and_claims_are_exclusive(broker: &dyn Broker) {
+        let minute = Duration::from_secs(60);
+        assert_eq!(broker.get("owner").await.unwrap(), None);
+        assert!(broker.set_if_absent("owner", "a", minute).await.unwrap());
+        assert!(!broker.set_if_absent("owner", "b", minute).await.unwrap());
+        assert_eq!(broker.get("owner").await.unwrap().as_deref(), Some("a"));
+        broker.set("owner", "b", minute).await.unwrap();
+        assert_eq!(broker.get("owner").await.unwrap().as_deref(), Some("b"));
+        assert!(broker.renew("owner", "b", minute).await.unwrap());
+        assert!(!broker.renew("owner", "a", minute).await.unwrap());
+        assert_eq!(broker.get("owner").await.unwrap().as_deref(), Some("b"));
+        assert!(broker.delete("owner").await.unwrap());
+        assert!(!broker.delete("owner").await.unwrap());
+        assert!(!broker.renew("owner", "b", minute).await.unwrap());
+        assert_eq!(broker.get("owner").await.unwrap(), None);
+
+        broker
+            .set("short", "lived", Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert!(broker
+            .renew("short", "lived", Duration::from_millis(150))
+            .await
+            .unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(broker.get("short").await.unwrap().as_deref(), Some("lived"));
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(broker.get("short").await.unwrap(), None);
+        assert!(broker
+            .set_if_absent("short", "again", minute)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{Broker, Result, Subscription};
+
+/// Connects the servers of one process. Used by default, when a server runs
+/// on its own, and in tests.
+#[derive(Default)]
+pub struct LocalBroker {
+    topics: Mutex<HashMap<String, Vec<mpsc::UnboundedSender<String>>>>,
+    keys: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl LocalBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The value of `key` unless it has expired, dropping it if it has.
+fn live_value(keys: &mut HashMap<String, (String, Instant)>, key: &str) -> Option<String> {
+    match keys.get(key) {
+        Some((value, expires)) if *expires > Instant::now() => Some(value.clone()),
+        Some(_) => {
+            keys.remove(key);
+            None
+        }
+        None => None,
+    }
+}
+
+#[async_trait]
+impl Broker for LocalBroker {
+    async fn publish(&self, topic: &str, payload: &str) -> Result<()> {
+        let mut topics = self.topics.lock().unwrap();
+        if let Some(subscribers) = topics.get_mut(topic) {
+            subscribers.retain(|tx| tx.send(payload.to_owned()).is_ok());
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.topics
+            .lock()
+            .unwrap()
+            .entry(topic.to_owned())
+            .or_default()
+            .push(tx);
+        Ok(rx)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()> {
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), (value.to_owned(), Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool> {
+        let mut keys = self.keys.lock().unwrap();
+        if live_value(&mut keys, key).is_some() {
+            return Ok(false);
+        }
+        keys.insert(key.to_owned(), (value.to_owned(), Instant::now() + ttl));
+        Ok(true)
+    }
+
+    async fn renew(&self, key: &str, value: &str, ttl: Duration) -> Result<bool> {
+        let mut keys = self.keys.lock().unwrap();
+        if live_value(&mut keys, key).as_deref() != Some(value) {
+            return Ok(false);
+        }
+        keys.insert(key.to_owned(), (value.to_owned(), Instant::now() + ttl));
+        Ok(true)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(live_value(&mut self.keys.lock().unwrap(), key))
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool> {
+        let mut keys = self.keys.lock().unwrap();
+        let live = live_value(&mut keys, key).is_some();
+        keys.remove(key);
+        Ok(live)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract;
+
+    #[tokio::test]
+    async fn messages_reach_every_subscriber_in_order() {
+        contract::messages_reach_every_subscriber_in_order(&LocalBroker::new()).await;
+    }
+
+    #[tokio::test]
+    async fn keys_expire_and_claims_are_exclusive() {
+        contract::keys_expire_and_claims_are_exclusive(&LocalBroker::new()).await;
+    }
+}
@@ -0,0 +1,449 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{Broker, BrokerError, Result, Subscription};
+
+const DEFAULT_PORT: u16 = 6379;
+
+/// How long connecting, or a command and its reply, may take by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resets the expiry of `KEYS[1]` to `ARGV[2]` milliseconds if it holds
+/// `ARGV[1]`, in one step.
+pub(crate) const RENEW_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('PEXPIRE', KEYS[1], ARGV[2]) else return 0 end";
+
+/// Talks to a Redis-compatible server over RESP2.
+///
+/// Commands share one connection that is opened on first use and re-opened
+/// after an error; every subscription gets a connection of its own. A
+/// server that does not answer in time counts as an error too, so that a
+/// hung server cannot stall the rooms waiting on it. Keys are used as-is,
+/// so give the cluster a database of its own if the server is shared.
+pub struct RedisBroker {
+    endpoint: Endpoint,
+    commands: Mutex<Option<BufStream<TcpStream>>>,
+    timeout: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Endpoint {
+    addr: String,
+    user: Option<String>,
+    password: Option<String>,
+    database: Option<u32>,
+}
+
+impl RedisBroker {
+    /// A broker for `redis://[[user]:password@]host[:port][/database]`.
+    /// Nothing is connected until the broker is first used.
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(RedisBroker {
+            endpoint: Endpoint::parse(url)?,
+            commands: Mutex::default(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Gives up on connecting, or on a command, after `timeout` rather than
+    /// the default 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs `step` against the broker, failing once it takes too long.
+    async fn in_time<T>(&self, step: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        tokio::time::timeout(self.timeout, step)
+            .await
+            .unwrap_or(Err(BrokerError::TimedOut(self.timeout)))
+    }
+
+    /// Opens a connection, logging in and selecting the database.
+    async fn connect(&self) -> Result<BufStream<TcpStream>> {
+        let mut conn = BufStream::new(TcpStream::connect(&self.endpoint.addr).await?);
+        if let Some(password) = &self.endpoint.password {
+            let mut args = vec!["AUTH".as_bytes()];
+            if let Some(user) = &self.endpoint.user {
+                args.push(user.as_bytes());
+            }
+            args.push(password.as_bytes());
+            call(&mut conn, &args).await?.ok()?;
+        }
+        if let Some(database) = self.endpoint.database {
+            call(&mut conn, &[b"SELECT", database.to_string().as_bytes()])
+                .await?
+                .ok()?;
+        }
+        Ok(conn)
+    }
+
+    /// Runs one command on the shared connection. An I/O error or a
+    /// timeout drops the connection so that the next command starts afresh.
+    async fn command(&self, args: &[&[u8]]) -> Result<Reply> {
+        let mut commands = self.commands.lock().await;
+        let result = self
+            .in_time(async {
+                if commands.is_none() {
+                    *commands = Some(self.connect().await?);
+                }
+                let conn = commands.as_mut().expect("connected above");
+                call(conn, args).await
+            })
+            .await;
+        match result {
+            Ok(reply) => reply.ok(),
+            Err(err) => {
+                *commands = None;
+                Err(err)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Broker for RedisBroker {
+    async fn publish(&self, topic: &str, payload: &str) -> Result<()> {
+        self.command(&[b"PUBLISH", topic.as_bytes(), payload.as_bytes()])
+            .await?;
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription> {
+        let mut conn = self
+            .in_time(async {
+                let mut conn = self.connect().await?;
+                call(&mut conn, &[b"SUBSCRIBE", topic.as_bytes()])
+                    .await?
+                    .ok()?;
+                Ok(conn)
+            })
+            .await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let topic = topic.to_owned();
+        tokio::spawn(async move {
+            loop {
+                let reply = tokio::select! {
+                    reply = read_reply(&mut conn) => reply,
+                    _ = tx.closed() => return,
+                };
+                match reply.and_then(Reply::into_message) {
+                    Ok(Some(payload)) => {
+                        if tx.send(payload).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::warn!(%topic, "subscription ended: {err}");
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()> {
+        let ms = ttl.as_millis().max(1).to_string();
+        self.command(&[
+            b"SET",
+            key.as_bytes(),
+            value.as_bytes(),
+            b"PX",
+            ms.as_bytes(),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool> {
+        let ms = ttl.as_millis().max(1).to_string();
+        let reply = self
+            .command(&[
+                b"SET",
+                key.as_bytes(),
+                value.as_bytes(),
+                b"NX",
+                b"PX",
+                ms.as_bytes(),
+            ])
+            .await?;
+        Ok(!matches!(reply, Reply::Bulk(None)))
+    }
+
+    async fn renew(&self, key: &str, value: &str, ttl: Duration) -> Result<bool> {
+        let ms = ttl.as_millis().max(1).to_string();
+        let reply = self
+            .command(&[
+                b"EVAL",
+                RENEW_SCRIPT.as_bytes(),
+                b"1",
+                key.as_bytes(),
+                value.as_bytes(),
+                ms.as_bytes(),
+            ])
+            .await?;
+        match reply {
+            Reply::Integer(renewed) => Ok(renewed > 0),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        match self.command(&[b"GET", key.as_bytes()]).await? {
+            Reply::Bulk(value) => value.map(utf8).transpose(),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool> {
+        match self.command(&[b"DEL", key.as_bytes()]).await? {
+            Reply::Integer(deleted) => Ok(deleted > 0),
+            other => Err(unexpected(&other)),
+        }
+    }
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self> {
+        let invalid = || BrokerError::InvalidUrl(url.to_owned());
+        let rest = url.strip_prefix("redis://").ok_or_else(invalid)?;
+        let (credentials, rest) = match rest.rsplit_once('@') {
+            Some((credentials, rest)) => (Some(credentials), rest),
+            None => (None, rest),
+        };
+        let (host, database) = match rest.split_once('/') {
+            Some((host, "")) => (host, None),
+            Some((host, db)) => (host, Some(db.parse().map_err(|_| invalid())?)),
+            None => (rest, None),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let addr = match host.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => host.to_owned(),
+            _ => format!("{host}:{DEFAULT_PORT}"),
+        };
+        let (user, password) = match credentials.map(|c| c.split_once(':')) {
+            Some(Some((user, password))) => (
+                Some(user.to_owned()).filter(|u| !u.is_empty()),
+                Some(password.to_owned()),
+            ),
+            Some(None) => (None, credentials.map(str::to_owned)),
+            None => (None, None),
+        };
+        Ok(Endpoint {
+            addr,
+            user,
+            password,
+            database,
+        })
+    }
+}
+
+/// A RESP2 value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Reply {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    /// Turns an error reply into an error.
+    fn ok(self) -> Result<Self> {
+        match self {
+            Reply::Error(message) => Err(BrokerError::Refused(message)),
+            reply => Ok(reply),
+        }
+    }
+
+    /// The payload of a pub/sub `message` push; `None` for other pushes.
+    fn into_message(self) -> Result<Option<String>> {
+        let Reply::Array(Some(mut items)) = self else {
+            return Err(unexpected(&self));
+        };
+        match items.as_slice() {
+            [Reply::Bulk(Some(kind)), _, Reply::Bulk(Some(_))] if kind == b"message" => {
+                let Some(Reply::Bulk(Some(payload))) = items.pop() else {
+                    unreachable!("matched above");
+                };
+                utf8(payload).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(|_| BrokerError::Protocol("value is not UTF-8".into()))
+}
+
+fn unexpected(reply: &Reply) -> BrokerError {
+    BrokerError::Protocol(format!("unexpected reply {reply:?}"))
+}
+
+/// Sends a command and reads its reply.
+async fn call(conn: &mut BufStream<TcpStream>, args: &[&[u8]]) -> Result<Reply> {
+    conn.write_all(&encode_command(args)).await?;
+    conn.flush().await?;
+    read_reply(conn).await
+}
+
+/// Encodes a command as an array of bulk strings.
+pub(crate) fn encode_command(args: &[&[u8]]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+pub(crate) async fn read_reply<R: AsyncBufRead + Unpin + Send>(reader: &mut R) -> Result<Reply> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).await?;
+    if line.is_empty() {
+        return Err(BrokerError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    let line = line
+        .strip_suffix(b"\r\n")
+        .ok_or_else(|| BrokerError::Protocol("line does not end in CRLF".into()))?;
+    let (&kind, rest) = line
+        .split_first()
+        .ok_or_else(|| BrokerError::Protocol("empty line".into()))?;
+    let text = || utf8(rest.to_vec());
+    let number = || {
+        text()?
+            .parse::<i64>()
+            .map_err(|_| BrokerError::Protocol("malformed number".into()))
+    };
+    Ok(match kind {
+        b'+' => Reply::Simple(text()?),
+        b'-' => Reply::Error(text()?),
+        b':' => Reply::Integer(number()?),
+        b'$' => match usize::try_from(number()?) {
+            Ok(len) => {
+                let mut data = vec![0; len + 2];
+                reader.read_exact(&mut data).await?;
+                data.truncate(len);
+                Reply::Bulk(Some(data))
+            }
+            Err(_) => Reply::Bulk(None),
+        },
+        b'*' => match usize::try_from(number()?) {
+            Ok(len) => {
+                let mut items = Vec::with_capacity(len.min(64));
+                for _ in 0..len {
+                    items.push(Box::pin(read_reply(reader)).await?);
+                }
+                Reply::Array(Some(items))
+            }
+            Err(_) => Reply::Array(None),
+        },
+        other => {
+            return Err(BrokerError::Protocol(format!(
+                "unknown reply type {:?}",
+                other as char
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract;
+    use crate::stub::StubRedis;
+
+    #[test]
+    fn parses_urls() {
+        let endpoint = Endpoint::parse("redis://:s3cret@cache.internal/2").unwrap();
+        assert_eq!(
+            endpoint,
+            Endpoint {
+                addr: "cache.internal:6379".into(),
+                user: None,
+                password: Some("s3cret".into()),
+                database: Some(2),
+            }
+        );
+        let endpoint = Endpoint::parse("redis://poker:pw@10.0.0.5:6380").unwrap();
+        assert_eq!(endpoint.addr, "10.0.0.5:6380");
+        assert_eq!(endpoint.user.as_deref(), Some("poker"));
+        for bad in ["http://cache", "redis://", "redis://cache/db"] {
+            assert!(matches!(
+                Endpoint::parse(bad),
+                Err(BrokerError::InvalidUrl(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_nested_replies() {
+        let mut input: &[u8] = b"*3\r\n$7\r\nmessage\r\n$-1\r\n*1\r\n:42\r\n";
+        assert_eq!(
+            read_reply(&mut input).await.unwrap(),
+            Reply::Array(Some(vec![
+                Reply::Bulk(Some(b"message".to_vec())),
+                Reply::Bulk(None),
+                Reply::Array(Some(vec![Reply::Integer(42)])),
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn messages_reach_every_subscriber_in_order() {
+        let server = StubRedis::start().await;
+        let broker = RedisBroker::new(&server.url()).unwrap();
+        contract::messages_reach_every_subscriber_in_order(&broker).await;
+    }
+
+    #[tokio::test]
+    async fn keys_expire_and_claims_are_exclusive() {
+        let server = StubRedis::start().await;
+        let broker = RedisBroker::new(&server.url()).unwrap();
+        contract::keys_expire_and_claims_are_exclusive(&broker).await;
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_server_drops_the_connection() {
+        let server = StubRedis::start().await;
+        let broker = RedisBroker::new(&server.url()).unwrap();
+        broker.set("k", "v", Duration::from_secs(60)).await.unwrap();
+        server.drop_connections();
+        // The first command notices the broken connection; the next one
+        // reconnects.
+        let _ = broker.get("k").await;
+        assert_eq!(broker.get("k").await.unwrap().as_deref(), Some("v"));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_server_that_stops_answering() {
+        let server = StubRedis::start().await;
+        let timeout = Duration::from_millis(100);
+        let broker = RedisBroker::new(&server.url())
+            .unwrap()
+            .with_timeout(timeout);
+        broker.set("k", "v", Duration::from_secs(60)).await.unwrap();
+        server.stop_answering(true);
+        assert!(matches!(
+            broker.get("k").await,
+            Err(BrokerError::TimedOut(t)) if t == timeout
+        ));
+        assert!(matches!(
+            broker.subscribe("rooms").await,
+            Err(BrokerError::TimedOut(_))
+        ));
+        server.stop_answering(false);
+        assert_eq!(broker.get("k").await.unwrap().as_deref(), Some("v"));
+    }
+}
@@ -0,0 +1,243 @@
+//! A server speaking just enough of the Redis protocol for a
+//! [`RedisBroker`](crate::RedisBroker): `PING`, `AUTH`, `SELECT`, `GET`,
+//! `SET` (with `NX`, `PX` and `EX`), `DEL`, `PUBLISH`, `SUBSCRIBE`, and
+//! `EVAL` of the script the broker renews keys with.
+//! Only meant for tests.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+
+use crate::redis::{encode_command, read_reply, Reply, RENEW_SCRIPT};
+
+/// A running stub server. It stops when the runtime does.
+pub struct StubRedis {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+/// Stored values with their expiry, if any.
+type Keys = HashMap<Vec<u8>, (Vec<u8>, Option<Instant>)>;
+
+/// Connections subscribed to each channel.
+type Channels = HashMap<Vec<u8>, Vec<mpsc::UnboundedSender<Vec<u8>>>>;
+
+struct Shared {
+    keys: Mutex<Keys>,
+    channels: Mutex<Channels>,
+    /// Bumped to make every open connection close.
+    closing: watch::Sender<u64>,
+    /// Set while commands go unanswered, as on a server that hangs.
+    stalled: AtomicBool,
+}
+
+impl StubRedis {
+    /// Serves on a free localhost port.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shared = Arc::new(Shared {
+            keys: Mutex::default(),
+            channels: Mutex::default(),
+            closing: watch::Sender::new(0),
+            stalled: AtomicBool::new(false),
+        });
+        tokio::spawn({
+            let shared = shared.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve_connection(stream, shared.clone()));
+                }
+            }
+        });
+        StubRedis { addr, shared }
+    }
+
+    pub fn url(&self) -> String {
+        format!("redis://{}", self.addr)
+    }
+
+    /// Closes every open connection, as a restarting server would.
+    pub fn drop_connections(&self) {
+        self.shared
+            .closing
+            .send_modify(|generation| *generation += 1);
+        self.shared.channels.lock().unwrap().clear();
+    }
+
+    /// Leaves commands unanswered until called again with `false`.
+    pub fn stop_answering(&self, stalled: bool) {
+        self.shared.stalled.store(stalled, Ordering::Relaxed);
+    }
+}
+
+async fn serve_connection(stream: TcpStream, shared: Arc<Shared>) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    // Replies and pushed messages share one writer so they never interleave.
+    let mut closing = shared.closing.subscribe();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                out = rx.recv() => match out {
+                    Some(out) if write.write_all(&out).await.is_ok() => {}
+                    _ => return,
+                },
+                _ = closing.changed() => return,
+            }
+        }
+    });
+    let mut closing = shared.closing.subscribe();
+    loop {
+        let command = tokio::select! {
+            command = read_reply(&mut reader) => command,
+            _ = closing.changed() => return,
+        };
+        let Ok(Reply::Array(Some(items))) = command else {
+            return;
+        };
+        let args: Vec<Vec<u8>> = items
+            .into_iter()
+            .filter_map(|item| match item {
+                Reply::Bulk(Some(arg)) => Some(arg),
+                _ => None,
+            })
+            .collect();
+        if shared.stalled.load(Ordering::Relaxed) {
+            continue;
+        }
+        let reply = shared.execute(&args, &tx);
+        if tx.send(reply).is_err() {
+            return;
+        }
+    }
+}
+
+impl Shared {
+    fn execute(&self, args: &[Vec<u8>], conn: &mpsc::UnboundedSender<Vec<u8>>) -> Vec<u8> {
+        let Some((name, args)) = args.split_first() else {
+            return error("empty command");
+        };
+        match (name.to_ascii_uppercase().as_slice(), args) {
+            (b"PING", _) => b"+PONG\r\n".to_vec(),
+            (b"AUTH" | b"SELECT", _) => b"+OK\r\n".to_vec(),
+            (b"GET", [key]) => bulk(self.get(key).as_deref()),
+            (b"SET", [key, value, options @ ..]) => self.set(key, value, options),
+            (b"DEL", keys) => {
+                let mut stored = self.keys.lock().unwrap();
+                let now = Instant::now();
+                let deleted = keys
+                    .iter()
+                    .filter_map(|key| stored.remove(key))
+                    .filter(|(_, expires)| expires.is_none_or(|e| e > now))
+                    .count();
+                format!(":{deleted}\r\n").into_bytes()
+            }
+            (b"EVAL", [script, keys, key, value, ms])
+                if script == RENEW_SCRIPT.as_bytes() && keys == b"1" =>
+            {
+                let Some(ms) = std::str::from_utf8(ms).ok().and_then(|ms| ms.parse().ok()) else {
+                    return error("value is not an integer or out of range");
+                };
+                if self.get(key).as_deref() != Some(value.as_slice()) {
+                    return b":0\r\n".to_vec();
+                }
+                let expires = Instant::now() + Duration::from_millis(ms);
+                self.keys
+                    .lock()
+                    .unwrap()
+                    .insert(key.clone(), (value.clone(), Some(expires)));
+                b":1\r\n".to_vec()
+            }
+            (b"PUBLISH", [channel, payload]) => {
+                let push = encode_command(&[b"message", channel, payload]);
+                let mut channels = self.channels.lock().unwrap();
+                let subscribers = channels.entry(channel.clone()).or_default();
+                subscribers.retain(|tx| tx.send(push.clone()).is_ok());
+                format!(":{}\r\n", subscribers.len()).into_bytes()
+            }
+            (b"SUBSCRIBE", [channel]) => {
+                self.channels
+                    .lock()
+                    .unwrap()
+                    .entry(channel.clone())
+                    .or_default()
+                    .push(conn.clone());
+                let mut reply = b"*3\r\n$9\r\nsubscribe\r\n".to_vec();
+                reply.extend_from_slice(&bulk(Some(channel)));
+                reply.extend_from_slice(b":1\r\n");
+                reply
+            }
+            _ => error("unknown command or wrong number of arguments"),
+        }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut keys = self.keys.lock().unwrap();
+        match keys.get(key) {
+            Some((_, Some(expires))) if *expires <= Instant::now() => {
+                keys.remove(key);
+                None
+            }
+            Some((value, _)) => Some(value.clone()),
+            None => None,
+        }
+    }
+
+    fn set(&self, key: &[u8], value: &[u8], options: &[Vec<u8>]) -> Vec<u8> {
+        let mut only_if_absent = false;
+        let mut ttl = None;
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let option = option.to_ascii_uppercase();
+            let unit = match option.as_slice() {
+                b"NX" => {
+                    only_if_absent = true;
+                    continue;
+                }
+                b"PX" => Duration::from_millis(1),
+                b"EX" => Duration::from_secs(1),
+                _ => return error("syntax error"),
+            };
+            let amount = options
+                .next()
+                .and_then(|n| std::str::from_utf8(n).ok()?.parse::<u32>().ok());
+            match amount {
+                Some(amount) => ttl = Some(unit * amount),
+                None => return error("value is not an integer or out of range"),
+            }
+        }
+        if only_if_absent && self.get(key).is_some() {
+            return bulk(None);
+        }
+        let expires = ttl.map(|ttl| Instant::now() + ttl);
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(key.to_vec(), (value.to_vec(), expires));
+        b"+OK\r\n".to_vec()
+    }
+}
+
+fn bulk(value: Option<&[u8]>) -> Vec<u8> {
+    match value {
+        Some(value) => {
+            let mut out = format!("${}\r\n", value.len()).into_bytes();
+            out.extend_from_slice(value);
+            out.extend_from_slice(b"\r\n");
+            out
+        }
+        None => b"$-1\r\n".to_vec(),
+    }
+}
+
+fn error(message: &str) -> Vec<u8> {
+    format!("-ERR {message}\r\n").into_bytes()
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A card a voter can play, identified by the label printed on it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Card(String);
+
+impl Card {
+    pub fn new(label: impl Into<String>) -> Self {
+        Card(label.into())
+    }
+
+    pub fn label(&self) -> &str {
+        &self.0
+    }
+
+    /// The numeric value of the card, or `None` for cards such as `?` or
+    /// `XL` that don't stand for a number.
+    pub fn value(&self) -> Option<f64> {
+        match self.0.trim() {
+            "½" => Some(0.5),
+            label => label.parse::<f64>().ok().filter(|v| v.is_finite()),
+        }
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Card {
+    fn from(label: &str) -> Self {
+        Card::new(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_labels_have_values() {
+        assert_eq!(Card::new("13").value(), Some(13.0));
+        assert_eq!(Card::new("0.5").value(), Some(0.5));
+        assert_eq!(Card::new("½").value(), Some(0.5));
+    }
+
+    #[test]
+    fn other_labels_have_no_value() {
+        assert_eq!(Card::new("?").value(), None);
+        assert_eq!(Card::new("XL").value(), None);
+        assert_eq!(Card::new("inf").value(), None);
+        assert_eq!(Card::new("NaN").value(), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::error::{Error, Result};
+
+const FIBONACCI: &[&str] = &[
+    "0", "½", "1", "2", "3", "5", "8", "13", "20", "40", "100", "?", "☕",
+];
+const T_SHIRT: &[&str] = &["XS", "S", "M", "L", "XL", "XXL", "?", "☕"];
+const POWERS_OF_TWO: &[&str] = &["0", "1", "2", "4", "8", "16", "32", "64", "?", "☕"];
+
+/// How a deck is described by clients and in storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeckSpec {
+    /// Modified Fibonacci: 0, ½, 1, 2, 3, 5, 8, 13, 20, 40, 100.
+    Fibonacci,
+    /// XS to XXL.
+    TShirt,
+    /// 0, 1, 2, 4, 8 up to 64.
+    PowersOfTwo,
+    /// Any list of distinct labels, in the order they should be shown.
+    Custom { cards: Vec<String> },
+}
+
+/// The cards a room votes with.
+///
+/// Every preset also carries the special `?` and `☕` cards. A deck is
+/// numeric when all of its other cards have a [`Card::value`]; only numeric
+/// decks get averages and medians in their [`Summary`](crate::Summary).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "DeckSpec", into = "DeckSpec")]
+pub struct Deck {
+    spec: DeckSpec,
+    cards: Vec<Card>,
+}
+
+impl Deck {
+    pub fn fibonacci() -> Self {
+        Deck::preset(DeckSpec::Fibonacci, FIBONACCI)
+    }
+
+    pub fn t_shirt() -> Self {
+        Deck::preset(DeckSpec::TShirt, T_SHIRT)
+    }
+
+    pub fn powers_of_two() -> Self {
+        Deck::preset(DeckSpec::PowersOfTwo, POWERS_OF_TWO)
+    }
+
+    /// Builds a deck from arbitrary labels, rejecting blank or repeated ones.
+    pub fn custom<I, S>(labels: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let labels: Vec<String> = labels
+            .into_iter()
+            .map(|l| l.into().trim().to_owned())
+            .collect();
+        if labels.is_empty() {
+            return Err(Error::EmptyDeck);
+        }
+        let mut seen = HashSet::new();
+        for label in &labels {
+            if label.is_empty() {
+                return Err(Error::EmptyCard);
+            }
+            if !seen.insert(label.as_str()) {
+                return Err(Error::DuplicateCard(label.clone()));
+            }
+        }
+        let cards = labels.iter().map(Card::new).collect();
+        Ok(Deck {
+            spec: DeckSpec::Custom { cards: labels },
+            cards,
+        })
+    }
+
+    fn preset(spec: DeckSpec, labels: &[&str]) -> Self {
+        Deck {
+            spec,
+            cards: labels.iter().copied().map(Card::new).collect(),
+        }
+    }
+
+    pub fn spec(&self) -> &DeckSpec {
+        &self.spec
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn contains(&self, card: &Card) -> bool {
+        self.cards.contains(card)
+    }
+
+    /// Position of a card in the deck, used to order distributions.
+    pub(crate) fn position(&self, card: &Card) -> Option<usize> {
+        self.cards.iter().position(|c| c == card)
+    }
+
+    /// Whether every card apart from `?`-style specials has a numeric value.
+    pub fn is_numeric(&self) -> bool {
+        let mut numeric = self.cards.iter().filter(|c| c.value().is_some());
+        numeric.next().is_some()
+            && self
+                .cards
+                .iter()
+                .all(|c| c.value().is_some() || is_special(c))
+    }
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Deck::fibonacci()
+    }
+}
+
+impl TryFrom<DeckSpec> for Deck {
+    type Error = Error;
+
+    fn try_from(spec: DeckSpec) -> Result<Self> {
+        match spec {
+            DeckSpec::Fibonacci => Ok(Deck::fibonacci()),
+            DeckSpec::TShirt => Ok(Deck::t_shirt()),
+            DeckSpec::PowersOfTwo => Ok(Deck::powers_of_two()),
+            DeckSpec::Custom { cards } => Deck::custom(cards),
+        }
+    }
+}
+
+impl From<Deck> for DeckSpec {
+    fn from(deck: Deck) -> Self {
+        deck.spec
+    }
+}
+
+/// Cards that express something other than a size, like "no idea" or
+/// "I need a break". They don't make a deck non-numeric.
+fn is_special(card: &Card) -> bool {
+    let label = card.label();
+    label == "?" || label == "☕" || label.eq_ignore_ascii_case("coffee") || label == "∞"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_numeric_except_t_shirt() {
+        assert!(Deck::fibonacci().is_numeric());
+        assert!(Deck::powers_of_two().is_numeric());
+        assert!(!Deck::t_shirt().is_numeric());
+    }
+
+    #[test]
+    fn presets_include_special_cards() {
+        for deck in [Deck::fibonacci(), Deck::t_shirt(), Deck::powers_of_two()] {
+            assert!(deck.contains(&Card::new("?")));
+            assert!(deck.contains(&Card::new("☕")));
+        }
+    }
+
+    #[test]
+    fn custom_deck_keeps_order_and_trims() {
+        let deck = Deck::custom([" 1", "2 ", "coffee", "?"]).unwrap();
+        let labels: Vec<_> = deck.cards().iter().map(Card::label).collect();
+        assert_eq!(labels, ["1", "2", "coffee", "?"]);
+        assert!(deck.is_numeric());
+    }
+
+    #[test]
+    fn custom_deck_with_words_is_not_numeric() {
+        let deck = Deck::custom(["small", "1", "?"]).unwrap();
+        assert!(!deck.is_numeric());
+        let specials_only = Deck::custom(["?", "coffee"]).unwrap();
+        assert!(!specials_only.is_numeric());
+    }
+
+    #[test]
+    fn custom_deck_is_validated() {
+        assert_eq!(Deck::custom(Vec::<String>::new()), Err(Error::EmptyDeck));
+        assert_eq!(Deck::custom(["1", " "]), Err(Error::EmptyCard));
+        assert_eq!(
+            Deck::custom(["1", "2", "1 "]),
+            Err(Error::DuplicateCard("1".into()))
+        );
+    }
+
+    #[test]
+    fn deck_round_trips_through_spec() {
+        let json = serde_json::to_string(&Deck::t_shirt()).unwrap();
+        assert_eq!(json, r#"{"kind":"t_shirt"}"#);
+        let deck: Deck = serde_json::from_str(r#"{"kind":"custom","cards":["a","b"]}"#).unwrap();
+        assert_eq!(deck, Deck::custom(["a", "b"]).unwrap());
+        assert!(serde_json::from_str::<Deck>(r#"{"kind":"custom","cards":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::card::Card;
+use crate::participant::ParticipantId;
+use crate::permission::Action;
+use crate::story::StoryId;
+
+/// Reasons a room transition can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("participant {0} is not in this room")]
+    UnknownParticipant(ParticipantId),
+    #[error("participant name must not be empty")]
+    EmptyName,
+    #[error("card label must not be empty")]
+    EmptyCard,
+    #[error("a deck needs at least one card")]
+    EmptyDeck,
+    #[error("card {0:?} appears more than once in the deck")]
+    DuplicateCard(String),
+    #[error("card {0} is not in this room's deck")]
+    CardNotInDeck(Card),
+    #[error("observers cannot vote")]
+    ObserverCannotVote,
+    #[error("the round has already been revealed")]
+    RoundRevealed,
+    #[error("the round has not been revealed yet")]
+    RoundNotRevealed,
+    #[error("there are no votes to reveal")]
+    NoVotes,
+    #[error("participant {0} has not voted")]
+    NoVoteCast(ParticipantId),
+    #[error("only the facilitator can {0}")]
+    NotFacilitator(Action),
+    #[error("use leave to remove yourself")]
+    CannotKickSelf,
+    #[error("story title must not be empty")]
+    EmptyStory,
+    #[error("story {0} is not in the backlog")]
+    UnknownStory(StoryId),
+    #[error("the current round is not about a backlog story")]
+    NoCurrentStory,
+    #[error("every story in the backlog has been estimated")]
+    BacklogDone,
+    #[error("a round timer must run for 1 second to 60 minutes")]
+    InvalidTimer,
+    #[error("say how long the round timer should run; this room has no default")]
+    NoTimerLength,
+    #[error("no round timer is running")]
+    NoTimer,
+    #[error("voting can be opened for 1 minute to 14 days")]
+    InvalidVotingPeriod,
+    #[error("a quorum needs at least one vote")]
+    InvalidQuorum,
+    #[error("template name must not be empty")]
+    EmptyTemplateName,
+    #[error("{0} is listed more than once")]
+    DuplicateMember(String),
+    #[error("{0} is not on this room's team")]
+    UnknownMember(String),
+    #[error("{0} already has a seat; resume it with its token")]
+    MemberSeated(String),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
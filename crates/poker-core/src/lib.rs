@@ -0,0 +1,47 @@
+//! Domain model for OpenPlanningPoker.
+//!
+//! A [`Room`] holds the [`Participant`]s of an estimation session, the
+//! current [`Round`] and a backlog of [`Story`]s with their agreed estimates
+//! and round history. Every state transition goes through a `Room` method,
+//! which validates it against the round's [`Phase`], the participant's
+//! [`Role`] and, for moderation [`Action`]s, whether they are the room's
+//! facilitator before mutating anything. Votes are checked against the room's
+//! [`Deck`], and a revealed round is described by a [`Summary`]. Accepted
+//! transitions are recorded as [`Event`]s that a transport can drain and fan
+//! out to clients.
+//!
+//! Rounds can also be left open for a [`VotingWindow`] of hours or days, for
+//! teams that estimate asynchronously.
+//!
+//! A [`Template`] saves a room's configuration and team so that the next
+//! room can be started from it.
+
+mod card;
+mod deck;
+mod error;
+mod participant;
+mod permission;
+mod room;
+mod round;
+mod settings;
+mod story;
+mod summary;
+mod template;
+mod time;
+mod timer;
+mod voting;
+
+pub use card::Card;
+pub use deck::{Deck, DeckSpec};
+pub use error::{Error, Result};
+pub use participant::{Participant, ParticipantId, Presence, Role};
+pub use permission::Action;
+pub use room::{Event, Room, RoomId};
+pub use round::{Phase, Round};
+pub use settings::{Settings, TimerExpiry};
+pub use story::{NewStory, RecordedVote, RoundResult, Story, StoryId};
+pub use summary::{CardCount, NumericSummary, Summary};
+pub use template::{Member, Template};
+pub use time::{Clock, ManualClock, SystemClock, Timestamp};
+pub use timer::Timer;
+pub use voting::{VotingClosed, VotingWindow};
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a participant within a single room.
+///
+/// Ids are allocated by the room in join order and are never reused, so a
+/// stale id held by a client can't accidentally address a newer participant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ParticipantId(pub u64);
+
+impl fmt::Display for ParticipantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// How a participant takes part in the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Estimates stories by casting votes.
+    Voter,
+    /// Follows the session without voting, e.g. a product owner.
+    Observer,
+}
+
+impl Role {
+    pub fn can_vote(self) -> bool {
+        matches!(self, Role::Voter)
+    }
+}
+
+/// Whether a participant's client is currently reachable.
+///
+/// Presence is reported by the transport; the room only records it so that
+/// everyone sees a seat go quiet instead of disappearing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    Connected,
+    /// Still connected, but nothing has been heard from the client lately.
+    Idle,
+    /// Disconnected; the seat is held in case the client comes back.
+    Gone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Participant {
+    pub id: ParticipantId,
+    pub name: String,
+    pub role: Role,
+    pub presence: Presence,
+}
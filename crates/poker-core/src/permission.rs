@@ -0,0 +1,43 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Room actions reserved for the facilitator.
+///
+/// Checked by [`Room`](crate::Room) itself so that every transport enforces
+/// the same rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Reveal,
+    Reset,
+    StartRound,
+    Kick,
+    ChangeDeck,
+    ChangeSettings,
+    HandOver,
+    ManageStories,
+    RecordEstimate,
+    ManageTimer,
+    OpenVoting,
+    ManageWebhooks,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Reveal => "reveal the votes",
+            Action::Reset => "reset the round",
+            Action::StartRound => "start a new round",
+            Action::Kick => "remove participants",
+            Action::ChangeDeck => "change the deck",
+            Action::ChangeSettings => "change room settings",
+            Action::HandOver => "hand over moderation",
+            Action::ManageStories => "manage the story backlog",
+            Action::RecordEstimate => "record the final estimate",
+            Action::ManageTimer => "run the round timer",
+            Action::OpenVoting => "open voting for a period",
+            Action::ManageWebhooks => "manage the room's webhooks",
+        })
+    }
+}
@@ -0,0 +1,2055 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::deck::Deck;
+use crate::error::{Error, Result};
+use crate::participant::{Participant, ParticipantId, Presence, Role};
+use crate::permission::Action;
+use crate::round::{Phase, Round};
+use crate::settings::{Settings, TimerExpiry};
+use crate::story::{NewStory, RecordedVote, RoundResult, Story, StoryId};
+use crate::summary::Summary;
+use crate::template::{Member, Template};
+use crate::time::Timestamp;
+use crate::timer::Timer;
+use crate::voting::{VotingClosed, VotingWindow};
+
+/// Public identifier of a room, e.g. the code people share to join it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RoomId(String);
+
+impl RoomId {
+    pub fn new(id: impl Into<String>) -> Self {
+        RoomId(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RoomId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Something that happened in a room, in the order it happened.
+///
+/// Events never carry hidden information: a vote cast before reveal is
+/// reported as [`Event::VoteCast`] without its card.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    ParticipantJoined(Participant),
+    ParticipantLeft(ParticipantId),
+    /// The facilitator removed a participant from the room.
+    ParticipantKicked(ParticipantId),
+    FacilitatorChanged(ParticipantId),
+    RoleChanged(ParticipantId, Role),
+    PresenceChanged(ParticipantId, Presence),
+    VoteCast(ParticipantId),
+    VoteRetracted(ParticipantId),
+    /// The round was revealed. `votes` is empty in an anonymous room.
+    Revealed {
+        votes: BTreeMap<ParticipantId, Card>,
+        summary: Summary,
+    },
+    RoundReset,
+    /// A new round opened, on a backlog story if `story_id` is set.
+    RoundStarted {
+        story: Option<String>,
+        story_id: Option<StoryId>,
+    },
+    StoriesAdded(Vec<Story>),
+    StoryRemoved(StoryId),
+    EstimateRecorded {
+        story: StoryId,
+        estimate: Card,
+    },
+    TimerStarted(Timer),
+    /// The facilitator stopped the timer, or the round it was timing ended.
+    TimerStopped,
+    /// The timer ran out. Followed by [`Event::Revealed`] when the room
+    /// reveals on expiry and there were votes.
+    TimerExpired,
+    /// The deck was replaced; the round's votes were discarded with it.
+    DeckChanged(Deck),
+    SettingsChanged(Settings),
+    VotingOpened(VotingWindow),
+    /// The voting window on the round about `story` closed. Followed by
+    /// [`Event::Revealed`] when it closed on quorum, or at the deadline with
+    /// votes cast.
+    VotingClosed {
+        reason: VotingClosed,
+        story: Option<String>,
+    },
+}
+
+/// A planning-poker session.
+///
+/// Serializes to its full state, hidden votes included, for storage. Pending
+/// events are not part of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Room {
+    id: RoomId,
+    name: String,
+    deck: Deck,
+    settings: Settings,
+    participants: BTreeMap<ParticipantId, Participant>,
+    facilitator: Option<ParticipantId>,
+    round: Round,
+    next_participant: u64,
+    /// The backlog, in the order it is worked through.
+    #[serde(default)]
+    stories: Vec<Story>,
+    /// The backlog story the current round estimates, if any.
+    #[serde(default)]
+    current_story: Option<StoryId>,
+    #[serde(default)]
+    next_story: u64,
+    /// Countdown on the current round, if one is running.
+    #[serde(default)]
+    timer: Option<Timer>,
+    /// Period the current round stays open for votes, if it was opened for one.
+    #[serde(default)]
+    voting: Option<VotingWindow>,
+    /// Name of the participant who moderates, from the room's template.
+    #[serde(default)]
+    moderator: Option<String>,
+    /// The team the room was set up for, from its template.
+    #[serde(default)]
+    members: Vec<Member>,
+    /// Name of the template the room was started from. Rooms started from
+    /// the same template belong to the same team.
+    #[serde(default)]
+    team: Option<String>,
+    /// Participants seated as members of the team, by the name the template
+    /// lists them under.
+    #[serde(default)]
+    seated_members: BTreeMap<ParticipantId, String>,
+    #[serde(skip)]
+    events: Vec<Event>,
+}
+
+impl Room {
+    pub fn new(id: RoomId, name: impl Into<String>, deck: Deck) -> Self {
+        Room {
+            id,
+            name: name.into(),
+            deck,
+            settings: Settings::default(),
+            participants: BTreeMap::new(),
+            facilitator: None,
+            round: Round::new(None),
+            next_participant: 1,
+            stories: Vec::new(),
+            current_story: None,
+            next_story: 1,
+            timer: None,
+            voting: None,
+            moderator: None,
+            members: Vec::new(),
+            team: None,
+            seated_members: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// A room set up the way `template` describes.
+    pub fn from_template(id: RoomId, name: impl Into<String>, template: &Template) -> Self {
+        Room {
+            settings: template.settings.clone(),
+            moderator: template.moderator.clone(),
+            members: template.members.clone(),
+            team: Some(template.name.clone()),
+            ..Room::new(id, name, template.deck.clone())
+        }
+    }
+
+    pub fn id(&self) -> &RoomId {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn deck(&self) -> &Deck {
+        &self.deck
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The participant who moderates the room. The first to join becomes
+    /// facilitator; when they leave, the longest-seated participant takes over.
+    /// A [`moderator`](Self::moderator) takes over whenever they join.
+    pub fn facilitator(&self) -> Option<ParticipantId> {
+        self.facilitator
+    }
+
+    /// Name of the member meant to moderate, if the room's template names
+    /// one.
+    pub fn moderator(&self) -> Option<&str> {
+        self.moderator.as_deref()
+    }
+
+    /// The team the room was set up for. Members seated through
+    /// [`join_member`](Self::join_member) take the role listed for them.
+    pub fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    /// The team whose sprints the room is one of, if it was started from a
+    /// template.
+    pub fn team(&self) -> Option<&str> {
+        self.team.as_deref()
+    }
+
+    pub fn is_facilitator(&self, id: ParticipantId) -> bool {
+        self.facilitator == Some(id)
+    }
+
+    pub fn round(&self) -> &Round {
+        &self.round
+    }
+
+    /// Who played which card in the revealed round, unless the room is
+    /// anonymous.
+    pub fn visible_votes(&self) -> Option<&BTreeMap<ParticipantId, Card>> {
+        if self.settings.anonymous {
+            return None;
+        }
+        self.round.revealed_votes()
+    }
+
+    /// Statistics for the current round, available once it is revealed.
+    pub fn summary(&self) -> Option<Summary> {
+        if !self.round.is_revealed() {
+            return None;
+        }
+        Some(Summary::new(&self.deck, self.round.votes().values()))
+    }
+
+    /// The backlog in order, estimated stories included.
+    pub fn stories(&self) -> &[Story] {
+        &self.stories
+    }
+
+    pub fn story(&self, id: StoryId) -> Option<&Story> {
+        self.stories.iter().find(|s| s.id == id)
+    }
+
+    /// The backlog story being estimated, if the round is about one.
+    pub fn current_story(&self) -> Option<&Story> {
+        self.story(self.current_story?)
+    }
+
+    /// The countdown on the current round, if one is running.
+    pub fn timer(&self) -> Option<&Timer> {
+        self.timer.as_ref()
+    }
+
+    /// The period the current round is open for votes, if it was opened for
+    /// one.
+    pub fn voting(&self) -> Option<&VotingWindow> {
+        self.voting.as_ref()
+    }
+
+    pub fn participant(&self, id: ParticipantId) -> Option<&Participant> {
+        self.participants.get(&id)
+    }
+
+    pub fn participants(&self) -> impl Iterator<Item = &Participant> {
+        self.participants.values()
+    }
+
+    /// Marks everyone as [`Presence::Gone`] without recording events, for a
+    /// room restored from storage before any client has reconnected.
+    pub fn disconnect_all(&mut self) {
+        for participant in self.participants.values_mut() {
+            participant.presence = Presence::Gone;
+        }
+    }
+
+    /// Takes the events recorded since the last call.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Seats a guest under the name and role they asked for.
+    pub fn join(&mut self, name: &str, role: Role) -> Result<ParticipantId> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::EmptyName);
+        }
+        Ok(self.seat(name.to_owned(), role, false))
+    }
+
+    /// Seats the member of the room's team called `member`, with the role
+    /// the template lists for them. Whoever the template names as moderator
+    /// takes over as facilitator. The caller vouches that the joiner is that
+    /// member; names typed into [`join`](Self::join) never count. A member
+    /// has one seat at a time.
+    pub fn join_member(&mut self, member: &str) -> Result<ParticipantId> {
+        let member = member.trim();
+        let listed = self
+            .members
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(member))
+            .map(|m| (m.name.clone(), m.role));
+        let moderates = self.is_moderator(member);
+        let (name, role) = match (listed, &self.moderator) {
+            (Some(listed), _) => listed,
+            (None, Some(moderator)) if moderates => (moderator.clone(), Role::Voter),
+            _ => return Err(Error::UnknownMember(member.to_owned())),
+        };
+        if self.seated_members.values().any(|seated| *seated == name) {
+            return Err(Error::MemberSeated(name));
+        }
+        let id = self.seat(name.clone(), role, moderates);
+        self.seated_members.insert(id, name);
+        Ok(id)
+    }
+
+    /// Everyone on the room's team, the moderator included, in display
+    /// order.
+    pub fn invitees(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.members.iter().map(|m| m.name.as_str()).collect();
+        if let Some(moderator) = &self.moderator {
+            if !names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(moderator))
+            {
+                names.insert(0, moderator);
+            }
+        }
+        names
+    }
+
+    fn seat(&mut self, name: String, role: Role, moderates: bool) -> ParticipantId {
+        let id = ParticipantId(self.next_participant);
+        self.next_participant += 1;
+        let participant = Participant {
+            id,
+            name,
+            role,
+            presence: Presence::Connected,
+        };
+        self.participants.insert(id, participant.clone());
+        self.events.push(Event::ParticipantJoined(participant));
+        if self.facilitator.is_none() || (moderates && !self.facilitator_is_moderator()) {
+            self.facilitator = Some(id);
+            self.events.push(Event::FacilitatorChanged(id));
+        }
+        id
+    }
+
+    fn is_moderator(&self, name: &str) -> bool {
+        self.moderator
+            .as_deref()
+            .is_some_and(|moderator| moderator.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the facilitator was seated as the template's moderator.
+    fn facilitator_is_moderator(&self) -> bool {
+        self.facilitator
+            .and_then(|id| self.seated_members.get(&id))
+            .is_some_and(|name| self.is_moderator(name))
+    }
+
+    /// Removes a participant. A hidden vote leaves with them; a revealed vote
+    /// stays part of the round's result.
+    pub fn leave(&mut self, id: ParticipantId, now: Timestamp) -> Result<()> {
+        self.remove(id)?;
+        self.events.push(Event::ParticipantLeft(id));
+        self.after_departure(id, now);
+        Ok(())
+    }
+
+    /// Removes another participant from the room.
+    pub fn kick(
+        &mut self,
+        actor: ParticipantId,
+        target: ParticipantId,
+        now: Timestamp,
+    ) -> Result<()> {
+        self.authorize(actor, Action::Kick)?;
+        if actor == target {
+            return Err(Error::CannotKickSelf);
+        }
+        self.remove(target)?;
+        self.events.push(Event::ParticipantKicked(target));
+        self.after_departure(target, now);
+        Ok(())
+    }
+
+    fn remove(&mut self, id: ParticipantId) -> Result<()> {
+        self.participants
+            .remove(&id)
+            .ok_or(Error::UnknownParticipant(id))?;
+        self.seated_members.remove(&id);
+        if !self.round.is_revealed() {
+            self.round.votes_mut().remove(&id);
+        }
+        Ok(())
+    }
+
+    fn after_departure(&mut self, id: ParticipantId, now: Timestamp) {
+        if self.facilitator == Some(id) {
+            self.facilitator = self.participants.keys().next().copied();
+            if let Some(next) = self.facilitator {
+                self.events.push(Event::FacilitatorChanged(next));
+            }
+        }
+        self.auto_reveal_if_complete(now);
+        self.close_voting_on_quorum(now);
+    }
+
+    /// Passes moderation to another participant.
+    pub fn hand_over(&mut self, actor: ParticipantId, to: ParticipantId) -> Result<()> {
+        self.authorize(actor, Action::HandOver)?;
+        if !self.participants.contains_key(&to) {
+            return Err(Error::UnknownParticipant(to));
+        }
+        if actor != to {
+            self.facilitator = Some(to);
+            self.events.push(Event::FacilitatorChanged(to));
+        }
+        Ok(())
+    }
+
+    /// Switches a participant between voting and observing. Becoming an
+    /// observer withdraws any hidden vote.
+    pub fn set_role(&mut self, id: ParticipantId, role: Role, now: Timestamp) -> Result<()> {
+        let participant = self
+            .participants
+            .get_mut(&id)
+            .ok_or(Error::UnknownParticipant(id))?;
+        if participant.role == role {
+            return Ok(());
+        }
+        participant.role = role;
+        self.events.push(Event::RoleChanged(id, role));
+        if !role.can_vote()
+            && !self.round.is_revealed()
+            && self.round.votes_mut().remove(&id).is_some()
+        {
+            self.events.push(Event::VoteRetracted(id));
+        }
+        self.auto_reveal_if_complete(now);
+        self.close_voting_on_quorum(now);
+        Ok(())
+    }
+
+    /// Records whether a participant's client is reachable. Their seat and any
+    /// hidden vote are kept whatever the presence.
+    pub fn set_presence(
+        &mut self,
+        id: ParticipantId,
+        presence: Presence,
+        now: Timestamp,
+    ) -> Result<()> {
+        let participant = self
+            .participants
+            .get_mut(&id)
+            .ok_or(Error::UnknownParticipant(id))?;
+        if participant.presence != presence {
+            participant.presence = presence;
+            self.events.push(Event::PresenceChanged(id, presence));
+            self.auto_reveal_if_complete(now);
+        }
+        Ok(())
+    }
+
+    /// Casts or changes a hidden vote. The card must be in the room's deck.
+    pub fn vote(&mut self, id: ParticipantId, card: Card, now: Timestamp) -> Result<()> {
+        let participant = self
+            .participants
+            .get(&id)
+            .ok_or(Error::UnknownParticipant(id))?;
+        if !participant.role.can_vote() {
+            return Err(Error::ObserverCannotVote);
+        }
+        if self.round.is_revealed() {
+            return Err(Error::RoundRevealed);
+        }
+        if !self.deck.contains(&card) {
+            return Err(Error::CardNotInDeck(card));
+        }
+        self.round.votes_mut().insert(id, card);
+        self.events.push(Event::VoteCast(id));
+        self.auto_reveal_if_complete(now);
+        self.close_voting_on_quorum(now);
+        Ok(())
+    }
+
+    pub fn retract_vote(&mut self, id: ParticipantId) -> Result<()> {
+        if !self.participants.contains_key(&id) {
+            return Err(Error::UnknownParticipant(id));
+        }
+        if self.round.is_revealed() {
+            return Err(Error::RoundRevealed);
+        }
+        self.round
+            .votes_mut()
+            .remove(&id)
+            .ok_or(Error::NoVoteCast(id))?;
+        self.events.push(Event::VoteRetracted(id));
+        Ok(())
+    }
+
+    /// Makes all votes visible and locks them.
+    pub fn reveal(&mut self, actor: ParticipantId, now: Timestamp) -> Result<()> {
+        self.authorize(actor, Action::Reveal)?;
+        if self.round.is_revealed() {
+            return Err(Error::RoundRevealed);
+        }
+        if self.round.votes().is_empty() {
+            return Err(Error::NoVotes);
+        }
+        self.reveal_now(now);
+        Ok(())
+    }
+
+    /// Reveals the round at `now`. In an anonymous room the event, and the
+    /// result kept on the story, carry the summary alone.
+    fn reveal_now(&mut self, now: Timestamp) {
+        self.clear_timer();
+        self.clear_voting();
+        self.round.set_phase(Phase::Revealed);
+        let summary = Summary::new(&self.deck, self.round.votes().values());
+        let votes = if self.settings.anonymous {
+            self.round.set_anonymous();
+            BTreeMap::new()
+        } else {
+            self.round.votes().clone()
+        };
+        if let Some(id) = self.current_story {
+            let result = RoundResult {
+                revealed_at: now,
+                votes: self.recorded_votes(&votes),
+                summary: summary.clone(),
+            };
+            if let Some(story) = self.stories.iter_mut().find(|s| s.id == id) {
+                story.rounds.push(result);
+            }
+        }
+        self.events.push(Event::Revealed { votes, summary });
+    }
+
+    /// Pairs votes with the names of the participants who cast them.
+    pub fn recorded_votes(&self, votes: &BTreeMap<ParticipantId, Card>) -> Vec<RecordedVote> {
+        votes
+            .iter()
+            .map(|(&participant, card)| RecordedVote {
+                participant,
+                name: self
+                    .participants
+                    .get(&participant)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default(),
+                card: card.clone(),
+            })
+            .collect()
+    }
+
+    /// Reveals on behalf of the facilitator once auto-reveal is on and no
+    /// connected voter is still thinking.
+    /// Does nothing while a voting window is open; it has its own quorum.
+    fn auto_reveal_if_complete(&mut self, now: Timestamp) {
+        if !self.settings.auto_reveal
+            || self.voting.is_some()
+            || self.round.is_revealed()
+            || self.round.votes().is_empty()
+        {
+            return;
+        }
+        let waiting = self.participants.values().any(|p| {
+            p.role.can_vote() && p.presence != Presence::Gone && !self.round.has_voted(p.id)
+        });
+        if !waiting {
+            self.reveal_now(now);
+        }
+    }
+
+    /// Discards the votes and re-opens voting on the same story.
+    pub fn reset(&mut self, actor: ParticipantId) -> Result<()> {
+        self.authorize(actor, Action::Reset)?;
+        self.clear_timer();
+        self.clear_voting();
+        self.round = Round::new(self.round.story().map(str::to_owned));
+        self.events.push(Event::RoundReset);
+        Ok(())
+    }
+
+    /// Starts estimating an ad-hoc story that is not on the backlog,
+    /// discarding the current round.
+    pub fn start_round(&mut self, actor: ParticipantId, story: Option<String>) -> Result<()> {
+        self.authorize(actor, Action::StartRound)?;
+        let story = story.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+        self.clear_timer();
+        self.clear_voting();
+        self.round = Round::new(story.clone());
+        self.current_story = None;
+        self.events.push(Event::RoundStarted {
+            story,
+            story_id: None,
+        });
+        Ok(())
+    }
+
+    /// Appends stories to the end of the backlog.
+    pub fn add_stories(
+        &mut self,
+        actor: ParticipantId,
+        stories: Vec<NewStory>,
+    ) -> Result<Vec<StoryId>> {
+        self.authorize(actor, Action::ManageStories)?;
+        let stories: Vec<_> = stories
+            .into_iter()
+            .map(|new| NewStory {
+                title: new.title.trim().to_owned(),
+                key: new
+                    .key
+                    .map(|k| k.trim().to_owned())
+                    .filter(|k| !k.is_empty()),
+            })
+            .collect();
+        if stories.iter().any(|s| s.title.is_empty()) {
+            return Err(Error::EmptyStory);
+        }
+        let added: Vec<_> = stories
+            .into_iter()
+            .map(|new| {
+                let id = StoryId(self.next_story);
+                self.next_story += 1;
+                Story::new(id, new)
+            })
+            .collect();
+        self.stories.extend(added.iter().cloned());
+        let ids = added.iter().map(|s| s.id).collect();
+        self.events.push(Event::StoriesAdded(added));
+        Ok(ids)
+    }
+
+    /// Drops a story from the backlog. A round already running on it carries
+    /// on as an ad-hoc round.
+    pub fn remove_story(&mut self, actor: ParticipantId, id: StoryId) -> Result<()> {
+        self.authorize(actor, Action::ManageStories)?;
+        let index = self
+            .stories
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(Error::UnknownStory(id))?;
+        self.stories.remove(index);
+        if self.current_story == Some(id) {
+            self.current_story = None;
+        }
+        self.events.push(Event::StoryRemoved(id));
+        Ok(())
+    }
+
+    /// Starts a fresh round on a backlog story, estimated or not.
+    pub fn start_story(&mut self, actor: ParticipantId, id: StoryId) -> Result<()> {
+        self.authorize(actor, Action::StartRound)?;
+        let title = self.story(id).ok_or(Error::UnknownStory(id))?.title.clone();
+        self.clear_timer();
+        self.clear_voting();
+        self.round = Round::new(Some(title.clone()));
+        self.current_story = Some(id);
+        self.events.push(Event::RoundStarted {
+            story: Some(title),
+            story_id: Some(id),
+        });
+        Ok(())
+    }
+
+    /// Moves on to the first unestimated story after the current one,
+    /// wrapping around to any that were skipped.
+    pub fn next_story(&mut self, actor: ParticipantId) -> Result<StoryId> {
+        self.authorize(actor, Action::StartRound)?;
+        let after = self
+            .current_story
+            .and_then(|id| self.stories.iter().position(|s| s.id == id))
+            .map_or(0, |i| i + 1);
+        let (earlier, later) = self.stories.split_at(after.min(self.stories.len()));
+        let next = later
+            .iter()
+            .chain(earlier)
+            .find(|s| !s.is_estimated())
+            .ok_or(Error::BacklogDone)?
+            .id;
+        self.start_story(actor, next)?;
+        Ok(next)
+    }
+
+    /// Records the agreed estimate for the story of the revealed round.
+    /// Recording again overwrites the previous estimate.
+    pub fn record_estimate(
+        &mut self,
+        actor: ParticipantId,
+        estimate: Card,
+        now: Timestamp,
+    ) -> Result<()> {
+        self.authorize(actor, Action::RecordEstimate)?;
+        if !self.round.is_revealed() {
+            return Err(Error::RoundNotRevealed);
+        }
+        if !self.deck.contains(&estimate) {
+            return Err(Error::CardNotInDeck(estimate));
+        }
+        let id = self.current_story.ok_or(Error::NoCurrentStory)?;
+        let story = self
+            .stories
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or(Error::NoCurrentStory)?;
+        story.estimate = Some(estimate.clone());
+        story.estimated_at = Some(now);
+        self.events.push(Event::EstimateRecorded {
+            story: id,
+            estimate,
+        });
+        Ok(())
+    }
+
+    /// Replaces the deck. Votes cast with the old deck are discarded and
+    /// voting re-opens on the same story.
+    pub fn set_deck(&mut self, actor: ParticipantId, deck: Deck) -> Result<()> {
+        self.authorize(actor, Action::ChangeDeck)?;
+        self.deck = deck.clone();
+        self.clear_timer();
+        self.clear_voting();
+        self.round = Round::new(self.round.story().map(str::to_owned));
+        self.events.push(Event::DeckChanged(deck));
+        Ok(())
+    }
+
+    pub fn update_settings(
+        &mut self,
+        actor: ParticipantId,
+        settings: Settings,
+        now: Timestamp,
+    ) -> Result<()> {
+        self.authorize(actor, Action::ChangeSettings)?;
+        settings.validate()?;
+        if self.settings != settings {
+            self.settings = settings.clone();
+            self.events.push(Event::SettingsChanged(settings));
+            self.auto_reveal_if_complete(now);
+        }
+        Ok(())
+    }
+
+    /// Starts a countdown on the current round, replacing any running one.
+    /// Without a `duration`, it runs for the room's default length. Returns
+    /// the deadline.
+    pub fn start_timer(
+        &mut self,
+        actor: ParticipantId,
+        duration: Option<Duration>,
+        now: Timestamp,
+    ) -> Result<Timestamp> {
+        self.authorize(actor, Action::ManageTimer)?;
+        let duration = duration
+            .or(self.settings.timer())
+            .ok_or(Error::NoTimerLength)?;
+        if !(Timer::MIN_DURATION..=Timer::MAX_DURATION).contains(&duration) {
+            return Err(Error::InvalidTimer);
+        }
+        if self.round.is_revealed() {
+            return Err(Error::RoundRevealed);
+        }
+        let timer = Timer::new(now, duration);
+        self.timer = Some(timer);
+        self.events.push(Event::TimerStarted(timer));
+        Ok(timer.deadline)
+    }
+
+    pub fn stop_timer(&mut self, actor: ParticipantId) -> Result<()> {
+        self.authorize(actor, Action::ManageTimer)?;
+        if self.timer.is_none() {
+            return Err(Error::NoTimer);
+        }
+        self.clear_timer();
+        Ok(())
+    }
+
+    /// Ends the running timer if its deadline has passed at `now`, then
+    /// either reveals or only notifies according to the room's settings.
+    /// Returns whether the timer expired.
+    pub fn expire_timer(&mut self, now: Timestamp) -> bool {
+        if !self.timer.is_some_and(|t| t.is_expired(now)) {
+            return false;
+        }
+        self.timer = None;
+        self.events.push(Event::TimerExpired);
+        if self.settings.on_timer_expiry == TimerExpiry::Reveal && !self.round.votes().is_empty() {
+            self.reveal_now(now);
+        }
+        true
+    }
+
+    /// Drops the running timer, e.g. because the round it was timing is over.
+    fn clear_timer(&mut self) {
+        if self.timer.take().is_some() {
+            self.events.push(Event::TimerStopped);
+        }
+    }
+
+    /// Leaves the current round open for votes for `duration`, or until
+    /// `quorum` votes are in. Replaces any window already open. Returns the
+    /// deadline.
+    pub fn open_voting(
+        &mut self,
+        actor: ParticipantId,
+        duration: Duration,
+        quorum: Option<u32>,
+        now: Timestamp,
+    ) -> Result<Timestamp> {
+        self.authorize(actor, Action::OpenVoting)?;
+        if !(VotingWindow::MIN_DURATION..=VotingWindow::MAX_DURATION).contains(&duration) {
+            return Err(Error::InvalidVotingPeriod);
+        }
+        if quorum == Some(0) {
+            return Err(Error::InvalidQuorum);
+        }
+        if self.round.is_revealed() {
+            return Err(Error::RoundRevealed);
+        }
+        let window = VotingWindow::new(now, duration, quorum);
+        self.voting = Some(window);
+        self.events.push(Event::VotingOpened(window));
+        self.close_voting_on_quorum(now);
+        Ok(window.deadline)
+    }
+
+    /// Closes the voting window if its deadline has passed at `now`,
+    /// revealing the votes cast so far, if there are any. Returns whether
+    /// the window closed.
+    pub fn close_voting(&mut self, now: Timestamp) -> bool {
+        if !self.voting.is_some_and(|w| w.is_over(now)) {
+            return false;
+        }
+        self.end_voting(VotingClosed::Deadline);
+        if !self.round.votes().is_empty() {
+            self.reveal_now(now);
+        }
+        true
+    }
+
+    /// Reveals once the open window's quorum is reached.
+    fn close_voting_on_quorum(&mut self, now: Timestamp) {
+        let Some(window) = self.voting else {
+            return;
+        };
+        let votes = self.round.votes().len();
+        let reached = match window.quorum {
+            Some(quorum) => votes >= quorum as usize,
+            None => {
+                votes > 0
+                    && self
+                        .participants
+                        .values()
+                        .all(|p| !p.role.can_vote() || self.round.has_voted(p.id))
+            }
+        };
+        if reached {
+            self.end_voting(VotingClosed::Quorum);
+            self.reveal_now(now);
+        }
+    }
+
+    /// Drops the voting window, e.g. because the facilitator revealed early.
+    fn clear_voting(&mut self) {
+        if self.voting.is_some() {
+            self.end_voting(VotingClosed::Facilitator);
+        }
+    }
+
+    fn end_voting(&mut self, reason: VotingClosed) {
+        self.voting = None;
+        self.events.push(Event::VotingClosed {
+            reason,
+            story: self.round.story().map(str::to_owned),
+        });
+    }
+
+    /// Checks that `actor` may perform a moderation action.
+    pub fn authorize(&self, actor: ParticipantId, action: Action) -> Result<()> {
+        if !self.participants.contains_key(&actor) {
+            return Err(Error::UnknownParticipant(actor));
+        }
+        if !self.is_facilitator(actor) {
+            return Err(Error::NotFacilitator(action));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: Timestamp = Timestamp(1_000);
+
+    fn room() -> Room {
+        Room::new(RoomId::new("r1"), "Sprint 42", Deck::fibonacci())
+    }
+
+    fn card(label: &str) -> Card {
+        Card::new(label)
+    }
+
+    #[test]
+    fn join_allocates_distinct_ids_and_records_event() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("  Bob ", Role::Observer).unwrap();
+        assert_ne!(alice, bob);
+        assert_eq!(room.participant(bob).unwrap().name, "Bob");
+        assert_eq!(
+            room.drain_events(),
+            vec![
+                Event::ParticipantJoined(Participant {
+                    id: alice,
+                    name: "Alice".into(),
+                    role: Role::Voter,
+                    presence: Presence::Connected,
+                }),
+                Event::FacilitatorChanged(alice),
+                Event::ParticipantJoined(Participant {
+                    id: bob,
+                    name: "Bob".into(),
+                    role: Role::Observer,
+                    presence: Presence::Connected,
+                }),
+            ]
+        );
+        assert!(room.drain_events().is_empty());
+    }
+
+    #[test]
+    fn join_rejects_blank_name() {
+        let mut room = room();
+        assert_eq!(room.join("   ", Role::Voter), Err(Error::EmptyName));
+        assert!(room.drain_events().is_empty());
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_leave() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.leave(alice, NOW).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        assert_ne!(alice, bob);
+    }
+
+    #[test]
+    fn leave_drops_hidden_vote() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.leave(alice, NOW).unwrap();
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(room.drain_events(), vec![Event::ParticipantLeft(alice)]);
+        assert_eq!(
+            room.leave(alice, NOW),
+            Err(Error::UnknownParticipant(alice))
+        );
+    }
+
+    #[test]
+    fn leave_keeps_revealed_vote() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.leave(alice, NOW).unwrap();
+        assert_eq!(room.round().revealed_votes().unwrap()[&alice], card("5"));
+    }
+
+    #[test]
+    fn vote_is_hidden_until_reveal() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.drain_events();
+        room.vote(alice, card("3"), NOW).unwrap();
+        assert!(room.round().has_voted(alice));
+        assert_eq!(room.round().revealed_votes(), None);
+        assert_eq!(room.drain_events(), vec![Event::VoteCast(alice)]);
+    }
+
+    #[test]
+    fn vote_can_change_before_reveal() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.vote(alice, card("8"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        assert_eq!(room.round().revealed_votes().unwrap()[&alice], card("8"));
+    }
+
+    #[test]
+    fn observer_cannot_vote() {
+        let mut room = room();
+        let olga = room.join("Olga", Role::Observer).unwrap();
+        assert_eq!(
+            room.vote(olga, card("3"), NOW),
+            Err(Error::ObserverCannotVote)
+        );
+        assert!(!room.round().has_voted(olga));
+    }
+
+    #[test]
+    fn unknown_participant_cannot_vote() {
+        let mut room = room();
+        let ghost = ParticipantId(99);
+        assert_eq!(
+            room.vote(ghost, card("3"), NOW),
+            Err(Error::UnknownParticipant(ghost))
+        );
+    }
+
+    #[test]
+    fn card_outside_deck_is_rejected() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        assert_eq!(
+            room.vote(alice, card("4"), NOW),
+            Err(Error::CardNotInDeck(card("4")))
+        );
+        assert_eq!(
+            room.vote(alice, card(" "), NOW),
+            Err(Error::CardNotInDeck(card(" ")))
+        );
+        assert!(!room.round().has_voted(alice));
+    }
+
+    #[test]
+    fn votes_follow_the_rooms_deck() {
+        let mut room = Room::new(RoomId::new("r2"), "Sizing", Deck::t_shirt());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        assert_eq!(
+            room.vote(alice, card("5"), NOW),
+            Err(Error::CardNotInDeck(card("5")))
+        );
+        room.vote(alice, card("XL"), NOW).unwrap();
+    }
+
+    #[test]
+    fn vote_cannot_change_after_reveal() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        assert_eq!(room.vote(alice, card("8"), NOW), Err(Error::RoundRevealed));
+        assert_eq!(room.vote(bob, card("8"), NOW), Err(Error::RoundRevealed));
+        assert_eq!(room.retract_vote(alice), Err(Error::RoundRevealed));
+        assert_eq!(room.round().revealed_votes().unwrap()[&alice], card("3"));
+    }
+
+    #[test]
+    fn retract_vote_before_reveal() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        assert_eq!(room.retract_vote(alice), Err(Error::NoVoteCast(alice)));
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.drain_events();
+        room.retract_vote(alice).unwrap();
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(room.drain_events(), vec![Event::VoteRetracted(alice)]);
+    }
+
+    #[test]
+    fn summary_is_hidden_until_reveal() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("?"), NOW).unwrap();
+        assert_eq!(room.summary(), None);
+        room.reveal(alice, NOW).unwrap();
+        assert_eq!(room.summary().unwrap().vote_count(), 1);
+    }
+
+    #[test]
+    fn reveal_requires_votes() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        assert_eq!(room.reveal(alice, NOW), Err(Error::NoVotes));
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    #[test]
+    fn reveal_publishes_votes_once() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.vote(bob, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.reveal(alice, NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Revealed);
+        let votes = BTreeMap::from([(alice, card("3")), (bob, card("5"))]);
+        let summary = room.summary().unwrap();
+        assert_eq!(summary.numeric.as_ref().unwrap().average, 4.0);
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::Revealed { votes, summary }]
+        );
+        assert_eq!(room.reveal(alice, NOW), Err(Error::RoundRevealed));
+    }
+
+    #[test]
+    fn reset_reopens_voting_on_same_story() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.start_round(alice, Some("Login page".into())).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.drain_events();
+        room.reset(alice).unwrap();
+        assert_eq!(room.round().phase(), Phase::Voting);
+        assert_eq!(room.round().story(), Some("Login page"));
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(room.drain_events(), vec![Event::RoundReset]);
+        room.vote(alice, card("5"), NOW).unwrap();
+    }
+
+    #[test]
+    fn reset_during_voting_clears_votes() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.reset(alice).unwrap();
+        assert_eq!(room.round().voters().count(), 0);
+    }
+
+    #[test]
+    fn start_round_replaces_story_and_votes() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.drain_events();
+        room.start_round(alice, Some(" Checkout ".into())).unwrap();
+        assert_eq!(room.round().story(), Some("Checkout"));
+        assert_eq!(room.round().phase(), Phase::Voting);
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::RoundStarted {
+                story: Some("Checkout".into()),
+                story_id: None
+            }]
+        );
+        room.start_round(alice, Some("  ".into())).unwrap();
+        assert_eq!(room.round().story(), None);
+    }
+
+    #[test]
+    fn becoming_observer_withdraws_hidden_vote() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.drain_events();
+        room.set_role(alice, Role::Observer, NOW).unwrap();
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(
+            room.drain_events(),
+            vec![
+                Event::RoleChanged(alice, Role::Observer),
+                Event::VoteRetracted(alice)
+            ]
+        );
+        assert_eq!(
+            room.vote(alice, card("3"), NOW),
+            Err(Error::ObserverCannotVote)
+        );
+    }
+
+    #[test]
+    fn observer_can_become_voter() {
+        let mut room = room();
+        let olga = room.join("Olga", Role::Observer).unwrap();
+        room.drain_events();
+        room.set_role(olga, Role::Voter, NOW).unwrap();
+        room.set_role(olga, Role::Voter, NOW).unwrap();
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::RoleChanged(olga, Role::Voter)]
+        );
+        room.vote(olga, card("1"), NOW).unwrap();
+    }
+
+    #[test]
+    fn presence_changes_keep_seat_and_hidden_vote() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("8"), NOW).unwrap();
+        room.drain_events();
+        room.set_presence(alice, Presence::Gone, NOW).unwrap();
+        room.set_presence(alice, Presence::Gone, NOW).unwrap();
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::PresenceChanged(alice, Presence::Gone)]
+        );
+        assert_eq!(room.participant(alice).unwrap().presence, Presence::Gone);
+        assert_eq!(room.round().vote_of(alice), Some(&card("8")));
+        room.set_presence(alice, Presence::Connected, NOW).unwrap();
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::PresenceChanged(alice, Presence::Connected)]
+        );
+    }
+
+    #[test]
+    fn presence_of_unknown_participant_fails() {
+        let mut room = room();
+        let ghost = ParticipantId(3);
+        assert_eq!(
+            room.set_presence(ghost, Presence::Idle, NOW),
+            Err(Error::UnknownParticipant(ghost))
+        );
+    }
+
+    #[test]
+    fn set_role_of_unknown_participant_fails() {
+        let mut room = room();
+        let ghost = ParticipantId(7);
+        assert_eq!(
+            room.set_role(ghost, Role::Voter, NOW),
+            Err(Error::UnknownParticipant(ghost))
+        );
+    }
+
+    #[test]
+    fn first_participant_becomes_facilitator() {
+        let mut room = room();
+        assert_eq!(room.facilitator(), None);
+        let alice = room.join("Alice", Role::Observer).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        assert!(room.is_facilitator(alice));
+        assert!(!room.is_facilitator(bob));
+    }
+
+    #[test]
+    fn template_rooms_know_their_team() {
+        let template = Template {
+            name: "Team".into(),
+            deck: Deck::t_shirt(),
+            settings: Settings {
+                anonymous: true,
+                ..Settings::default()
+            },
+            moderator: Some("Carol".into()),
+            members: vec![Member {
+                name: "Pat".into(),
+                role: Role::Observer,
+            }],
+        };
+        let mut room = Room::from_template(RoomId::new("r1"), "Sprint 43", &template);
+        assert_eq!(room.deck(), &Deck::t_shirt());
+        assert!(room.settings().anonymous);
+        assert_eq!(room.team(), Some("Team"));
+
+        assert_eq!(room.invitees(), ["Carol", "Pat"]);
+
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        // Typing a member's name gets a guest seat, not theirs.
+        let guest = room.join("pat", Role::Voter).unwrap();
+        assert_eq!(room.participant(guest).unwrap().role, Role::Voter);
+        let pat = room.join_member("pat").unwrap();
+        assert_eq!(room.participant(pat).unwrap().name, "Pat");
+        assert_eq!(room.participant(pat).unwrap().role, Role::Observer);
+        room.join("Carol", Role::Voter).unwrap();
+        assert!(room.is_facilitator(alice));
+        assert_eq!(
+            room.join_member("Mallory"),
+            Err(Error::UnknownMember("Mallory".into()))
+        );
+        room.drain_events();
+
+        // The moderator takes over when they arrive, from anyone but
+        // themselves.
+        let carol = room.join_member("Carol").unwrap();
+        assert!(room.is_facilitator(carol));
+        assert!(room
+            .drain_events()
+            .contains(&Event::FacilitatorChanged(carol)));
+        // Nor can a member take a second seat, e.g. by using their invite
+        // twice.
+        assert_eq!(
+            room.join_member("carol"),
+            Err(Error::MemberSeated("Carol".into()))
+        );
+        assert!(room.is_facilitator(carol));
+        assert_eq!(room.participants().count(), 5);
+        room.leave(carol, NOW).unwrap();
+        room.join_member("Carol").unwrap();
+    }
+
+    #[test]
+    fn only_facilitator_may_moderate() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.vote(bob, card("3"), NOW).unwrap();
+        room.drain_events();
+
+        assert_eq!(
+            room.reveal(bob, NOW),
+            Err(Error::NotFacilitator(Action::Reveal))
+        );
+        assert_eq!(room.reset(bob), Err(Error::NotFacilitator(Action::Reset)));
+        assert_eq!(
+            room.start_round(bob, None),
+            Err(Error::NotFacilitator(Action::StartRound))
+        );
+        assert_eq!(
+            room.kick(bob, alice, NOW),
+            Err(Error::NotFacilitator(Action::Kick))
+        );
+        assert_eq!(
+            room.set_deck(bob, Deck::t_shirt()),
+            Err(Error::NotFacilitator(Action::ChangeDeck))
+        );
+        assert_eq!(
+            room.update_settings(
+                bob,
+                Settings {
+                    auto_reveal: true,
+                    ..Settings::default()
+                },
+                NOW
+            ),
+            Err(Error::NotFacilitator(Action::ChangeSettings))
+        );
+        assert_eq!(
+            room.hand_over(bob, bob),
+            Err(Error::NotFacilitator(Action::HandOver))
+        );
+        assert!(room.drain_events().is_empty());
+        assert_eq!(room.round().phase(), Phase::Voting);
+        assert!(room.round().has_voted(bob));
+    }
+
+    #[test]
+    fn unknown_actor_is_rejected_before_permission_check() {
+        let mut room = room();
+        room.join("Alice", Role::Voter).unwrap();
+        let ghost = ParticipantId(42);
+        assert_eq!(
+            room.reveal(ghost, NOW),
+            Err(Error::UnknownParticipant(ghost))
+        );
+    }
+
+    #[test]
+    fn facilitator_kicks_participant() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.vote(bob, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.kick(alice, bob, NOW).unwrap();
+        assert!(room.participant(bob).is_none());
+        assert!(!room.round().has_voted(bob));
+        assert_eq!(room.drain_events(), vec![Event::ParticipantKicked(bob)]);
+        assert_eq!(
+            room.kick(alice, bob, NOW),
+            Err(Error::UnknownParticipant(bob))
+        );
+        assert_eq!(room.kick(alice, alice, NOW), Err(Error::CannotKickSelf));
+    }
+
+    #[test]
+    fn hand_over_moves_moderation() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Observer).unwrap();
+        room.drain_events();
+        room.hand_over(alice, bob).unwrap();
+        assert!(room.is_facilitator(bob));
+        assert_eq!(room.drain_events(), vec![Event::FacilitatorChanged(bob)]);
+        assert_eq!(
+            room.reveal(alice, NOW),
+            Err(Error::NotFacilitator(Action::Reveal))
+        );
+        let ghost = ParticipantId(9);
+        assert_eq!(
+            room.hand_over(bob, ghost),
+            Err(Error::UnknownParticipant(ghost))
+        );
+    }
+
+    #[test]
+    fn facilitator_leaving_passes_moderation_on() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        let carol = room.join("Carol", Role::Voter).unwrap();
+        room.drain_events();
+        room.leave(alice, NOW).unwrap();
+        assert!(room.is_facilitator(bob));
+        assert_eq!(
+            room.drain_events(),
+            vec![
+                Event::ParticipantLeft(alice),
+                Event::FacilitatorChanged(bob)
+            ]
+        );
+        room.leave(carol, NOW).unwrap();
+        assert!(room.is_facilitator(bob));
+        room.leave(bob, NOW).unwrap();
+        assert_eq!(room.facilitator(), None);
+    }
+
+    #[test]
+    fn changing_deck_discards_votes() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.start_round(alice, Some("Search".into())).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.set_deck(alice, Deck::t_shirt()).unwrap();
+        assert_eq!(room.deck(), &Deck::t_shirt());
+        assert!(!room.round().has_voted(alice));
+        assert_eq!(room.round().story(), Some("Search"));
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::DeckChanged(Deck::t_shirt())]
+        );
+        assert_eq!(
+            room.vote(alice, card("5"), NOW),
+            Err(Error::CardNotInDeck(card("5")))
+        );
+        room.vote(alice, card("M"), NOW).unwrap();
+    }
+
+    fn auto_reveal_room() -> (Room, ParticipantId, ParticipantId) {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.join("Olga", Role::Observer).unwrap();
+        room.update_settings(
+            alice,
+            Settings {
+                auto_reveal: true,
+                ..Settings::default()
+            },
+            NOW,
+        )
+        .unwrap();
+        room.drain_events();
+        (room, alice, bob)
+    }
+
+    #[test]
+    fn auto_reveal_when_every_voter_has_voted() {
+        let (mut room, alice, bob) = auto_reveal_room();
+        room.vote(alice, card("3"), NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Voting);
+        room.vote(bob, card("5"), NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Revealed);
+        let events = room.drain_events();
+        assert!(matches!(events.last(), Some(Event::Revealed { .. })));
+    }
+
+    #[test]
+    fn auto_reveal_ignores_disconnected_voters() {
+        let (mut room, alice, bob) = auto_reveal_room();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.set_presence(bob, Presence::Gone, NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Revealed);
+    }
+
+    #[test]
+    fn auto_reveal_when_last_holdout_leaves() {
+        let (mut room, alice, bob) = auto_reveal_room();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.leave(bob, NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Revealed);
+    }
+
+    #[test]
+    fn auto_reveal_needs_at_least_one_vote() {
+        let (mut room, alice, bob) = auto_reveal_room();
+        room.set_role(alice, Role::Observer, NOW).unwrap();
+        room.set_role(bob, Role::Observer, NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    #[test]
+    fn enabling_auto_reveal_reveals_a_complete_round() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("8"), NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Voting);
+        room.update_settings(
+            alice,
+            Settings {
+                auto_reveal: true,
+                ..Settings::default()
+            },
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(room.round().phase(), Phase::Revealed);
+    }
+
+    #[test]
+    fn without_auto_reveal_full_votes_stay_hidden() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("8"), NOW).unwrap();
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    #[test]
+    fn room_round_trips_through_serde_without_events() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        let json = serde_json::to_string(&room).unwrap();
+        let mut restored: Room = serde_json::from_str(&json).unwrap();
+        assert!(restored.drain_events().is_empty());
+        assert_eq!(restored.round().vote_of(alice), Some(&card("5")));
+        assert!(restored.is_facilitator(alice));
+        assert_ne!(restored.join("Bob", Role::Voter).unwrap(), alice);
+    }
+
+    #[test]
+    fn disconnect_all_is_silent() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.join("Bob", Role::Voter).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        room.update_settings(
+            alice,
+            Settings {
+                auto_reveal: true,
+                ..Settings::default()
+            },
+            NOW,
+        )
+        .unwrap();
+        room.drain_events();
+        room.disconnect_all();
+        assert_eq!(room.participant(alice).unwrap().presence, Presence::Gone);
+        assert!(room.drain_events().is_empty());
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    fn backlog_room() -> (Room, ParticipantId, Vec<StoryId>) {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let ids = room
+            .add_stories(
+                alice,
+                vec!["Login".into(), " Search ".into(), "Cart".into()],
+            )
+            .unwrap();
+        room.drain_events();
+        (room, alice, ids)
+    }
+
+    #[test]
+    fn stories_are_queued_in_order() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.drain_events();
+        assert_eq!(
+            room.add_stories(bob, vec!["Login".into()]),
+            Err(Error::NotFacilitator(Action::ManageStories))
+        );
+        assert_eq!(
+            room.add_stories(alice, vec!["Login".into(), " ".into()]),
+            Err(Error::EmptyStory)
+        );
+        assert!(room.stories().is_empty());
+
+        let ids = room
+            .add_stories(alice, vec!["Login".into(), " Search ".into()])
+            .unwrap();
+        let titles: Vec<_> = room.stories().iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Login", "Search"]);
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::StoriesAdded(room.stories().to_vec())]
+        );
+        let more = room
+            .add_stories(alice, vec![NewStory::new("Cart").with_key(" WEB-1 ")])
+            .unwrap();
+        assert!(ids.iter().all(|id| !more.contains(id)));
+        assert_eq!(room.story(more[0]).unwrap().key.as_deref(), Some("WEB-1"));
+    }
+
+    #[test]
+    fn next_story_walks_the_backlog() {
+        let (mut room, alice, ids) = backlog_room();
+        assert_eq!(room.next_story(alice), Ok(ids[0]));
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::RoundStarted {
+                story: Some("Login".into()),
+                story_id: Some(ids[0]),
+            }]
+        );
+        assert_eq!(room.round().story(), Some("Login"));
+        assert_eq!(room.current_story().unwrap().id, ids[0]);
+        assert_eq!(room.next_story(alice), Ok(ids[1]));
+        assert_eq!(room.next_story(alice), Ok(ids[2]));
+        // Skipped stories come around again until they are estimated.
+        assert_eq!(room.next_story(alice), Ok(ids[0]));
+    }
+
+    #[test]
+    fn next_story_skips_estimated_stories() {
+        let (mut room, alice, ids) = backlog_room();
+        room.start_story(alice, ids[1]).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.record_estimate(alice, card("3"), NOW).unwrap();
+        assert_eq!(room.next_story(alice), Ok(ids[2]));
+        assert_eq!(room.next_story(alice), Ok(ids[0]));
+        assert_eq!(room.next_story(alice), Ok(ids[2]));
+
+        for id in [ids[0], ids[2]] {
+            room.start_story(alice, id).unwrap();
+            room.vote(alice, card("1"), NOW).unwrap();
+            room.reveal(alice, NOW).unwrap();
+            room.record_estimate(alice, card("1"), NOW).unwrap();
+        }
+        assert_eq!(room.next_story(alice), Err(Error::BacklogDone));
+    }
+
+    #[test]
+    fn every_revealed_round_is_kept_on_the_story() {
+        let (mut room, alice, ids) = backlog_room();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.start_story(alice, ids[0]).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.vote(bob, card("13"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.reset(alice).unwrap();
+        room.vote(alice, card("5"), NOW).unwrap();
+        room.vote(bob, card("5"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        room.leave(bob, NOW).unwrap();
+
+        let story = room.story(ids[0]).unwrap();
+        assert_eq!(story.rounds.len(), 2);
+        assert_eq!(
+            story.rounds[0].votes,
+            vec![
+                RecordedVote {
+                    participant: alice,
+                    name: "Alice".into(),
+                    card: card("3"),
+                },
+                RecordedVote {
+                    participant: bob,
+                    name: "Bob".into(),
+                    card: card("13"),
+                },
+            ]
+        );
+        assert!(!story.rounds[0].summary.numeric.as_ref().unwrap().consensus);
+        assert!(story.rounds[1].summary.numeric.as_ref().unwrap().consensus);
+        assert!(story.rounds[0].revealed_at <= story.rounds[1].revealed_at);
+        assert!(room.story(ids[1]).unwrap().rounds.is_empty());
+    }
+
+    #[test]
+    fn ad_hoc_rounds_leave_the_backlog_alone() {
+        let (mut room, alice, ids) = backlog_room();
+        room.start_story(alice, ids[0]).unwrap();
+        room.start_round(alice, Some("Hotfix".into())).unwrap();
+        assert!(room.current_story().is_none());
+        room.vote(alice, card("1"), NOW).unwrap();
+        room.reveal(alice, NOW).unwrap();
+        assert!(room.stories().iter().all(|s| s.rounds.is_empty()));
+        assert_eq!(
+            room.record_estimate(alice, card("1"), NOW),
+            Err(Error::NoCurrentStory)
+        );
+    }
+
+    #[test]
+    fn estimate_needs_a_revealed_round_and_a_deck_card() {
+        let (mut room, alice, ids) = backlog_room();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.start_story(alice, ids[0]).unwrap();
+        room.vote(alice, card("8"), NOW).unwrap();
+        assert_eq!(
+            room.record_estimate(alice, card("8"), NOW),
+            Err(Error::RoundNotRevealed)
+        );
+        room.reveal(alice, NOW).unwrap();
+        assert_eq!(
+            room.record_estimate(bob, card("8"), NOW),
+            Err(Error::NotFacilitator(Action::RecordEstimate))
+        );
+        assert_eq!(
+            room.record_estimate(alice, card("XL"), NOW),
+            Err(Error::CardNotInDeck(card("XL")))
+        );
+        room.drain_events();
+
+        // The agreed estimate need not be one of the cards played.
+        room.record_estimate(alice, card("5"), NOW).unwrap();
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::EstimateRecorded {
+                story: ids[0],
+                estimate: card("5"),
+            }]
+        );
+        let story = room.story(ids[0]).unwrap();
+        assert_eq!(story.estimate, Some(card("5")));
+        assert!(story.estimated_at.is_some());
+    }
+
+    #[test]
+    fn removing_the_current_story_keeps_the_round() {
+        let (mut room, alice, ids) = backlog_room();
+        room.start_story(alice, ids[1]).unwrap();
+        room.vote(alice, card("2"), NOW).unwrap();
+        room.drain_events();
+        room.remove_story(alice, ids[1]).unwrap();
+        assert_eq!(room.drain_events(), vec![Event::StoryRemoved(ids[1])]);
+        assert!(room.current_story().is_none());
+        assert!(room.round().has_voted(alice));
+        assert_eq!(
+            room.remove_story(alice, ids[1]),
+            Err(Error::UnknownStory(ids[1]))
+        );
+        assert_eq!(room.next_story(alice), Ok(ids[0]));
+    }
+
+    fn timed_room() -> (Room, ParticipantId, ParticipantId) {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.drain_events();
+        (room, alice, bob)
+    }
+
+    #[test]
+    fn timer_runs_until_its_deadline() {
+        let (mut room, alice, _) = timed_room();
+        let deadline = room
+            .start_timer(alice, Some(Duration::from_secs(60)), Timestamp(1_000))
+            .unwrap();
+        assert_eq!(deadline, Timestamp(61_000));
+        let timer = *room.timer().unwrap();
+        assert_eq!(timer.duration(), Duration::from_secs(60));
+        assert_eq!(room.drain_events(), vec![Event::TimerStarted(timer)]);
+
+        assert!(!room.expire_timer(Timestamp(60_999)));
+        assert!(room.drain_events().is_empty());
+        assert!(room.expire_timer(Timestamp(61_000)));
+        assert_eq!(room.drain_events(), vec![Event::TimerExpired]);
+        assert!(room.timer().is_none());
+        assert!(!room.expire_timer(Timestamp(99_000)));
+    }
+
+    #[test]
+    fn expiry_only_notifies_by_default() {
+        let (mut room, alice, bob) = timed_room();
+        room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0))
+            .unwrap();
+        room.vote(bob, card("3"), NOW).unwrap();
+        room.drain_events();
+        room.expire_timer(Timestamp(30_000));
+        assert_eq!(room.drain_events(), vec![Event::TimerExpired]);
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    #[test]
+    fn expiry_can_reveal_the_votes_cast_so_far() {
+        let (mut room, alice, bob) = timed_room();
+        let settings = Settings {
+            on_timer_expiry: TimerExpiry::Reveal,
+            ..Settings::default()
+        };
+        room.update_settings(alice, settings, NOW).unwrap();
+        room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0))
+            .unwrap();
+        room.vote(bob, card("3"), NOW).unwrap();
+        room.drain_events();
+        room.expire_timer(Timestamp(30_000));
+        let events = room.drain_events();
+        assert_eq!(events[0], Event::TimerExpired);
+        assert!(matches!(events[1], Event::Revealed { .. }));
+        assert_eq!(room.round().phase(), Phase::Revealed);
+    }
+
+    #[test]
+    fn expiry_without_votes_only_notifies() {
+        let (mut room, alice, _) = timed_room();
+        let settings = Settings {
+            on_timer_expiry: TimerExpiry::Reveal,
+            ..Settings::default()
+        };
+        room.update_settings(alice, settings, NOW).unwrap();
+        room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0))
+            .unwrap();
+        room.drain_events();
+        room.expire_timer(Timestamp(30_000));
+        assert_eq!(room.drain_events(), vec![Event::TimerExpired]);
+        assert_eq!(room.round().phase(), Phase::Voting);
+    }
+
+    #[test]
+    fn timers_default_to_the_rooms_length() {
+        let (mut room, alice, _) = timed_room();
+        let settings = Settings {
+            timer_seconds: Some(0),
+            ..Settings::default()
+        };
+        assert_eq!(
+            room.update_settings(alice, settings, NOW),
+            Err(Error::InvalidTimer)
+        );
+        let settings = Settings {
+            timer_seconds: Some(90),
+            ..Settings::default()
+        };
+        room.update_settings(alice, settings, NOW).unwrap();
+        assert_eq!(
+            room.start_timer(alice, None, Timestamp(1_000)),
+            Ok(Timestamp(91_000))
+        );
+        assert_eq!(
+            room.start_timer(alice, Some(Duration::from_secs(10)), Timestamp(1_000)),
+            Ok(Timestamp(11_000))
+        );
+    }
+
+    #[test]
+    fn timer_is_facilitator_only_and_bounded() {
+        let (mut room, alice, bob) = timed_room();
+        let now = Timestamp(0);
+        assert_eq!(
+            room.start_timer(bob, Some(Duration::from_secs(30)), now),
+            Err(Error::NotFacilitator(Action::ManageTimer))
+        );
+        assert_eq!(
+            room.start_timer(alice, Some(Duration::ZERO), now),
+            Err(Error::InvalidTimer)
+        );
+        assert_eq!(
+            room.start_timer(
+                alice,
+                Some(Timer::MAX_DURATION + Duration::from_secs(1)),
+                now
+            ),
+            Err(Error::InvalidTimer)
+        );
+        assert_eq!(
+            room.start_timer(alice, None, now),
+            Err(Error::NoTimerLength)
+        );
+        assert_eq!(room.stop_timer(alice), Err(Error::NoTimer));
+        room.start_timer(alice, Some(Duration::from_secs(30)), now)
+            .unwrap();
+        assert_eq!(
+            room.stop_timer(bob),
+            Err(Error::NotFacilitator(Action::ManageTimer))
+        );
+        room.drain_events();
+        room.stop_timer(alice).unwrap();
+        assert_eq!(room.drain_events(), vec![Event::TimerStopped]);
+        assert!(room.timer().is_none());
+    }
+
+    #[test]
+    fn ending_the_round_stops_the_timer() {
+        let (mut room, alice, bob) = timed_room();
+        room.vote(bob, card("3"), NOW).unwrap();
+        room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0))
+            .unwrap();
+        room.drain_events();
+        room.reveal(alice, NOW).unwrap();
+        let events = room.drain_events();
+        assert_eq!(events[0], Event::TimerStopped);
+        assert!(room.timer().is_none());
+        assert_eq!(
+            room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0)),
+            Err(Error::RoundRevealed)
+        );
+
+        room.start_round(alice, None).unwrap();
+        room.start_timer(alice, Some(Duration::from_secs(30)), Timestamp(0))
+            .unwrap();
+        room.drain_events();
+        room.reset(alice).unwrap();
+        assert_eq!(
+            room.drain_events(),
+            vec![Event::TimerStopped, Event::RoundReset]
+        );
+    }
+
+    #[test]
+    fn anonymous_reveal_shares_only_the_distribution() {
+        let (mut room, alice, ids) = backlog_room();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        let anonymous = Settings {
+            anonymous: true,
+            ..Settings::default()
+        };
+        room.update_settings(alice, anonymous, NOW).unwrap();
+        room.start_story(alice, ids[0]).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.vote(bob, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.reveal(alice, NOW).unwrap();
+
+        let Some(Event::Revealed { votes, summary }) = room.drain_events().pop() else {
+            panic!("expected a reveal");
+        };
+        assert!(votes.is_empty());
+        assert_eq!(summary.distribution.len(), 2);
+        assert_eq!(room.summary(), Some(summary));
+        assert_eq!(room.visible_votes(), None);
+        assert!(room.story(ids[0]).unwrap().rounds[0].votes.is_empty());
+
+        // Switching the mode off later does not expose the anonymous round.
+        room.update_settings(alice, Settings::default(), NOW)
+            .unwrap();
+        assert!(room.round().is_anonymous());
+        assert_eq!(room.visible_votes(), None);
+    }
+    #[test]
+    fn voting_window_closes_once_every_voter_voted_connected_or_not() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.join("Olga", Role::Observer).unwrap();
+        room.update_settings(
+            alice,
+            Settings {
+                auto_reveal: true,
+                ..Settings::default()
+            },
+            NOW,
+        )
+        .unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let deadline = room.open_voting(alice, day, None, Timestamp(0)).unwrap();
+        assert_eq!(deadline, Timestamp(86_400_000));
+        room.set_presence(bob, Presence::Gone, NOW).unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.drain_events();
+        // Auto-reveal would not wait for Bob, who is gone; the window does.
+        assert!(!room.round().is_revealed());
+
+        room.vote(bob, card("5"), NOW).unwrap();
+        let events = room.drain_events();
+        assert!(matches!(
+            events[1],
+            Event::VotingClosed {
+                reason: VotingClosed::Quorum,
+                ..
+            }
+        ));
+        assert!(matches!(events[2], Event::Revealed { .. }));
+        assert_eq!(room.voting(), None);
+    }
+
+    #[test]
+    fn voting_window_closes_on_quorum_or_at_the_deadline() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.join("Carol", Role::Voter).unwrap();
+        let hour = Duration::from_secs(3600);
+        room.open_voting(alice, hour, Some(2), Timestamp(0))
+            .unwrap();
+        room.vote(alice, card("3"), NOW).unwrap();
+        room.vote(bob, card("8"), NOW).unwrap();
+        assert!(room.round().is_revealed());
+
+        room.start_round(alice, None).unwrap();
+        room.open_voting(alice, hour, Some(2), Timestamp(0))
+            .unwrap();
+        room.vote(bob, card("8"), NOW).unwrap();
+        room.drain_events();
+        assert!(!room.close_voting(Timestamp(3_599_999)));
+        assert!(room.close_voting(Timestamp(3_600_000)));
+        let events = room.drain_events();
+        assert!(matches!(
+            events[0],
+            Event::VotingClosed {
+                reason: VotingClosed::Deadline,
+                ..
+            }
+        ));
+        assert!(matches!(events[1], Event::Revealed { .. }));
+        assert!(!room.close_voting(Timestamp(9_999_999)));
+    }
+
+    #[test]
+    fn voting_window_is_validated_and_ends_with_the_round() {
+        let mut room = room();
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        let now = Timestamp(0);
+        assert_eq!(
+            room.open_voting(alice, Duration::from_secs(59), None, now),
+            Err(Error::InvalidVotingPeriod)
+        );
+        assert_eq!(
+            room.open_voting(alice, Duration::from_secs(3600), Some(0), now),
+            Err(Error::InvalidQuorum)
+        );
+        assert_eq!(
+            room.open_voting(bob, Duration::from_secs(3600), None, now),
+            Err(Error::NotFacilitator(Action::OpenVoting))
+        );
+
+        room.open_voting(alice, Duration::from_secs(3600), None, now)
+            .unwrap();
+        room.vote(bob, card("5"), NOW).unwrap();
+        room.drain_events();
+        room.reveal(alice, NOW).unwrap();
+        let events = room.drain_events();
+        assert!(matches!(
+            events[0],
+            Event::VotingClosed {
+                reason: VotingClosed::Facilitator,
+                ..
+            }
+        ));
+        assert_eq!(room.voting(), None);
+        // Nothing to close once the facilitator took over.
+        assert!(!room.close_voting(Timestamp(u64::MAX)));
+    }
+
+    #[test]
+    fn rooms_stored_before_backlogs_still_load() {
+        let mut json = serde_json::to_value(room()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("stories");
+        fields.remove("current_story");
+        fields.remove("next_story");
+        let mut restored: Room = serde_json::from_value(json).unwrap();
+        assert!(restored.stories().is_empty());
+        let alice = restored.join("Alice", Role::Voter).unwrap();
+        let ids = restored
+            .add_stories(alice, vec!["A".into(), "B".into()])
+            .unwrap();
+        assert_ne!(ids[0], ids[1]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::participant::ParticipantId;
+
+/// Where a round is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Votes are being collected and stay hidden.
+    Voting,
+    /// Votes are visible and locked.
+    Revealed,
+}
+
+/// One estimation of a story.
+///
+/// A round only stores state; the rules for changing it live on
+/// [`Room`](crate::Room) so they are enforced in a single place.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+    story: Option<String>,
+    phase: Phase,
+    votes: BTreeMap<ParticipantId, Card>,
+    /// Revealed as a distribution only; who voted what stays hidden for good.
+    #[serde(default)]
+    anonymous: bool,
+}
+
+impl Round {
+    pub(crate) fn new(story: Option<String>) -> Self {
+        Round {
+            story,
+            phase: Phase::Voting,
+            votes: BTreeMap::new(),
+            anonymous: false,
+        }
+    }
+
+    /// The story being estimated, if one was named.
+    pub fn story(&self) -> Option<&str> {
+        self.story.as_deref()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.phase == Phase::Revealed
+    }
+
+    pub fn has_voted(&self, id: ParticipantId) -> bool {
+        self.votes.contains_key(&id)
+    }
+
+    /// The card a participant played, even while still hidden. Only hand this
+    /// to the participant who cast it.
+    pub fn vote_of(&self, id: ParticipantId) -> Option<&Card> {
+        self.votes.get(&id)
+    }
+
+    /// Participants who have voted, without their cards.
+    pub fn voters(&self) -> impl Iterator<Item = ParticipantId> + '_ {
+        self.votes.keys().copied()
+    }
+
+    /// Whether the round was revealed in anonymous mode.
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    /// The cast votes, or `None` while they are still hidden or if the round
+    /// was revealed anonymously.
+    pub fn revealed_votes(&self) -> Option<&BTreeMap<ParticipantId, Card>> {
+        (self.is_revealed() && !self.anonymous).then_some(&self.votes)
+    }
+
+    pub(crate) fn votes(&self) -> &BTreeMap<ParticipantId, Card> {
+        &self.votes
+    }
+
+    pub(crate) fn votes_mut(&mut self) -> &mut BTreeMap<ParticipantId, Card> {
+        &mut self.votes
+    }
+
+    pub(crate) fn set_phase(&mut self, phase: Phase) {
+        self.phase = phase;
+    }
+
+    pub(crate) fn set_anonymous(&mut self) {
+        self.anonymous = true;
+    }
+}
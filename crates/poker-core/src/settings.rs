@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::timer::Timer;
+
+/// Room options the facilitator can change at any time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Reveal as soon as every connected voter has voted.
+    pub auto_reveal: bool,
+    /// Show only the distribution on reveal, never who played which card.
+    pub anonymous: bool,
+    /// What happens when the round timer runs out.
+    pub on_timer_expiry: TimerExpiry,
+    /// Length of the round timer, in seconds, when the facilitator starts one
+    /// without saying how long.
+    pub timer_seconds: Option<u64>,
+}
+
+impl Settings {
+    /// The default round timer length, if the room has one.
+    pub fn timer(&self) -> Option<Duration> {
+        self.timer_seconds.map(Duration::from_secs)
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self.timer() {
+            Some(length) if !(Timer::MIN_DURATION..=Timer::MAX_DURATION).contains(&length) => {
+                Err(Error::InvalidTimer)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerExpiry {
+    /// Only tell everyone that time is up.
+    #[default]
+    Notify,
+    /// Reveal the votes cast so far, if there are any.
+    Reveal,
+}
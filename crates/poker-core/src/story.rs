@@ -0,0 +1,144 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::participant::ParticipantId;
+use crate::summary::Summary;
+use crate::time::Timestamp;
+
+/// Identifies a story within a room's backlog. Like participant ids, story
+/// ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StoryId(pub u64);
+
+impl fmt::Display for StoryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s{}", self.0)
+    }
+}
+
+/// An item of a room's backlog together with its estimation history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Story {
+    pub id: StoryId,
+    pub title: String,
+    /// Reference to the story in an external tracker, e.g. `PROJ-123`.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// The estimate the team agreed on, once the facilitator recorded it.
+    pub estimate: Option<Card>,
+    pub estimated_at: Option<Timestamp>,
+    /// Every revealed round on this story, re-votes included, oldest first.
+    pub rounds: Vec<RoundResult>,
+}
+
+impl Story {
+    pub(crate) fn new(id: StoryId, new: NewStory) -> Self {
+        Story {
+            id,
+            title: new.title,
+            key: new.key,
+            estimate: None,
+            estimated_at: None,
+            rounds: Vec::new(),
+        }
+    }
+
+    pub fn is_estimated(&self) -> bool {
+        self.estimate.is_some()
+    }
+}
+
+/// A story to be added to a backlog.
+///
+/// Deserializes from either a bare title or an object with a `title` and an
+/// optional tracker `key`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "NewStorySpec")]
+pub struct NewStory {
+    pub title: String,
+    pub key: Option<String>,
+}
+
+impl NewStory {
+    pub fn new(title: impl Into<String>) -> Self {
+        NewStory {
+            title: title.into(),
+            key: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+}
+
+impl From<&str> for NewStory {
+    fn from(title: &str) -> Self {
+        NewStory::new(title)
+    }
+}
+
+impl From<String> for NewStory {
+    fn from(title: String) -> Self {
+        NewStory::new(title)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NewStorySpec {
+    Title(String),
+    Full {
+        title: String,
+        #[serde(default)]
+        key: Option<String>,
+    },
+}
+
+impl From<NewStorySpec> for NewStory {
+    fn from(spec: NewStorySpec) -> Self {
+        match spec {
+            NewStorySpec::Title(title) => NewStory { title, key: None },
+            NewStorySpec::Full { title, key } => NewStory { title, key },
+        }
+    }
+}
+
+/// The outcome of one revealed round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundResult {
+    pub revealed_at: Timestamp,
+    pub votes: Vec<RecordedVote>,
+    pub summary: Summary,
+}
+
+/// A revealed vote. The name is copied so history stays readable after the
+/// participant has left.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedVote {
+    pub participant: ParticipantId,
+    pub name: String,
+    pub card: Card,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_story_accepts_a_bare_title_or_an_object() {
+        let stories: Vec<NewStory> =
+            serde_json::from_str(r#"["Login", {"title": "Search", "key": "WEB-7"}]"#).unwrap();
+        assert_eq!(
+            stories,
+            [
+                NewStory::new("Login"),
+                NewStory::new("Search").with_key("WEB-7")
+            ]
+        );
+    }
+}
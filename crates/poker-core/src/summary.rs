@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use crate::card::Card;
+use crate::deck::Deck;
+
+/// How many votes a card received.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardCount {
+    pub card: Card,
+    pub count: usize,
+}
+
+/// Statistics over the numeric votes of a round. Special cards such as `?`
+/// are left out of every figure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumericSummary {
+    pub average: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Every vote in the round was the same numeric card.
+    pub consensus: bool,
+}
+
+/// The outcome of a revealed round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Cards that received at least one vote, in deck order.
+    pub distribution: Vec<CardCount>,
+    /// Present only for numeric decks with at least one numeric vote.
+    pub numeric: Option<NumericSummary>,
+}
+
+impl Summary {
+    pub fn new<'a>(deck: &Deck, votes: impl IntoIterator<Item = &'a Card>) -> Self {
+        let votes: Vec<&Card> = votes.into_iter().collect();
+
+        let mut distribution: Vec<CardCount> = Vec::new();
+        for card in &votes {
+            match distribution.iter_mut().find(|c| &c.card == *card) {
+                Some(entry) => entry.count += 1,
+                None => distribution.push(CardCount {
+                    card: (*card).clone(),
+                    count: 1,
+                }),
+            }
+        }
+        distribution.sort_by_key(|c| deck.position(&c.card).unwrap_or(usize::MAX));
+
+        let numeric = if deck.is_numeric() {
+            numeric_summary(&votes)
+        } else {
+            None
+        };
+        Summary {
+            distribution,
+            numeric,
+        }
+    }
+
+    pub fn vote_count(&self) -> usize {
+        self.distribution.iter().map(|c| c.count).sum()
+    }
+}
+
+fn numeric_summary(votes: &[&Card]) -> Option<NumericSummary> {
+    let mut values: Vec<f64> = votes.iter().filter_map(|c| c.value()).collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let n = values.len();
+    let median = if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    };
+    Some(NumericSummary {
+        average: values.iter().sum::<f64>() / n as f64,
+        median,
+        min: values[0],
+        max: values[n - 1],
+        consensus: values.len() == votes.len() && values[0] == values[n - 1],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(labels: &[&str]) -> Vec<Card> {
+        labels.iter().copied().map(Card::new).collect()
+    }
+
+    #[test]
+    fn numeric_deck_reports_statistics() {
+        let votes = cards(&["3", "8", "5", "5"]);
+        let summary = Summary::new(&Deck::fibonacci(), &votes);
+        let numeric = summary.numeric.as_ref().unwrap();
+        assert_eq!(numeric.average, 5.25);
+        assert_eq!(numeric.median, 5.0);
+        assert_eq!(numeric.min, 3.0);
+        assert_eq!(numeric.max, 8.0);
+        assert!(!numeric.consensus);
+        assert_eq!(summary.vote_count(), 4);
+    }
+
+    #[test]
+    fn median_of_odd_count_is_middle_value() {
+        let votes = cards(&["13", "½", "2"]);
+        let numeric = Summary::new(&Deck::fibonacci(), &votes).numeric.unwrap();
+        assert_eq!(numeric.median, 2.0);
+        assert_eq!(numeric.min, 0.5);
+    }
+
+    #[test]
+    fn distribution_follows_deck_order() {
+        let votes = cards(&["8", "?", "3", "8"]);
+        let summary = Summary::new(&Deck::fibonacci(), &votes);
+        assert_eq!(
+            summary.distribution,
+            vec![
+                CardCount {
+                    card: Card::new("3"),
+                    count: 1
+                },
+                CardCount {
+                    card: Card::new("8"),
+                    count: 2
+                },
+                CardCount {
+                    card: Card::new("?"),
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn special_cards_are_excluded_from_statistics() {
+        let votes = cards(&["5", "?", "☕", "5"]);
+        let numeric = Summary::new(&Deck::fibonacci(), &votes).numeric.unwrap();
+        assert_eq!(numeric.average, 5.0);
+        assert!(!numeric.consensus, "a `?` vote is not agreement");
+    }
+
+    #[test]
+    fn identical_votes_are_consensus() {
+        let votes = cards(&["8", "8", "8"]);
+        let numeric = Summary::new(&Deck::powers_of_two(), &votes)
+            .numeric
+            .unwrap();
+        assert!(numeric.consensus);
+    }
+
+    #[test]
+    fn only_special_votes_have_no_statistics() {
+        let votes = cards(&["?", "☕"]);
+        let summary = Summary::new(&Deck::fibonacci(), &votes);
+        assert_eq!(summary.numeric, None);
+        assert_eq!(summary.distribution.len(), 2);
+    }
+
+    #[test]
+    fn non_numeric_deck_reports_distribution_only() {
+        let votes = cards(&["M", "L", "M"]);
+        let summary = Summary::new(&Deck::t_shirt(), &votes);
+        assert_eq!(summary.numeric, None);
+        assert_eq!(summary.distribution[0].card, Card::new("M"));
+        assert_eq!(summary.distribution[0].count, 2);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::deck::Deck;
+use crate::error::{Error, Result};
+use crate::participant::Role;
+use crate::settings::Settings;
+
+/// Someone on the team a room is set up for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub name: String,
+    /// The role they take whenever they join.
+    #[serde(default = "voter")]
+    pub role: Role,
+}
+
+fn voter() -> Role {
+    Role::Voter
+}
+
+/// A saved room configuration, for teams that set up the same room every
+/// sprint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    /// Unique among the server's templates.
+    pub name: String,
+    #[serde(default)]
+    pub deck: Deck,
+    /// Anonymity, auto-reveal and the default round timer, among others.
+    #[serde(default)]
+    pub settings: Settings,
+    /// Name of the member who moderates. They take over as facilitator
+    /// when they join.
+    #[serde(default)]
+    pub moderator: Option<String>,
+    /// The team, in display order.
+    #[serde(default)]
+    pub members: Vec<Member>,
+}
+
+impl Template {
+    /// Trims names and rejects a template no room could be started from.
+    pub fn normalize(mut self) -> Result<Self> {
+        self.name = self.name.trim().to_owned();
+        if self.name.is_empty() {
+            return Err(Error::EmptyTemplateName);
+        }
+        self.settings.validate()?;
+        self.moderator = self
+            .moderator
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty());
+        let mut seen = HashSet::new();
+        for member in &mut self.members {
+            member.name = member.name.trim().to_owned();
+            if member.name.is_empty() {
+                return Err(Error::EmptyName);
+            }
+            if !seen.insert(member.name.to_lowercase()) {
+                return Err(Error::DuplicateMember(member.name.clone()));
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> Template {
+        Template {
+            name: " Team Rocket ".into(),
+            deck: Deck::t_shirt(),
+            settings: Settings::default(),
+            moderator: Some(" Alice ".into()),
+            members: vec![
+                Member {
+                    name: "Alice".into(),
+                    role: Role::Voter,
+                },
+                Member {
+                    name: " Pat ".into(),
+                    role: Role::Observer,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn normalizing_trims_names() {
+        let template = template().normalize().unwrap();
+        assert_eq!(template.name, "Team Rocket");
+        assert_eq!(template.moderator.as_deref(), Some("Alice"));
+        assert_eq!(template.members[1].name, "Pat");
+    }
+
+    #[test]
+    fn rejects_what_no_room_could_use() {
+        let mut unnamed = template();
+        unnamed.name = "  ".into();
+        assert_eq!(unnamed.normalize(), Err(Error::EmptyTemplateName));
+
+        let mut twice = template();
+        twice.members[1].name = "alice".into();
+        assert_eq!(
+            twice.normalize(),
+            Err(Error::DuplicateMember("alice".into()))
+        );
+
+        let mut endless = template();
+        endless.settings.timer_seconds = Some(24 * 60 * 60);
+        assert_eq!(endless.normalize(), Err(Error::InvalidTimer));
+    }
+
+    #[test]
+    fn only_a_name_is_required() {
+        let template: Template = serde_json::from_str(r#"{"name": "Quick"}"#).unwrap();
+        assert_eq!(template.deck, Deck::default());
+        assert_eq!(template.moderator, None);
+        assert!(template.members.is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::time::Timestamp;
+
+/// A countdown on the current round, decided by the server's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timer {
+    pub started_at: Timestamp,
+    pub deadline: Timestamp,
+}
+
+impl Timer {
+    /// Shortest countdown a facilitator can start.
+    pub const MIN_DURATION: Duration = Duration::from_secs(1);
+    /// Longest countdown a facilitator can start.
+    pub const MAX_DURATION: Duration = Duration::from_secs(60 * 60);
+
+    pub(crate) fn new(started_at: Timestamp, duration: Duration) -> Self {
+        Timer {
+            started_at,
+            deadline: started_at.saturating_add(duration),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.deadline.saturating_duration_since(self.started_at)
+    }
+
+    /// Time left at `now`, zero once the deadline has passed.
+    pub fn remaining(&self, now: Timestamp) -> Duration {
+        self.deadline.saturating_duration_since(now)
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        now >= self.deadline
+    }
+}
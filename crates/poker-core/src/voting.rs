@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::time::Timestamp;
+
+/// A round left open for votes over a longer period, for teams that do not
+/// meet live. It reveals at its deadline or as soon as `quorum` is reached,
+/// whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VotingWindow {
+    pub opened_at: Timestamp,
+    pub deadline: Timestamp,
+    /// Number of votes that closes the window early. `None` waits for every
+    /// voter in the room, connected or not.
+    pub quorum: Option<u32>,
+}
+
+impl VotingWindow {
+    /// Shortest period voting can be opened for.
+    pub const MIN_DURATION: Duration = Duration::from_secs(60);
+    /// Longest period voting can be opened for.
+    pub const MAX_DURATION: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+    pub(crate) fn new(opened_at: Timestamp, duration: Duration, quorum: Option<u32>) -> Self {
+        VotingWindow {
+            opened_at,
+            deadline: opened_at.saturating_add(duration),
+            quorum,
+        }
+    }
+
+    pub fn is_over(&self, now: Timestamp) -> bool {
+        now >= self.deadline
+    }
+}
+
+/// Why a voting window closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingClosed {
+    /// The deadline passed.
+    Deadline,
+    /// Enough votes came in.
+    Quorum,
+    /// The facilitator revealed or moved on before either.
+    Facilitator,
+}
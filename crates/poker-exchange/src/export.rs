@@ -0,0 +1,222 @@
+use std::io::Write;
+
+use poker_core::{Card, CardCount, NumericSummary, Story};
+use serde::Serialize;
+
+use crate::{ColumnMapping, Format, Result};
+
+/// A story as written by [`export_json`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportedStory {
+    pub key: Option<String>,
+    pub title: String,
+    pub estimate: Option<Card>,
+    /// RFC 3339, UTC.
+    pub estimated_at: Option<String>,
+    /// Every revealed round, oldest first.
+    pub rounds: Vec<ExportedRound>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportedRound {
+    /// RFC 3339, UTC.
+    pub revealed_at: String,
+    pub distribution: Vec<CardCount>,
+    pub numeric: Option<NumericSummary>,
+}
+
+impl From<&Story> for ExportedStory {
+    fn from(story: &Story) -> Self {
+        ExportedStory {
+            key: story.key.clone(),
+            title: story.title.clone(),
+            estimate: story.estimate.clone(),
+            estimated_at: story.estimated_at.map(|t| t.to_rfc3339()),
+            rounds: story
+                .rounds
+                .iter()
+                .map(|round| ExportedRound {
+                    revealed_at: round.revealed_at.to_rfc3339(),
+                    distribution: round.summary.distribution.clone(),
+                    numeric: round.summary.numeric.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Renders `stories` in the given format.
+pub fn export(format: Format, stories: &[Story], mapping: &ColumnMapping) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        Format::Csv => export_csv(stories, mapping, &mut out)?,
+        Format::Json => export_json(stories, &mut out)?,
+    }
+    Ok(out)
+}
+
+/// Writes one row per story. The distribution and average are those of the
+/// last revealed round, the one the estimate was agreed in.
+pub fn export_csv(stories: &[Story], mapping: &ColumnMapping, writer: impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut header = Vec::new();
+    header.extend(mapping.key.as_deref());
+    header.extend([
+        mapping.title.as_str(),
+        mapping.estimate.as_str(),
+        "estimated_at",
+        "rounds",
+        "distribution",
+        "average",
+        "first_revealed_at",
+        "last_revealed_at",
+    ]);
+    writer.write_record(&header)?;
+
+    for story in stories {
+        let last = story.rounds.last();
+        let mut row = Vec::new();
+        if mapping.key.is_some() {
+            row.push(story.key.clone().unwrap_or_default());
+        }
+        row.extend([
+            story.title.clone(),
+            story
+                .estimate
+                .as_ref()
+                .map(Card::to_string)
+                .unwrap_or_default(),
+            story
+                .estimated_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            story.rounds.len().to_string(),
+            last.map(|r| distribution(&r.summary.distribution))
+                .unwrap_or_default(),
+            last.and_then(|r| r.summary.numeric.as_ref())
+                .map(|n| format!("{:.2}", n.average))
+                .unwrap_or_default(),
+            story
+                .rounds
+                .first()
+                .map(|r| r.revealed_at.to_rfc3339())
+                .unwrap_or_default(),
+            last.map(|r| r.revealed_at.to_rfc3339()).unwrap_or_default(),
+        ]);
+        writer.write_record(&row)?;
+    }
+    writer.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+/// Writes the stories with the summary of every revealed round.
+pub fn export_json(stories: &[Story], writer: impl Write) -> Result<()> {
+    let stories: Vec<_> = stories.iter().map(ExportedStory::from).collect();
+    serde_json::to_writer_pretty(writer, &stories)?;
+    Ok(())
+}
+
+/// `"3: 1; 5: 2"`.
+fn distribution(counts: &[CardCount]) -> String {
+    counts
+        .iter()
+        .map(|c| format!("{}: {}", c.card, c.count))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use poker_core::{Deck, NewStory, Role, Room, RoomId, Timestamp};
+
+    use super::*;
+    use crate::{import, Format};
+
+    /// A room whose first story took two rounds and got an estimate.
+    fn stories() -> Vec<Story> {
+        let mut room = Room::new(RoomId::new("r"), "R", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.add_stories(
+            alice,
+            vec![NewStory::new("Login").with_key("WEB-1"), "Search".into()],
+        )
+        .unwrap();
+        room.next_story(alice).unwrap();
+        for (a, b) in [("3", "8"), ("5", "8")] {
+            room.vote(alice, Card::new(a), Timestamp(0)).unwrap();
+            room.vote(bob, Card::new(b), Timestamp(0)).unwrap();
+            room.reveal(alice, Timestamp(0)).unwrap();
+            room.reset(alice).unwrap();
+        }
+        room.vote(alice, Card::new("5"), Timestamp(0)).unwrap();
+        room.vote(bob, Card::new("8"), Timestamp(0)).unwrap();
+        room.reveal(alice, Timestamp(0)).unwrap();
+        room.record_estimate(alice, Card::new("8"), Timestamp(0))
+            .unwrap();
+
+        let mut stories = room.stories().to_vec();
+        // Pin the clock so the expected output is stable.
+        for (i, round) in stories[0].rounds.iter_mut().enumerate() {
+            round.revealed_at = Timestamp(1_709_285_400_000 + i as u64 * 60_000);
+        }
+        stories[0].estimated_at = Some(Timestamp(1_709_285_600_000));
+        stories
+    }
+
+    #[test]
+    fn csv_has_one_row_per_story() {
+        let csv = export(Format::Csv, &stories(), &ColumnMapping::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "key,title,estimate,estimated_at,rounds,distribution,average,first_revealed_at,last_revealed_at\n\
+             WEB-1,Login,8,2024-03-01T09:33:20Z,3,5: 1; 8: 1,6.50,2024-03-01T09:30:00Z,2024-03-01T09:32:00Z\n\
+             ,Search,,,0,,,,\n"
+        );
+    }
+
+    #[test]
+    fn exported_csv_imports_back_with_the_same_mapping() {
+        let mapping = ColumnMapping {
+            title: "Summary".into(),
+            key: Some("Issue key".into()),
+            estimate: "Story Points".into(),
+        };
+        let csv = export(Format::Csv, &stories(), &mapping).unwrap();
+        let text = String::from_utf8(csv).unwrap();
+        assert!(text.starts_with("Issue key,Summary,Story Points,"));
+        let imported = import(Format::Csv, &text, &mapping).unwrap();
+        assert_eq!(
+            imported,
+            [NewStory::new("Login").with_key("WEB-1"), "Search".into()]
+        );
+    }
+
+    #[test]
+    fn csv_can_leave_out_keys() {
+        let mapping = ColumnMapping {
+            key: None,
+            ..ColumnMapping::default()
+        };
+        let csv = export(Format::Csv, &stories(), &mapping).unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .starts_with("title,estimate,"));
+    }
+
+    #[test]
+    fn json_keeps_every_round() {
+        let json = export(Format::Json, &stories(), &ColumnMapping::default()).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let login = &value[0];
+        assert_eq!(login["key"], "WEB-1");
+        assert_eq!(login["estimate"], "8");
+        assert_eq!(login["estimated_at"], "2024-03-01T09:33:20Z");
+        assert_eq!(login["rounds"].as_array().unwrap().len(), 3);
+        assert_eq!(login["rounds"][0]["revealed_at"], "2024-03-01T09:30:00Z");
+        assert_eq!(login["rounds"][0]["numeric"]["consensus"], false);
+        // Who voted what stays out of the export.
+        assert!(login["rounds"][0].get("votes").is_none());
+        assert_eq!(value[1]["estimate"], serde_json::Value::Null);
+    }
+}
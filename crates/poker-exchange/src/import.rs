@@ -0,0 +1,172 @@
+use std::io::Read;
+
+use poker_core::NewStory;
+use serde_json::Value;
+
+use crate::{ColumnMapping, ExchangeError, Format, Result};
+
+/// Reads stories from `data` in the given format. Entries without a title
+/// are skipped, so blank trailing rows don't matter.
+pub fn import(format: Format, data: &str, mapping: &ColumnMapping) -> Result<Vec<NewStory>> {
+    match format {
+        Format::Csv => import_csv(data.as_bytes(), mapping),
+        Format::Json => import_json(data.as_bytes(), mapping),
+    }
+}
+
+/// Reads stories from CSV with a header row.
+pub fn import_csv(reader: impl Read, mapping: &ColumnMapping) -> Result<Vec<NewStory>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+    };
+    let title =
+        find(&mapping.title).ok_or_else(|| ExchangeError::MissingColumn(mapping.title.clone()))?;
+    let key = mapping.key.as_deref().and_then(find);
+
+    let mut stories = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let Some(title) = record.get(title).map(str::trim).filter(|t| !t.is_empty()) else {
+            continue;
+        };
+        let mut story = NewStory::new(title);
+        if let Some(key) = key.and_then(|i| record.get(i)).map(str::trim) {
+            if !key.is_empty() {
+                story = story.with_key(key);
+            }
+        }
+        stories.push(story);
+    }
+    Ok(stories)
+}
+
+/// Reads stories from a JSON array whose entries are either titles or
+/// objects holding the mapped fields.
+pub fn import_json(reader: impl Read, mapping: &ColumnMapping) -> Result<Vec<NewStory>> {
+    let Value::Array(entries) = serde_json::from_reader(reader)? else {
+        return Err(ExchangeError::NotAList);
+    };
+    let mut stories = Vec::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let invalid = |reason: String| ExchangeError::InvalidEntry { index, reason };
+        let (title, key) = match &entry {
+            Value::String(title) => (Some(title.clone()), None),
+            Value::Object(fields) => {
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .find(|(k, _)| k.trim().eq_ignore_ascii_case(name.trim()))
+                        .map(|(_, v)| v)
+                };
+                let title = field(&mapping.title)
+                    .map(|v| {
+                        scalar(v).ok_or_else(|| invalid(format!("{:?} is not text", mapping.title)))
+                    })
+                    .transpose()?;
+                let key = match mapping.key.as_deref().and_then(field) {
+                    Some(v) => scalar(v),
+                    None => None,
+                };
+                (title, key)
+            }
+            _ => return Err(invalid("expected a title or an object".into())),
+        };
+        let Some(title) = title.map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()) else {
+            continue;
+        };
+        let mut story = NewStory::new(title);
+        if let Some(key) = key.map(|k| k.trim().to_owned()).filter(|k| !k.is_empty()) {
+            story = story.with_key(key);
+        }
+        stories.push(story);
+    }
+    Ok(stories)
+}
+
+/// Text of a string or number value; trackers often use numeric ids.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jira() -> ColumnMapping {
+        ColumnMapping {
+            title: "Summary".into(),
+            key: Some("Issue key".into()),
+            ..ColumnMapping::default()
+        }
+    }
+
+    #[test]
+    fn csv_columns_are_mapped_by_header() {
+        let csv = "Issue key,Status,Summary\n\
+                   WEB-1,Open,Login page\n\
+                   WEB-2,Open,\"Search, with filters\"\n\
+                   ,,\n";
+        let stories = import(Format::Csv, csv, &jira()).unwrap();
+        assert_eq!(
+            stories,
+            [
+                NewStory::new("Login page").with_key("WEB-1"),
+                NewStory::new("Search, with filters").with_key("WEB-2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_headers_match_case_insensitively_and_key_is_optional() {
+        let stories = import(Format::Csv, "TITLE\nLogin\n", &ColumnMapping::default()).unwrap();
+        assert_eq!(stories, [NewStory::new("Login")]);
+    }
+
+    #[test]
+    fn csv_without_title_column_is_rejected() {
+        let err = import(Format::Csv, "Name\nLogin\n", &jira()).unwrap_err();
+        assert!(matches!(err, ExchangeError::MissingColumn(c) if c == "Summary"));
+    }
+
+    #[test]
+    fn json_accepts_titles_and_mapped_objects() {
+        let json = r#"[
+            "Login",
+            {"Summary": "Search", "Issue key": 42},
+            {"Summary": "  "}
+        ]"#;
+        let stories = import(Format::Json, json, &jira()).unwrap();
+        assert_eq!(
+            stories,
+            [
+                NewStory::new("Login"),
+                NewStory::new("Search").with_key("42")
+            ]
+        );
+    }
+
+    #[test]
+    fn json_must_be_a_list_of_stories() {
+        let mapping = ColumnMapping::default();
+        assert!(matches!(
+            import(Format::Json, r#"{"title": "x"}"#, &mapping),
+            Err(ExchangeError::NotAList)
+        ));
+        assert!(matches!(
+            import(Format::Json, r#"["ok", 3]"#, &mapping),
+            Err(ExchangeError::InvalidEntry { index: 1, .. })
+        ));
+        assert!(matches!(
+            import(Format::Json, r#"[{"title": ["x"]}]"#, &mapping),
+            Err(ExchangeError::InvalidEntry { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! Moving stories and estimates in and out of OpenPlanningPoker.
+//!
+//! Backlogs are imported from the CSV or JSON that issue trackers export, with
+//! a [`ColumnMapping`] naming the columns that hold each story's title and
+//! tracker key. After a session, a room's stories are exported with their
+//! final estimate, the vote distribution of the deciding round and when it
+//! all happened, ready to paste back into the tracker.
+
+mod export;
+mod import;
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+pub use export::{export, export_csv, export_json, ExportedRound, ExportedStory};
+pub use import::{import, import_csv, import_json};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExchangeError {
+    #[error("invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("no {0:?} column found")]
+    MissingColumn(String),
+    #[error("expected a JSON array of stories")]
+    NotAList,
+    #[error("entry {index}: {reason}")]
+    InvalidEntry { index: usize, reason: String },
+}
+
+pub type Result<T, E = ExchangeError> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unknown format {other:?}, expected csv or json")),
+        }
+    }
+}
+
+/// Which columns (CSV) or fields (JSON) hold what.
+///
+/// Names are matched case-insensitively on import and used verbatim as
+/// headers on export, so a file exported with the tracker's own column names
+/// can be imported back into it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    /// The story title. Required on import.
+    pub title: String,
+    /// The tracker key, e.g. `PROJ-123`. Imported when the column exists;
+    /// `None` leaves keys out entirely.
+    pub key: Option<String>,
+    /// The final estimate. Only used on export.
+    pub estimate: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            title: "title".into(),
+            key: Some("key".into()),
+            estimate: "estimate".into(),
+        }
+    }
+}
@@ -0,0 +1,874 @@
+//! Wire protocol between OpenPlanningPoker clients and servers.
+//!
+//! Every WebSocket text frame carries one JSON object whose `type` field names
+//! the message. Clients announce the [`PROTOCOL_VERSION`] they speak when they
+//! create or join a room, and the server refuses versions it doesn't know.
+//! The full message set is documented in `docs/protocol.md`.
+
+use poker_core::{
+    Card, Deck, Error, Event, Member, NewStory, Participant, ParticipantId, Phase, Presence, Role,
+    Room, RoomId, Settings, Story, StoryId, Summary, Template, Timer, Timestamp, VotingClosed,
+    VotingWindow,
+};
+use poker_exchange::{ColumnMapping, Format};
+use poker_webhook::{DeliveryFailure, EventKind, Subscription, WebhookId};
+use serde::{Deserialize, Serialize};
+
+/// Version of the message set defined in this crate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Messages sent by clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Opens a new room. The server answers with [`ServerMessage::RoomCreated`];
+    /// the connection still has to [`join`](ClientMessage::Join) it.
+    CreateRoom {
+        version: u32,
+        name: String,
+        #[serde(default)]
+        deck: Deck,
+        /// Makes the room private: joining it takes this passphrase.
+        #[serde(default)]
+        passphrase: Option<String>,
+        /// Sets the room up from a saved [`Template`], whose deck is used
+        /// instead of `deck`. An empty `name` defaults to the template's.
+        #[serde(default)]
+        template: Option<String>,
+    },
+    /// Saves a room template under its name, replacing any template of that
+    /// name the sender owns. Answered with [`ServerMessage::TemplateSaved`].
+    SaveTemplate {
+        template: Template,
+        /// On servers without login, the token from
+        /// [`ServerMessage::TemplateSaved`] that proves the sender saved the
+        /// template before.
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// Deletes a room template the sender owns.
+    DeleteTemplate {
+        name: String,
+        /// As for [`ClientMessage::SaveTemplate`].
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// Asks for [`ServerMessage::Templates`].
+    ListTemplates,
+    /// Takes a seat in a room. Answered with [`ServerMessage::Welcome`].
+    /// On a server that requires login, `name` is replaced by the name of
+    /// the logged-in user.
+    Join {
+        version: u32,
+        room: RoomId,
+        #[serde(default)]
+        name: String,
+        #[serde(default = "default_role")]
+        role: Role,
+        /// Required for private rooms.
+        #[serde(default)]
+        passphrase: Option<String>,
+        /// One of the [`Invite`]s handed out when the room was started from
+        /// a template. Seats the member it was made for, under their name
+        /// and role, instead of `name` and `role`.
+        #[serde(default)]
+        invite: Option<String>,
+    },
+    /// Attaches a login session obtained from `/auth/callback` to the
+    /// connection. Answered with [`ServerMessage::Authenticated`].
+    Authenticate {
+        session: String,
+    },
+    /// Reclaims a seat held since a dropped connection, using the token from
+    /// the original [`ServerMessage::Welcome`].
+    Resume {
+        version: u32,
+        room: RoomId,
+        token: String,
+    },
+    /// Gives up the seat; the connection may join another room afterwards.
+    Leave,
+    /// Heartbeat that keeps the participant from being shown as idle.
+    Ping,
+    SetRole {
+        role: Role,
+    },
+    Vote {
+        card: Card,
+    },
+    RetractVote,
+    Reveal,
+    /// Clears the votes and re-opens voting on the same story.
+    Reset,
+    /// Moves on to an ad-hoc story that is not on the backlog.
+    StartRound {
+        #[serde(default)]
+        story: Option<String>,
+    },
+    /// Appends stories to the backlog. Facilitator only.
+    AddStories {
+        stories: Vec<NewStory>,
+    },
+    /// Appends the stories found in a CSV or JSON document to the backlog.
+    /// Facilitator only.
+    ImportStories {
+        format: Format,
+        data: String,
+        #[serde(default)]
+        mapping: ColumnMapping,
+    },
+    /// Appends the issues matching `query` in the server's issue tracker,
+    /// e.g. a JQL search. Facilitator only.
+    FetchStories {
+        query: String,
+    },
+    /// Facilitator only.
+    RemoveStory {
+        story: StoryId,
+    },
+    /// Starts a round on a backlog story. Facilitator only.
+    StartStory {
+        story: StoryId,
+    },
+    /// Starts a round on the next story still lacking an estimate.
+    /// Facilitator only.
+    NextStory,
+    /// Records the agreed estimate for the current story once the round is
+    /// revealed. Facilitator only.
+    RecordEstimate {
+        estimate: Card,
+    },
+    /// Starts a countdown on the current round, replacing any running one.
+    /// Without `seconds`, it runs for the room's default length. Facilitator
+    /// only.
+    StartTimer {
+        #[serde(default)]
+        seconds: Option<u64>,
+    },
+    /// Facilitator only.
+    StopTimer,
+    /// Leaves the current round open for votes for `seconds`, revealing at
+    /// the deadline or once `quorum` votes are in. Without a quorum, the
+    /// round waits for every voter. Facilitator only.
+    OpenVoting {
+        seconds: u64,
+        #[serde(default)]
+        quorum: Option<u32>,
+    },
+    /// Removes another participant. Facilitator only.
+    Kick {
+        participant: ParticipantId,
+    },
+    /// Passes moderation to another participant. Facilitator only.
+    HandOver {
+        participant: ParticipantId,
+    },
+    /// Replaces the deck and discards the current votes. Facilitator only.
+    SetDeck {
+        deck: Deck,
+    },
+    /// Facilitator only.
+    UpdateSettings {
+        settings: Settings,
+    },
+    /// Subscribes `url` to the room's notifications, or only to those about
+    /// `events`. The server answers with [`ServerMessage::WebhookAdded`].
+    /// Facilitator only.
+    AddWebhook {
+        url: String,
+        #[serde(default)]
+        events: Vec<EventKind>,
+    },
+    /// Facilitator only.
+    RemoveWebhook {
+        webhook: WebhookId,
+    },
+    /// Asks for [`ServerMessage::Webhooks`]. Facilitator only.
+    ListWebhooks,
+}
+
+fn default_role() -> Role {
+    Role::Voter
+}
+
+/// Messages sent by the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    RoomCreated {
+        room: RoomId,
+        /// For a room started from a template, one invite per member of the
+        /// team, for the creator to pass on.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        invites: Vec<Invite>,
+    },
+    /// The template as saved, names trimmed.
+    TemplateSaved {
+        template: Template,
+        /// On servers without login, what it takes to replace or delete the
+        /// template later.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    TemplateDeleted {
+        name: String,
+    },
+    /// The server's room templates, by name.
+    Templates {
+        templates: Vec<Template>,
+    },
+    /// Confirms a [`ClientMessage::Authenticate`] with who the identity
+    /// provider says you are.
+    Authenticated {
+        name: String,
+        email: Option<String>,
+    },
+    /// Confirms a join or resume and carries everything needed to render the
+    /// room.
+    Welcome {
+        version: u32,
+        you: ParticipantId,
+        /// Secret that lets this participant [`resume`](ClientMessage::Resume)
+        /// their seat from a new connection.
+        token: String,
+        /// The participant's own vote, even while it is hidden from others.
+        your_vote: Option<Card>,
+        room: Box<RoomSnapshot>,
+    },
+    /// Confirms a [`ClientMessage::Leave`].
+    Left,
+    Pong,
+    ParticipantJoined {
+        participant: Participant,
+    },
+    ParticipantLeft {
+        participant: ParticipantId,
+    },
+    /// The facilitator removed a participant. When that is you, your seat is
+    /// gone and the connection may join a room again.
+    ParticipantKicked {
+        participant: ParticipantId,
+    },
+    FacilitatorChanged {
+        participant: ParticipantId,
+    },
+    RoleChanged {
+        participant: ParticipantId,
+        role: Role,
+    },
+    PresenceChanged {
+        participant: ParticipantId,
+        presence: Presence,
+    },
+    /// Someone voted. The card stays hidden until [`ServerMessage::Revealed`].
+    Voted {
+        participant: ParticipantId,
+    },
+    VoteRetracted {
+        participant: ParticipantId,
+    },
+    /// The votes are out. `votes` is empty in an anonymous room, where only
+    /// the summary is shared.
+    Revealed {
+        votes: Vec<VoteView>,
+        summary: Summary,
+    },
+    RoundReset,
+    /// A new round opened; `story_id` is set when it is about a backlog
+    /// story.
+    RoundStarted {
+        story: Option<String>,
+        #[serde(default)]
+        story_id: Option<StoryId>,
+    },
+    StoriesAdded {
+        stories: Vec<Story>,
+    },
+    StoryRemoved {
+        story: StoryId,
+    },
+    EstimateRecorded {
+        story: StoryId,
+        estimate: Card,
+    },
+    /// A recorded estimate could not be written back to the issue tracker.
+    /// Sent to whoever recorded it; the estimate stays recorded in the room.
+    EstimateSyncFailed {
+        story: StoryId,
+        message: String,
+    },
+    /// A countdown started on the current round. Both instants are read from
+    /// the server's clock.
+    TimerStarted {
+        started_at: Timestamp,
+        deadline: Timestamp,
+    },
+    /// The timer was stopped before it ran out.
+    TimerStopped,
+    /// The timer ran out. When the room reveals on expiry,
+    /// [`ServerMessage::Revealed`] follows if anyone had voted.
+    TimerExpired,
+    /// The deck changed and all votes were discarded.
+    DeckChanged {
+        deck: Deck,
+        cards: Vec<Card>,
+    },
+    SettingsChanged {
+        settings: Settings,
+    },
+    /// The current round is open for votes until `deadline`, read from the
+    /// server's clock.
+    VotingOpened {
+        opened_at: Timestamp,
+        deadline: Timestamp,
+        quorum: Option<u32>,
+    },
+    /// The voting window closed. [`ServerMessage::Revealed`] follows when it
+    /// closed on quorum, or at the deadline with votes cast.
+    VotingClosed {
+        reason: VotingClosed,
+    },
+    /// A webhook was subscribed. Sent to the facilitator only, with the
+    /// secret its payloads are signed with; it is not shown again.
+    WebhookAdded {
+        webhook: WebhookView,
+        secret: String,
+    },
+    WebhookRemoved {
+        webhook: WebhookId,
+    },
+    /// The room's webhooks, in the order they were added.
+    Webhooks {
+        webhooks: Vec<WebhookView>,
+    },
+    /// A notification could not be delivered to a webhook, retries included.
+    /// Sent to the facilitator; it stays on the webhook as its last failure.
+    WebhookFailed {
+        webhook: WebhookId,
+        failure: DeliveryFailure,
+    },
+    /// The last client message was rejected. Nothing changed.
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+impl ServerMessage {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        ServerMessage::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Event> for ServerMessage {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::ParticipantJoined(participant) => {
+                ServerMessage::ParticipantJoined { participant }
+            }
+            Event::ParticipantLeft(participant) => ServerMessage::ParticipantLeft { participant },
+            Event::ParticipantKicked(participant) => {
+                ServerMessage::ParticipantKicked { participant }
+            }
+            Event::FacilitatorChanged(participant) => {
+                ServerMessage::FacilitatorChanged { participant }
+            }
+            Event::RoleChanged(participant, role) => {
+                ServerMessage::RoleChanged { participant, role }
+            }
+            Event::PresenceChanged(participant, presence) => ServerMessage::PresenceChanged {
+                participant,
+                presence,
+            },
+            Event::VoteCast(participant) => ServerMessage::Voted { participant },
+            Event::VoteRetracted(participant) => ServerMessage::VoteRetracted { participant },
+            Event::Revealed { votes, summary } => ServerMessage::Revealed {
+                votes: votes
+                    .into_iter()
+                    .map(|(participant, card)| VoteView { participant, card })
+                    .collect(),
+                summary,
+            },
+            Event::RoundReset => ServerMessage::RoundReset,
+            Event::RoundStarted { story, story_id } => {
+                ServerMessage::RoundStarted { story, story_id }
+            }
+            Event::StoriesAdded(stories) => ServerMessage::StoriesAdded { stories },
+            Event::StoryRemoved(story) => ServerMessage::StoryRemoved { story },
+            Event::EstimateRecorded { story, estimate } => {
+                ServerMessage::EstimateRecorded { story, estimate }
+            }
+            Event::TimerStarted(timer) => ServerMessage::TimerStarted {
+                started_at: timer.started_at,
+                deadline: timer.deadline,
+            },
+            Event::TimerStopped => ServerMessage::TimerStopped,
+            Event::TimerExpired => ServerMessage::TimerExpired,
+            Event::DeckChanged(deck) => ServerMessage::DeckChanged {
+                cards: deck.cards().to_vec(),
+                deck,
+            },
+            Event::SettingsChanged(settings) => ServerMessage::SettingsChanged { settings },
+            Event::VotingOpened(window) => ServerMessage::VotingOpened {
+                opened_at: window.opened_at,
+                deadline: window.deadline,
+                quorum: window.quorum,
+            },
+            Event::VotingClosed { reason, .. } => ServerMessage::VotingClosed { reason },
+        }
+    }
+}
+
+impl From<&Error> for ServerMessage {
+    fn from(error: &Error) -> Self {
+        ServerMessage::error(ErrorCode::from(error), error.to_string())
+    }
+}
+
+/// Machine-readable reason attached to [`ServerMessage::Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UnsupportedVersion,
+    InvalidMessage,
+    RoomNotFound,
+    /// The resume token is unknown or its seat was given up.
+    InvalidSession,
+    /// Another connection resumed this seat; this one no longer holds it.
+    SessionReplaced,
+    NotJoined,
+    AlreadyJoined,
+    UnknownParticipant,
+    InvalidName,
+    InvalidDeck,
+    InvalidCard,
+    ObserverCannotVote,
+    RoundRevealed,
+    RoundNotRevealed,
+    NoVotes,
+    NoVoteCast,
+    NotFacilitator,
+    CannotKickSelf,
+    InvalidStory,
+    UnknownStory,
+    NoCurrentStory,
+    BacklogDone,
+    /// An imported document could not be read.
+    InvalidImport,
+    /// No issue tracker is configured, or it could not be reached.
+    TrackerUnavailable,
+    InvalidTimer,
+    NoTimer,
+    /// The room is private and the passphrase was missing or wrong.
+    InvalidPassphrase,
+    /// The server requires everyone to log in before creating or joining a
+    /// room.
+    LoginRequired,
+    /// The login session is unknown or has expired.
+    InvalidLogin,
+    /// The server could not reach the other instances it shares rooms with.
+    /// Nothing changed; try again.
+    Unavailable,
+    InvalidVotingPeriod,
+    InvalidQuorum,
+    /// The webhook URL is not an HTTP(S) URL, or the room has as many
+    /// webhooks as it may.
+    InvalidWebhook,
+    UnknownWebhook,
+    /// The template has no name or lists a member twice.
+    InvalidTemplate,
+    UnknownTemplate,
+    /// The template was saved by someone else; only they can replace or
+    /// delete it.
+    NotTemplateOwner,
+    /// The invite was not handed out for this room.
+    InvalidInvite,
+    /// The room seats as many participants as the server allows.
+    RoomFull,
+    /// The client sent messages or created rooms faster than the server
+    /// allows. Nothing changed; slow down and try again.
+    RateLimited,
+}
+
+impl From<&Error> for ErrorCode {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::UnknownParticipant(_) => ErrorCode::UnknownParticipant,
+            Error::EmptyName => ErrorCode::InvalidName,
+            Error::EmptyCard | Error::EmptyDeck | Error::DuplicateCard(_) => ErrorCode::InvalidDeck,
+            Error::CardNotInDeck(_) => ErrorCode::InvalidCard,
+            Error::ObserverCannotVote => ErrorCode::ObserverCannotVote,
+            Error::RoundRevealed => ErrorCode::RoundRevealed,
+            Error::RoundNotRevealed => ErrorCode::RoundNotRevealed,
+            Error::NoVotes => ErrorCode::NoVotes,
+            Error::NoVoteCast(_) => ErrorCode::NoVoteCast,
+            Error::NotFacilitator(_) => ErrorCode::NotFacilitator,
+            Error::CannotKickSelf => ErrorCode::CannotKickSelf,
+            Error::EmptyStory => ErrorCode::InvalidStory,
+            Error::UnknownStory(_) => ErrorCode::UnknownStory,
+            Error::NoCurrentStory => ErrorCode::NoCurrentStory,
+            Error::BacklogDone => ErrorCode::BacklogDone,
+            Error::InvalidTimer => ErrorCode::InvalidTimer,
+            Error::NoTimerLength => ErrorCode::InvalidTimer,
+            Error::NoTimer => ErrorCode::NoTimer,
+            Error::InvalidVotingPeriod => ErrorCode::InvalidVotingPeriod,
+            Error::InvalidQuorum => ErrorCode::InvalidQuorum,
+            Error::EmptyTemplateName | Error::DuplicateMember(_) => ErrorCode::InvalidTemplate,
+            Error::UnknownMember(_) => ErrorCode::InvalidInvite,
+            Error::MemberSeated(_) => ErrorCode::AlreadyJoined,
+        }
+    }
+}
+
+/// A participant as shown to others: whether they voted, never what.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantView {
+    pub id: ParticipantId,
+    pub name: String,
+    pub role: Role,
+    pub presence: Presence,
+    pub voted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteView {
+    pub participant: ParticipantId,
+    pub card: Card,
+}
+
+/// What takes a member of a template's team to their seat in a room started
+/// from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invite {
+    /// The member, as the template lists them.
+    pub name: String,
+    /// Passed to [`ClientMessage::Join`] as `invite`.
+    pub token: String,
+}
+
+/// A webhook subscription as shown to the facilitator, without its secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookView {
+    pub id: WebhookId,
+    pub url: String,
+    /// Empty when the webhook hears about everything.
+    pub events: Vec<EventKind>,
+    pub last_failure: Option<DeliveryFailure>,
+}
+
+impl From<&Subscription> for WebhookView {
+    fn from(subscription: &Subscription) -> Self {
+        WebhookView {
+            id: subscription.id,
+            url: subscription.url.clone(),
+            events: subscription.events.clone(),
+            last_failure: subscription.last_failure.clone(),
+        }
+    }
+}
+
+/// The state of a room as seen by a newly joined participant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub id: RoomId,
+    pub name: String,
+    pub deck: Deck,
+    /// The deck's cards in display order.
+    pub cards: Vec<Card>,
+    pub settings: Settings,
+    pub facilitator: Option<ParticipantId>,
+    pub story: Option<String>,
+    pub phase: Phase,
+    pub participants: Vec<ParticipantView>,
+    /// Present once the round is revealed, unless the room is anonymous.
+    pub votes: Option<Vec<VoteView>>,
+    pub summary: Option<Summary>,
+    /// The backlog in order, with estimates and round history.
+    pub stories: Vec<Story>,
+    /// The backlog story being estimated, if any.
+    pub current_story: Option<StoryId>,
+    /// The countdown on the current round, if one is running.
+    pub timer: Option<Timer>,
+    /// The period the current round is open for votes, if it was opened for
+    /// one.
+    pub voting: Option<VotingWindow>,
+    /// Who the room's template says moderates.
+    pub moderator: Option<String>,
+    /// The team the room was set up for, whether they joined or not.
+    pub members: Vec<Member>,
+}
+
+impl RoomSnapshot {
+    pub fn of(room: &Room) -> Self {
+        let round = room.round();
+        RoomSnapshot {
+            id: room.id().clone(),
+            name: room.name().to_owned(),
+            deck: room.deck().clone(),
+            cards: room.deck().cards().to_vec(),
+            settings: room.settings().clone(),
+            facilitator: room.facilitator(),
+            story: round.story().map(str::to_owned),
+            phase: round.phase(),
+            participants: room
+                .participants()
+                .map(|p| ParticipantView {
+                    id: p.id,
+                    name: p.name.clone(),
+                    role: p.role,
+                    presence: p.presence,
+                    voted: round.has_voted(p.id),
+                })
+                .collect(),
+            votes: room.visible_votes().map(|votes| {
+                votes
+                    .iter()
+                    .map(|(&participant, card)| VoteView {
+                        participant,
+                        card: card.clone(),
+                    })
+                    .collect()
+            }),
+            summary: room.summary(),
+            stories: room
+                .stories()
+                .iter()
+                .cloned()
+                .map(|mut story| {
+                    if room.settings().anonymous {
+                        for round in &mut story.rounds {
+                            round.votes.clear();
+                        }
+                    }
+                    story
+                })
+                .collect(),
+            current_story: room.current_story().map(|s| s.id),
+            timer: room.timer().copied(),
+            voting: room.voting().copied(),
+            moderator: room.moderator().map(str::to_owned),
+            members: room.members().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn client_messages_use_snake_case_tags() {
+        let msg: ClientMessage = serde_json::from_value(json!({
+            "type": "join",
+            "version": 1,
+            "room": "abc",
+            "name": "Alice"
+        }))
+        .unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::Join {
+                version: 1,
+                room: RoomId::new("abc"),
+                name: "Alice".into(),
+                role: Role::Voter,
+                passphrase: None,
+                invite: None,
+            }
+        );
+        let msg: ClientMessage = serde_json::from_value(json!({"type": "retract_vote"})).unwrap();
+        assert_eq!(msg, ClientMessage::RetractVote);
+    }
+
+    #[test]
+    fn import_mapping_defaults_per_field() {
+        let msg: ClientMessage = serde_json::from_value(json!({
+            "type": "import_stories",
+            "format": "csv",
+            "data": "Summary\nLogin\n",
+            "mapping": {"title": "Summary"}
+        }))
+        .unwrap();
+        let ClientMessage::ImportStories {
+            format, mapping, ..
+        } = msg
+        else {
+            panic!("unexpected {msg:?}");
+        };
+        assert_eq!(format, Format::Csv);
+        assert_eq!(mapping.title, "Summary");
+        assert_eq!(mapping.key.as_deref(), Some("key"));
+    }
+
+    #[test]
+    fn create_room_defaults_to_fibonacci() {
+        let msg: ClientMessage =
+            serde_json::from_value(json!({"type": "create_room", "version": 1, "name": "R"}))
+                .unwrap();
+        let ClientMessage::CreateRoom { deck, template, .. } = msg else {
+            panic!("unexpected {msg:?}");
+        };
+        assert_eq!(deck, Deck::fibonacci());
+        assert_eq!(template, None);
+    }
+
+    #[test]
+    fn timers_may_leave_their_length_to_the_room() {
+        let msg: ClientMessage = serde_json::from_value(json!({"type": "start_timer"})).unwrap();
+        assert_eq!(msg, ClientMessage::StartTimer { seconds: None });
+    }
+
+    #[test]
+    fn hidden_vote_event_carries_no_card() {
+        let msg = ServerMessage::from(Event::VoteCast(ParticipantId(3)));
+        assert_eq!(
+            serde_json::to_value(msg).unwrap(),
+            json!({"type": "voted", "participant": 3})
+        );
+    }
+
+    #[test]
+    fn timer_events_carry_server_instants() {
+        let mut room = Room::new(RoomId::new("r"), "R", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.start_timer(
+            alice,
+            Some(std::time::Duration::from_secs(90)),
+            Timestamp(5_000),
+        )
+        .unwrap();
+        let event = room.drain_events().pop().unwrap();
+        assert_eq!(
+            serde_json::to_value(ServerMessage::from(event)).unwrap(),
+            json!({"type": "timer_started", "started_at": 5000, "deadline": 95000})
+        );
+        assert_eq!(
+            RoomSnapshot::of(&room).timer.map(|t| t.deadline),
+            Some(Timestamp(95_000))
+        );
+    }
+
+    #[test]
+    fn voting_windows_travel_with_their_quorum() {
+        let msg: ClientMessage =
+            serde_json::from_value(json!({"type": "open_voting", "seconds": 86400})).unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::OpenVoting {
+                seconds: 86_400,
+                quorum: None
+            }
+        );
+        let mut room = Room::new(RoomId::new("r"), "R", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.open_voting(
+            alice,
+            std::time::Duration::from_secs(60),
+            Some(3),
+            Timestamp(0),
+        )
+        .unwrap();
+        let event = room.drain_events().pop().unwrap();
+        assert_eq!(
+            serde_json::to_value(ServerMessage::from(event)).unwrap(),
+            json!({"type": "voting_opened", "opened_at": 0, "deadline": 60000, "quorum": 3})
+        );
+        assert_eq!(
+            serde_json::to_value(ServerMessage::VotingClosed {
+                reason: VotingClosed::Quorum
+            })
+            .unwrap(),
+            json!({"type": "voting_closed", "reason": "quorum"})
+        );
+    }
+
+    #[test]
+    fn webhook_filters_default_to_everything() {
+        let msg: ClientMessage =
+            serde_json::from_value(json!({"type": "add_webhook", "url": "https://x.test/hook"}))
+                .unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::AddWebhook {
+                url: "https://x.test/hook".into(),
+                events: Vec::new(),
+            }
+        );
+        let failed = ServerMessage::WebhookFailed {
+            webhook: WebhookId(2),
+            failure: DeliveryFailure {
+                at: Timestamp(7_000),
+                event: EventKind::RoundRevealed,
+                attempts: 5,
+                message: "webhook answered 503: busy".into(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(failed).unwrap(),
+            json!({
+                "type": "webhook_failed",
+                "webhook": 2,
+                "failure": {
+                    "at": 7000,
+                    "event": "round_revealed",
+                    "attempts": 5,
+                    "message": "webhook answered 503: busy"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn errors_carry_a_code() {
+        let msg = ServerMessage::from(&Error::ObserverCannotVote);
+        assert_eq!(
+            serde_json::to_value(msg).unwrap(),
+            json!({"type": "error", "code": "observer_cannot_vote", "message": "observers cannot vote"})
+        );
+    }
+
+    #[test]
+    fn snapshot_hides_votes_until_reveal() {
+        let mut room = Room::new(RoomId::new("r"), "R", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, Card::new("5"), Timestamp(0)).unwrap();
+        let snapshot = RoomSnapshot::of(&room);
+        assert!(snapshot.participants[0].voted);
+        assert_eq!(snapshot.votes, None);
+        room.reveal(alice, Timestamp(0)).unwrap();
+        let snapshot = RoomSnapshot::of(&room);
+        assert_eq!(
+            snapshot.votes,
+            Some(vec![VoteView {
+                participant: alice,
+                card: Card::new("5")
+            }])
+        );
+        assert!(snapshot.summary.is_some());
+    }
+
+    #[test]
+    fn anonymous_snapshot_names_no_voters() {
+        let mut room = Room::new(RoomId::new("r"), "R", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.add_stories(alice, vec!["Login".into()]).unwrap();
+        room.next_story(alice).unwrap();
+        room.vote(alice, Card::new("5"), Timestamp(0)).unwrap();
+        room.reveal(alice, Timestamp(0)).unwrap();
+        assert_eq!(RoomSnapshot::of(&room).stories[0].rounds[0].votes.len(), 1);
+
+        let settings = Settings {
+            anonymous: true,
+            ..Settings::default()
+        };
+        room.update_settings(alice, settings, Timestamp(0)).unwrap();
+        let snapshot = RoomSnapshot::of(&room);
+        assert_eq!(snapshot.votes, None);
+        assert!(snapshot.summary.is_some());
+        assert!(snapshot.stories[0].rounds[0].votes.is_empty());
+    }
+}
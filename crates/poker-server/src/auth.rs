@@ -0,0 +1,90 @@
+use std::time::{Duration, SystemTime};
+
+use poker_auth::{AuthError, Identity, OidcClient};
+use poker_broker::{Broker, BrokerError};
+
+/// How long the identity provider has to send the browser back.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Why a login could not be completed.
+#[derive(Debug, thiserror::Error)]
+pub enum LoginError {
+    #[error("the login is unknown or took too long; start over")]
+    UnknownState,
+    #[error(transparent)]
+    Provider(#[from] AuthError),
+    #[error(transparent)]
+    Broker(#[from] BrokerError),
+}
+
+/// Logs users in through an OpenID Connect provider.
+///
+/// A browser is sent to `/auth/login`, comes back to `/auth/callback` with a
+/// session token, and the client hands that token to the server with
+/// `authenticate` on its WebSocket. Logins in progress and sessions are kept
+/// in the [`Broker`], so each step may reach a different instance of the
+/// cluster. Sessions last as long as the provider's ID token.
+pub struct Login {
+    client: OidcClient,
+}
+
+impl Login {
+    pub fn new(client: OidcClient) -> Self {
+        Login { client }
+    }
+
+    /// Starts a login and returns the provider URL to send the browser to.
+    pub async fn start(&self, broker: &dyn Broker) -> Result<String, LoginError> {
+        let login = self.client.start_login();
+        broker
+            .set(&pending_key(&login.state), &login.nonce, LOGIN_TIMEOUT)
+            .await?;
+        Ok(login.url)
+    }
+
+    /// Completes the login identified by `state` and opens a session for it.
+    pub async fn finish(
+        &self,
+        broker: &dyn Broker,
+        state: &str,
+        code: &str,
+    ) -> Result<(String, Identity), LoginError> {
+        let key = pending_key(state);
+        let nonce = broker.get(&key).await?.ok_or(LoginError::UnknownState)?;
+        if !broker.delete(&key).await? {
+            // Someone else finished it in the meantime.
+            return Err(LoginError::UnknownState);
+        }
+        let identity = self.client.finish_login(code, &nonce).await?;
+        let session = poker_auth::random_secret();
+        let ttl = identity
+            .expires_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        let json = serde_json::to_string(&identity).expect("identities serialize");
+        broker.set(&session_key(&session), &json, ttl).await?;
+        Ok((session, identity))
+    }
+
+    /// The user a session belongs to, unless it is unknown or has expired.
+    pub async fn session(
+        &self,
+        broker: &dyn Broker,
+        session: &str,
+        now: SystemTime,
+    ) -> Result<Option<Identity>, LoginError> {
+        let Some(json) = broker.get(&session_key(session)).await? else {
+            return Ok(None);
+        };
+        let identity: Option<Identity> = serde_json::from_str(&json).ok();
+        Ok(identity.filter(|identity| identity.expires_at > now))
+    }
+}
+
+fn pending_key(state: &str) -> String {
+    format!("poker:login:{state}")
+}
+
+fn session_key(session: &str) -> String {
+    format!("poker:session:{session}")
+}
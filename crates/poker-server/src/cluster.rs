@@ -0,0 +1,153 @@
+//! Relaying clients between the instances of a cluster.
+//!
+//! Every room is hosted by exactly one instance, recorded in the broker under
+//! [`room_key`] for as long as the host keeps renewing its [`LEASE`]. A client
+//! that joins or resumes a room hosted elsewhere stays connected to the
+//! instance it reached, which relays its messages to the host and passes the
+//! replies back. The host runs the relayed connection exactly like a local
+//! one, so a room only ever changes in one place and every client sees the
+//! same sequence of events.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use poker_auth::Identity;
+use poker_broker::{BrokerError, Subscription};
+use poker_core::RoomId;
+use poker_protocol::{ClientMessage, ServerMessage};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::connection;
+use crate::hub::{ConnectionId, Hub};
+use crate::outbox::Outbox;
+
+/// How long a claim on a room lasts unless its host renews it. A room whose
+/// host went away can be taken over once this has passed.
+pub(crate) const LEASE: Duration = Duration::from_secs(30);
+
+/// How long to wait before subscribing again after the broker connection
+/// was lost.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Broker key naming the instance that hosts a room.
+pub(crate) fn room_key(id: &RoomId) -> String {
+    format!("poker:room:{id}")
+}
+
+/// Broker topic an instance receives relayed traffic on.
+pub(crate) fn topic(instance: &str) -> String {
+    format!("poker:instance:{instance}")
+}
+
+/// What instances send each other. `conn` always identifies the connection
+/// on the relaying instance `from`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Envelope {
+    /// A client is being relayed to this instance; its messages follow.
+    Open {
+        from: String,
+        conn: u64,
+        identity: Option<Identity>,
+        /// The client's address, so that this instance limits the client
+        /// like one connected to it. Unset by instances that predate it.
+        #[serde(default)]
+        peer: Option<IpAddr>,
+    },
+    Frame {
+        from: String,
+        conn: u64,
+        msg: ClientMessage,
+    },
+    /// The relayed client disconnected.
+    Closed { from: String, conn: u64 },
+    /// A message for a client this instance relays.
+    Deliver { conn: u64, msg: ServerMessage },
+}
+
+/// Publishes an envelope to another instance.
+pub(crate) async fn send(
+    hub: &Hub,
+    instance: &str,
+    envelope: &Envelope,
+) -> Result<(), BrokerError> {
+    let json = serde_json::to_string(envelope).expect("envelopes serialize");
+    hub.broker().publish(&topic(instance), &json).await
+}
+
+/// Serves what other instances send this one, resubscribing whenever the
+/// broker connection is lost. Runs until the task is aborted.
+pub(crate) async fn listen(hub: Arc<Hub>, mut inbox: Subscription) {
+    // Connections relayed here, by relaying instance and connection.
+    let mut relayed = HashMap::new();
+    loop {
+        while let Some(payload) = inbox.recv().await {
+            match serde_json::from_str(&payload) {
+                Ok(envelope) => receive(&hub, &mut relayed, envelope),
+                Err(err) => tracing::warn!("dropping malformed relay message: {err}"),
+            }
+        }
+        tracing::warn!("lost the broker subscription; resubscribing");
+        inbox = loop {
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            match hub.broker().subscribe(&topic(hub.instance())).await {
+                Ok(inbox) => break inbox,
+                Err(err) => tracing::warn!("resubscribing failed: {err}"),
+            }
+        };
+    }
+}
+
+fn receive(
+    hub: &Arc<Hub>,
+    relayed: &mut HashMap<(String, u64), mpsc::UnboundedSender<ClientMessage>>,
+    envelope: Envelope,
+) {
+    match envelope {
+        Envelope::Open {
+            from,
+            conn,
+            identity,
+            peer,
+        } => {
+            let (inbox, queue) = mpsc::unbounded_channel();
+            let outbox = reply_to(hub.clone(), from.clone(), conn);
+            tokio::spawn(connection::run_relayed(
+                hub.clone(),
+                queue,
+                outbox,
+                identity,
+                peer,
+            ));
+            relayed.insert((from, conn), inbox);
+        }
+        Envelope::Frame { from, conn, msg } => {
+            if let Some(inbox) = relayed.get(&(from, conn)) {
+                let _ = inbox.send(msg);
+            }
+        }
+        // Dropping the inbox ends the relayed connection, which holds its
+        // seat for the grace period like any other.
+        Envelope::Closed { from, conn } => {
+            relayed.remove(&(from, conn));
+        }
+        Envelope::Deliver { conn, msg } => hub.deliver(ConnectionId(conn), msg),
+    }
+}
+
+/// An outbox whose messages are sent back to the relaying instance.
+fn reply_to(hub: Arc<Hub>, instance: String, conn: u64) -> Outbox {
+    let (outbox, mut queue) = Outbox::new();
+    tokio::spawn(async move {
+        while let Some(msg) = queue.recv().await {
+            let envelope = Envelope::Deliver { conn, msg };
+            if let Err(err) = send(&hub, &instance, &envelope).await {
+                tracing::warn!(%instance, conn, "relaying a reply failed: {err}");
+            }
+        }
+    });
+    outbox
+}
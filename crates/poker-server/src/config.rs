@@ -0,0 +1,58 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::limits::Rate;
+
+/// Tunables for a [`Hub`](crate::Hub).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// How long the seat of a disconnected participant is held for them to
+    /// resume.
+    pub grace_period: Duration,
+    /// How long a connected client may stay silent before it is shown as idle.
+    /// Clients are expected to `ping` more often than this.
+    pub idle_after: Duration,
+    /// Messages one connection may send. Messages beyond it are refused.
+    pub message_rate: Rate,
+    /// WebSocket connections one IP address may open.
+    pub connection_rate: Rate,
+    /// Rooms one IP address may create.
+    pub room_rate: Rate,
+    /// Wrong passphrases that may be tried against one private room, and
+    /// from one IP address. Joins beyond it are refused until the allowance
+    /// refills; right passphrases do not count.
+    pub passphrase_rate: Rate,
+    /// Largest message a client may send, in bytes. A client that sends a
+    /// larger one is disconnected.
+    pub max_message_size: usize,
+    /// Most participants one room may seat.
+    pub max_room_size: usize,
+    /// How long a room nobody is seated in is kept before it is closed.
+    pub idle_room_ttl: Duration,
+    /// Hosts, by name or IP address, that webhooks facilitators add may be
+    /// posted to although they are not on the public internet. All others
+    /// must resolve to public addresses only.
+    pub webhook_allowlist: Vec<String>,
+    /// Addresses of the reverse proxies or load balancers in front of the
+    /// server. Requests they pass on are limited by the client address they
+    /// give in `Forwarded` or `X-Forwarded-For` rather than by their own.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            grace_period: Duration::from_secs(120),
+            idle_after: Duration::from_secs(30),
+            message_rate: Rate::per_second(20),
+            connection_rate: Rate::per_minute(30),
+            room_rate: Rate::per_minute(10),
+            passphrase_rate: Rate::per_minute(10),
+            max_message_size: 1024 * 1024,
+            max_room_size: 100,
+            idle_room_ttl: Duration::from_secs(24 * 60 * 60),
+            webhook_allowlist: Vec::new(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,761 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use axum::extract::ws::{Message, WebSocket};
+use futures_util::{SinkExt, StreamExt};
+use poker_auth::Identity;
+use poker_broker::BrokerError;
+use poker_core::{Action, Card, ParticipantId, Result, Room, RoomId, StoryId};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, WebhookView, PROTOCOL_VERSION};
+use poker_store::{TemplateOwner, TemplateRecord};
+use tokio::sync::mpsc;
+use tracing::Instrument;
+
+use crate::cluster::{self, Envelope};
+use crate::hub::{ConnectionId, Hub, Joiner, Location, RoomHandle, SeatError};
+use crate::limits::Bucket;
+use crate::outbox::Outbox;
+
+/// Drives one WebSocket from `peer` until the client goes away.
+pub(crate) async fn run(socket: WebSocket, hub: Arc<Hub>, peer: IpAddr) {
+    let (mut sink, mut stream) = socket.split();
+    let (outbox, mut queue) = Outbox::new();
+    let metrics = hub.metrics();
+
+    // The writer stops once every outbox clone is dropped, i.e. after the
+    // connection has left its room and `Connection` itself is gone, or as
+    // soon as the outbox overflows, which also ends the reader below.
+    let writer = tokio::spawn({
+        let metrics = metrics.clone();
+        async move {
+            while let Some(msg) = queue.recv().await {
+                let text = serde_json::to_string(&msg).expect("server messages serialize");
+                metrics.message_sent();
+                if sink.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        }
+    });
+
+    let rate = hub.config().message_rate;
+    let mut allowance = Bucket::full(rate, Instant::now());
+    // Messages dropped since the last one that was let through. The client
+    // is told once; one that sends another burst's worth is cut off.
+    let mut dropped = 0;
+    let overflow = outbox.overflow();
+    let mut conn = Connection::new(hub, outbox, None, Some(peer));
+    let span = conn.span();
+    metrics.client_connected();
+    async {
+        tracing::info!(%peer, "connection opened");
+        loop {
+            let frame = tokio::select! {
+                frame = stream.next() => frame,
+                () = overflow.wait() => {
+                    tracing::info!("closing a connection that fell behind");
+                    break;
+                }
+            };
+            let Some(Ok(frame)) = frame else {
+                break;
+            };
+            match frame {
+                Message::Text(text) => {
+                    metrics.message_received();
+                    if allowance.take(rate, Instant::now()) {
+                        dropped = 0;
+                        conn.handle_text(&text).await
+                    } else if dropped < rate.count {
+                        tracing::debug!("dropping a message over the rate limit");
+                        if dropped == 0 {
+                            conn.send(rate_limited("you are sending messages too fast"));
+                        }
+                        dropped += 1;
+                    } else {
+                        tracing::info!("closing a connection that keeps flooding");
+                        break;
+                    }
+                }
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        conn.disconnect().await;
+        tracing::info!("connection closed");
+    }
+    .instrument(span)
+    .await;
+    metrics.client_disconnected();
+    drop(conn);
+    let _ = writer.await;
+}
+
+/// Drives a connection relayed from another instance until that instance
+/// reports it closed.
+pub(crate) async fn run_relayed(
+    hub: Arc<Hub>,
+    mut inbox: mpsc::UnboundedReceiver<ClientMessage>,
+    outbox: Outbox,
+    identity: Option<Identity>,
+    peer: Option<IpAddr>,
+) {
+    let mut conn = Connection::new(hub, outbox, identity, peer);
+    let span = conn.span();
+    async {
+        tracing::info!("relayed connection opened");
+        while let Some(msg) = inbox.recv().await {
+            conn.handle(msg).await;
+        }
+        conn.disconnect().await;
+        tracing::info!("relayed connection closed");
+    }
+    .instrument(span)
+    .await;
+}
+
+struct Seat {
+    room: Arc<RoomHandle>,
+    participant: ParticipantId,
+}
+
+struct Connection {
+    id: ConnectionId,
+    hub: Arc<Hub>,
+    outbox: Outbox,
+    seat: Option<Seat>,
+    /// Who logged in on this connection, on servers that require login.
+    identity: Option<Identity>,
+    /// The instance this connection is relayed to, once it joined a room
+    /// hosted there. Everything the client sends is passed on from then on.
+    relay: Option<String>,
+    /// The client's address, as passed on by the instance that relays the
+    /// connection. Unknown only for connections relayed by instances that
+    /// predate passing it on; their rooms and passphrase guesses are not
+    /// limited per address.
+    peer: Option<IpAddr>,
+}
+
+impl Connection {
+    fn new(
+        hub: Arc<Hub>,
+        outbox: Outbox,
+        identity: Option<Identity>,
+        peer: Option<IpAddr>,
+    ) -> Self {
+        Connection {
+            id: hub.next_connection_id(),
+            hub,
+            outbox,
+            seat: None,
+            identity,
+            relay: None,
+            peer,
+        }
+    }
+
+    /// Span that everything logged on behalf of this connection belongs to.
+    /// `room` is filled in once the connection takes a seat.
+    fn span(&self) -> tracing::Span {
+        tracing::info_span!("connection", conn = %self.id, room = tracing::field::Empty)
+    }
+
+    fn send(&self, msg: ServerMessage) {
+        self.outbox.send(msg);
+    }
+
+    async fn handle_text(&mut self, text: &str) {
+        match serde_json::from_str::<ClientMessage>(text) {
+            Ok(msg) => self.handle(msg).await,
+            Err(err) => self.send(ServerMessage::error(
+                ErrorCode::InvalidMessage,
+                err.to_string(),
+            )),
+        }
+    }
+
+    async fn handle(&mut self, msg: ClientMessage) {
+        if let Some(host) = &self.relay {
+            let frame = Envelope::Frame {
+                from: self.hub.instance().to_owned(),
+                conn: self.id.0,
+                msg,
+            };
+            if let Err(err) = cluster::send(&self.hub, host, &frame).await {
+                self.send(unavailable(&err));
+            }
+            return;
+        }
+        if let (
+            None,
+            ClientMessage::Join { version, room, .. } | ClientMessage::Resume { version, room, .. },
+        ) = (&self.seat, &msg)
+        {
+            // Refuse what would be refused anyway before asking the broker
+            // and the database where the room is.
+            if !self.check_version(*version) {
+                return;
+            }
+            if matches!(msg, ClientMessage::Join { .. }) && self.check_login().is_none() {
+                return;
+            }
+            match self.hub.locate(room).await {
+                Ok(Location::Elsewhere(host)) => return self.relay_to(host, msg).await,
+                Ok(Location::Here | Location::Nowhere) => {}
+                Err(err) => return self.send(unavailable(&err)),
+            }
+        }
+        match msg {
+            ClientMessage::CreateRoom {
+                version,
+                name,
+                deck,
+                passphrase,
+                template,
+            } => {
+                if !self.check_version(version) || self.check_login().is_none() {
+                    return;
+                }
+                if let Some(peer) = self.peer {
+                    if !self.hub.allow_room_creation(peer) {
+                        return self.send(rate_limited("you are creating rooms too fast"));
+                    }
+                }
+                let passphrase = passphrase.as_deref();
+                let created = match template {
+                    Some(template) => self
+                        .hub
+                        .create_room_from_template(&name, &template, passphrase)
+                        .await
+                        .map_err(|err| ServerMessage::from(&err)),
+                    None => self
+                        .hub
+                        .create_room(&name, deck, passphrase)
+                        .await
+                        .map(|room| (room, Vec::new()))
+                        .map_err(|err| unavailable(&err)),
+                };
+                match created {
+                    Ok((room, invites)) => self.send(ServerMessage::RoomCreated { room, invites }),
+                    Err(msg) => self.send(msg),
+                }
+            }
+            ClientMessage::SaveTemplate { template, token } => {
+                let Some(identity) = self.check_login() else {
+                    return;
+                };
+                match self
+                    .hub
+                    .save_template(template, template_claim(identity, token))
+                {
+                    Ok(TemplateRecord { template, owner }) => {
+                        let token = match owner {
+                            Some(TemplateOwner::Token(token)) => Some(token),
+                            _ => None,
+                        };
+                        self.send(ServerMessage::TemplateSaved { template, token })
+                    }
+                    Err(err) => self.send(ServerMessage::from(&err)),
+                }
+            }
+            ClientMessage::DeleteTemplate { name, token } => {
+                let Some(identity) = self.check_login() else {
+                    return;
+                };
+                let claim = template_claim(identity, token);
+                match self.hub.delete_template(&name, claim.as_ref()) {
+                    Ok(()) => self.send(ServerMessage::TemplateDeleted {
+                        name: name.trim().to_owned(),
+                    }),
+                    Err(err) => self.send(ServerMessage::from(&err)),
+                }
+            }
+            ClientMessage::ListTemplates => {
+                if self.check_login().is_some() {
+                    match self.hub.templates() {
+                        Ok(templates) => self.send(ServerMessage::Templates { templates }),
+                        Err(err) => self.send(ServerMessage::from(&err)),
+                    }
+                }
+            }
+            ClientMessage::Join {
+                version,
+                room,
+                name,
+                role,
+                passphrase,
+                invite,
+            } => {
+                if !self.check_version(version) {
+                    return;
+                }
+                let Some(identity) = self.check_login() else {
+                    return;
+                };
+                // Logged-in users go by the name their identity provider knows.
+                let name = identity.map_or(name, |identity| identity.name);
+                if self.holds_seat() {
+                    return self.send(ServerMessage::error(
+                        ErrorCode::AlreadyJoined,
+                        "leave the current room first",
+                    ));
+                }
+                let Some(handle) = self.find_room(&room) else {
+                    return;
+                };
+                let joiner = match &invite {
+                    Some(invite) => Joiner::Invited(invite),
+                    None => Joiner::Guest { name: &name, role },
+                };
+                let seated = handle
+                    .join(
+                        joiner,
+                        passphrase.as_deref(),
+                        self.peer,
+                        self.id,
+                        self.outbox.clone(),
+                        Instant::now(),
+                    )
+                    .await;
+                self.take_seat(handle, seated);
+            }
+            ClientMessage::Resume {
+                version,
+                room,
+                token,
+            } => {
+                if !self.check_version(version) {
+                    return;
+                }
+                if self.holds_seat() {
+                    return self.send(ServerMessage::error(
+                        ErrorCode::AlreadyJoined,
+                        "leave the current room first",
+                    ));
+                }
+                let Some(handle) = self.find_room(&room) else {
+                    return;
+                };
+                let seated = handle.resume(&token, self.id, self.outbox.clone(), Instant::now());
+                self.take_seat(handle, seated);
+            }
+            ClientMessage::Authenticate { session } => self.authenticate(&session).await,
+            ClientMessage::Leave => {
+                let Some(seat) = self.seat.take() else {
+                    return self.send(not_joined());
+                };
+                match seat.room.leave(seat.participant, self.id) {
+                    Ok(()) => self.send(ServerMessage::Left),
+                    Err(err) => self.send(ServerMessage::from(&err)),
+                }
+            }
+            ClientMessage::Ping => {
+                if let Some(seat) = &self.seat {
+                    let touched = seat.room.touch(seat.participant, self.id, Instant::now());
+                    if let Err(err) = touched {
+                        return self.reject(err);
+                    }
+                }
+                self.send(ServerMessage::Pong);
+            }
+            ClientMessage::SetRole { role } => {
+                let now = self.hub.now();
+                self.apply(|room, id| room.set_role(id, role, now))
+            }
+            ClientMessage::Vote { card } => {
+                let now = self.hub.now();
+                self.apply(|room, id| room.vote(id, card, now))
+            }
+            ClientMessage::RetractVote => self.apply(|room, id| room.retract_vote(id)),
+            ClientMessage::Reveal => {
+                let now = self.hub.now();
+                self.apply(|room, id| room.reveal(id, now))
+            }
+            ClientMessage::Reset => self.apply(|room, id| room.reset(id)),
+            ClientMessage::StartRound { story } => {
+                self.apply(|room, id| room.start_round(id, story))
+            }
+            ClientMessage::AddStories { stories } => {
+                self.apply(|room, id| room.add_stories(id, stories).map(drop))
+            }
+            ClientMessage::ImportStories {
+                format,
+                data,
+                mapping,
+            } => match poker_exchange::import(format, &data, &mapping) {
+                Ok(stories) => self.apply(|room, id| room.add_stories(id, stories).map(drop)),
+                Err(err) => self.send(ServerMessage::error(
+                    ErrorCode::InvalidImport,
+                    err.to_string(),
+                )),
+            },
+            ClientMessage::FetchStories { query } => self.fetch_stories(query),
+            ClientMessage::RemoveStory { story } => {
+                self.apply(|room, id| room.remove_story(id, story))
+            }
+            ClientMessage::StartStory { story } => {
+                self.apply(|room, id| room.start_story(id, story))
+            }
+            ClientMessage::NextStory => self.apply(|room, id| room.next_story(id).map(drop)),
+            ClientMessage::RecordEstimate { estimate } => {
+                let now = self.hub.now();
+                let recorded = self.try_apply(|room, id| {
+                    room.record_estimate(id, estimate.clone(), now)?;
+                    Ok(room.current_story().map(|s| (s.id, s.key.clone())))
+                });
+                if let Some(Some((story, Some(key)))) = recorded {
+                    self.sync_estimate(story, key, estimate);
+                }
+            }
+            ClientMessage::StartTimer { seconds } => {
+                let now = self.hub.now();
+                self.apply(|room, id| {
+                    room.start_timer(id, seconds.map(Duration::from_secs), now)
+                        .map(drop)
+                })
+            }
+            ClientMessage::StopTimer => self.apply(|room, id| room.stop_timer(id)),
+            ClientMessage::OpenVoting { seconds, quorum } => {
+                let now = self.hub.now();
+                self.apply(|room, id| {
+                    room.open_voting(id, Duration::from_secs(seconds), quorum, now)
+                        .map(drop)
+                })
+            }
+            ClientMessage::Kick { participant } => {
+                let now = self.hub.now();
+                self.apply(|room, id| room.kick(id, participant, now))
+            }
+            ClientMessage::HandOver { participant } => {
+                self.apply(|room, id| room.hand_over(id, participant))
+            }
+            ClientMessage::SetDeck { deck } => self.apply(|room, id| room.set_deck(id, deck)),
+            ClientMessage::UpdateSettings { settings } => {
+                let now = self.hub.now();
+                self.apply(|room, id| room.update_settings(id, settings, now))
+            }
+            ClientMessage::AddWebhook { url, events } => {
+                if self.seat.is_none() {
+                    return self.send(not_joined());
+                }
+                // Resolving the host takes a while, so it is checked before
+                // the room is locked; every delivery checks it again.
+                let allowed = &self.hub.config().webhook_allowlist;
+                if let Err(err) = poker_webhook::check_public(url.trim(), allowed).await {
+                    return self.send(ServerMessage::error(
+                        ErrorCode::InvalidWebhook,
+                        err.to_string(),
+                    ));
+                }
+                let added = self.on_seat(|room, id, conn| {
+                    room.add_webhook(id, conn, Instant::now(), &url, events)
+                });
+                if let Some(subscription) = added {
+                    self.send(ServerMessage::WebhookAdded {
+                        webhook: WebhookView::from(&subscription),
+                        secret: subscription.secret,
+                    });
+                }
+            }
+            ClientMessage::RemoveWebhook { webhook } => {
+                let removed = self.on_seat(|room, id, conn| {
+                    room.remove_webhook(id, conn, Instant::now(), webhook)
+                });
+                if removed.is_some() {
+                    self.send(ServerMessage::WebhookRemoved { webhook });
+                }
+            }
+            ClientMessage::ListWebhooks => {
+                let listed = self.on_seat(|room, id, conn| room.webhooks(id, conn, Instant::now()));
+                if let Some(webhooks) = listed {
+                    self.send(ServerMessage::Webhooks {
+                        webhooks: webhooks.iter().map(WebhookView::from).collect(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_version(&self, version: u32) -> bool {
+        if version == PROTOCOL_VERSION {
+            return true;
+        }
+        self.send(ServerMessage::error(
+            ErrorCode::UnsupportedVersion,
+            format!("server speaks protocol version {PROTOCOL_VERSION}, client sent {version}"),
+        ));
+        false
+    }
+
+    /// Attaches the identity behind a login session to this connection.
+    async fn authenticate(&mut self, session: &str) {
+        let Some(login) = self.hub.login() else {
+            return self.send(ServerMessage::error(
+                ErrorCode::InvalidLogin,
+                "this server does not use login",
+            ));
+        };
+        match login
+            .session(self.hub.broker(), session, SystemTime::now())
+            .await
+        {
+            Err(err) => self.send(ServerMessage::error(
+                ErrorCode::Unavailable,
+                err.to_string(),
+            )),
+            Ok(Some(identity)) => {
+                self.send(ServerMessage::Authenticated {
+                    name: identity.name.clone(),
+                    email: identity.email.clone(),
+                });
+                self.identity = Some(identity);
+            }
+            Ok(None) => self.send(ServerMessage::error(
+                ErrorCode::InvalidLogin,
+                "login session is unknown or has expired",
+            )),
+        }
+    }
+
+    /// On servers that require login, the identity of the logged-in user,
+    /// or `None` after telling the client to log in first. Always
+    /// `Some(None)` elsewhere.
+    fn check_login(&mut self) -> Option<Option<Identity>> {
+        if self.hub.login().is_none() {
+            return Some(None);
+        }
+        match &self.identity {
+            Some(identity) if identity.expires_at > SystemTime::now() => {
+                Some(Some(identity.clone()))
+            }
+            _ => {
+                self.identity = None;
+                self.send(ServerMessage::error(
+                    ErrorCode::LoginRequired,
+                    "log in before creating or joining a room",
+                ));
+                None
+            }
+        }
+    }
+
+    /// Hands this connection over to the instance hosting the room it wants
+    /// to join, starting with that request.
+    async fn relay_to(&mut self, host: String, msg: ClientMessage) {
+        if let ClientMessage::Join { room, .. } | ClientMessage::Resume { room, .. } = &msg {
+            tracing::Span::current().record("room", tracing::field::display(room));
+        }
+        let from = self.hub.instance().to_owned();
+        let conn = self.id.0;
+        self.hub.start_relay(self.id, self.outbox.clone());
+        let open = Envelope::Open {
+            from: from.clone(),
+            conn,
+            identity: self.identity.clone(),
+            peer: self.peer,
+        };
+        let mut sent = cluster::send(&self.hub, &host, &open).await;
+        if sent.is_ok() {
+            sent = cluster::send(&self.hub, &host, &Envelope::Frame { from, conn, msg }).await;
+        }
+        match sent {
+            Ok(()) => {
+                tracing::info!(%host, "relaying to the instance hosting the room");
+                self.relay = Some(host);
+            }
+            Err(err) => {
+                self.hub.end_relay(self.id);
+                self.send(unavailable(&err));
+            }
+        }
+    }
+
+    fn find_room(&self, room: &RoomId) -> Option<Arc<RoomHandle>> {
+        let handle = self.hub.room(room);
+        if handle.is_none() {
+            self.send(ServerMessage::error(
+                ErrorCode::RoomNotFound,
+                format!("room {room} does not exist"),
+            ));
+        }
+        handle
+    }
+
+    /// Whether the connection holds a seat. One in a room that was evicted
+    /// to another instance no longer counts.
+    fn holds_seat(&mut self) -> bool {
+        if self.seat.as_ref().is_some_and(|seat| seat.room.is_closed()) {
+            self.seat = None;
+        }
+        self.seat.is_some()
+    }
+
+    fn take_seat(&mut self, room: Arc<RoomHandle>, seated: Result<ParticipantId, SeatError>) {
+        match seated {
+            Ok(participant) => {
+                tracing::Span::current().record("room", tracing::field::display(room.id()));
+                tracing::info!(%participant, "took a seat");
+                self.seat = Some(Seat { room, participant });
+            }
+            Err(err) => self.send(ServerMessage::from(&err)),
+        }
+    }
+
+    /// Runs a transition on behalf of the seated participant, reporting a
+    /// rejection back to this client only.
+    fn apply(&mut self, f: impl FnOnce(&mut Room, ParticipantId) -> Result<()>) {
+        self.try_apply(f);
+    }
+
+    /// Like [`apply`](Self::apply), but hands back what the transition
+    /// returned if it was accepted.
+    fn try_apply<T>(&mut self, f: impl FnOnce(&mut Room, ParticipantId) -> Result<T>) -> Option<T> {
+        self.on_seat(|room, participant, conn| {
+            room.apply(participant, conn, Instant::now(), |room| {
+                f(room, participant)
+            })
+        })
+    }
+
+    /// Makes a request to the room this connection is seated in, reporting
+    /// a rejection back to this client only.
+    fn on_seat<T>(
+        &mut self,
+        f: impl FnOnce(&RoomHandle, ParticipantId, ConnectionId) -> Result<T, SeatError>,
+    ) -> Option<T> {
+        let Some(seat) = &self.seat else {
+            self.send(not_joined());
+            return None;
+        };
+        match f(&seat.room, seat.participant, self.id) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.reject(err);
+                None
+            }
+        }
+    }
+
+    /// Searches the issue tracker in the background and adds what it finds
+    /// to the backlog.
+    fn fetch_stories(&mut self, query: String) {
+        let Some(tracker) = self.hub.tracker() else {
+            return self.send(no_tracker());
+        };
+        // Check up front so that only the facilitator can make the server
+        // call out.
+        if self
+            .try_apply(|room, id| room.authorize(id, Action::ManageStories))
+            .is_none()
+        {
+            return;
+        }
+        let Some(seat) = &self.seat else {
+            return;
+        };
+        let (room, participant) = (seat.room.clone(), seat.participant);
+        let (conn, outbox) = (self.id, self.outbox.clone());
+        tokio::spawn(
+            async move {
+                let stories = match tracker.fetch_stories(&query).await {
+                    Ok(stories) => stories,
+                    Err(err) => {
+                        tracing::warn!("fetching stories failed: {err}");
+                        outbox.send(ServerMessage::error(
+                            ErrorCode::TrackerUnavailable,
+                            err.to_string(),
+                        ));
+                        return;
+                    }
+                };
+                let added = room.apply(participant, conn, Instant::now(), |room| {
+                    room.add_stories(participant, stories)
+                });
+                if let Err(err) = added {
+                    outbox.send(ServerMessage::from(&err));
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    /// Writes a recorded estimate back to the issue tracker, if there is one.
+    fn sync_estimate(&self, story: StoryId, key: String, estimate: Card) {
+        let Some(tracker) = self.hub.tracker() else {
+            return;
+        };
+        let outbox = self.outbox.clone();
+        tokio::spawn(
+            async move {
+                if let Err(err) = tracker.write_estimate(&key, &estimate).await {
+                    tracing::warn!(%key, "writing estimate back failed: {err}");
+                    outbox.send(ServerMessage::EstimateSyncFailed {
+                        story,
+                        message: err.to_string(),
+                    });
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    fn reject(&mut self, err: SeatError) {
+        if matches!(err, SeatError::Replaced | SeatError::SeatLost) {
+            self.seat = None;
+        }
+        self.send(ServerMessage::from(&err));
+    }
+
+    /// Keeps the seat for the grace period so the client can resume it.
+    async fn disconnect(&mut self) {
+        if let Some(host) = self.relay.take() {
+            self.hub.end_relay(self.id);
+            let closed = Envelope::Closed {
+                from: self.hub.instance().to_owned(),
+                conn: self.id.0,
+            };
+            if let Err(err) = cluster::send(&self.hub, &host, &closed).await {
+                tracing::warn!(%host, "could not report a relayed disconnect: {err}");
+            }
+        }
+        if let Some(seat) = self.seat.take() {
+            seat.room
+                .disconnect(seat.participant, self.id, Instant::now());
+        }
+    }
+}
+
+fn not_joined() -> ServerMessage {
+    ServerMessage::error(ErrorCode::NotJoined, "join a room first")
+}
+
+fn unavailable(err: &BrokerError) -> ServerMessage {
+    ServerMessage::error(
+        ErrorCode::Unavailable,
+        format!("could not reach the rest of the cluster: {err}"),
+    )
+}
+
+fn rate_limited(message: &str) -> ServerMessage {
+    ServerMessage::error(ErrorCode::RateLimited, message)
+}
+
+fn no_tracker() -> ServerMessage {
+    ServerMessage::error(
+        ErrorCode::TrackerUnavailable,
+        "this server is not connected to an issue tracker",
+    )
+}
+
+/// Who is asking to change a template: the logged-in user, or on servers
+/// without login whoever holds `token`.
+fn template_claim(identity: Option<Identity>, token: Option<String>) -> Option<TemplateOwner> {
+    match identity {
+        Some(identity) => Some(TemplateOwner::Subject(identity.subject)),
+        None => token
+            .filter(|token| !token.is_empty())
+            .map(TemplateOwner::Token),
+    }
+}
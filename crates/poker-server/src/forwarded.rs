@@ -0,0 +1,149 @@
+//! Client addresses of requests passed on by reverse proxies.
+//!
+//! `Forwarded` and `X-Forwarded-For` are only believed when the connection
+//! comes from one of the [`Config::trusted_proxies`](crate::Config); anyone
+//! else could name whatever address suits them.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+
+use crate::Hub;
+
+/// The address of the client behind a request: the peer of the connection,
+/// or when that is one of the [`Config::trusted_proxies`](crate::Config),
+/// the address the proxies say they forwarded the request for.
+pub(crate) struct ClientAddr(pub IpAddr);
+
+impl FromRequestParts<Arc<Hub>> for ClientAddr {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, hub: &Arc<Hub>) -> Result<Self, Response> {
+        let ConnectInfo(peer) = ConnectInfo::<SocketAddr>::from_request_parts(parts, hub)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let trusted = &hub.config().trusted_proxies;
+        Ok(ClientAddr(client_address(
+            peer.ip(),
+            &parts.headers,
+            trusted,
+        )))
+    }
+}
+
+/// Walks the hops recorded in `Forwarded`, or failing that in
+/// `X-Forwarded-For`, from the nearest back for as long as they were added
+/// by a trusted proxy. A hop that is not an IP address ends the walk at the
+/// proxy that added it.
+fn client_address(peer: IpAddr, headers: &HeaderMap, trusted: &[IpAddr]) -> IpAddr {
+    let hops = if headers.contains_key("forwarded") {
+        forwarded_hops(headers)
+    } else {
+        header_list(headers, "x-forwarded-for")
+    };
+    let mut client = peer;
+    for hop in hops.iter().rev() {
+        if !trusted.contains(&client) {
+            break;
+        }
+        match parse_node(hop) {
+            Some(addr) => client = addr,
+            None => break,
+        }
+    }
+    client
+}
+
+/// The comma separated elements of every `name` header, in order.
+fn header_list(headers: &HeaderMap, name: &str) -> Vec<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|element| element.trim().to_owned())
+        .collect()
+}
+
+/// The `for` parameter of every element of the `Forwarded` headers (RFC
+/// 7239). An element without one counts as a hop that is not an address.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<String> {
+    header_list(headers, "forwarded")
+        .into_iter()
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                .map(|(_, value)| value.trim().trim_matches('"').to_owned())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// An address, with or without a port, IPv6 addresses in brackets or not.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(addr) = node.parse() {
+        return Some(addr);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    let bracketed = node.strip_prefix('[')?;
+    bracketed.split(']').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const PROXY: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn untrusted_peers_cannot_claim_another_address() {
+        let spoofed = headers(&[("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(client_address(PROXY, &spoofed, &[]), PROXY);
+    }
+
+    #[test]
+    fn trusted_proxies_are_walked_back_to_the_client() {
+        let trusted = [PROXY, ip("10.0.0.2")];
+        let chain = headers(&[("x-forwarded-for", "198.51.100.1, 203.0.113.7, 10.0.0.2")]);
+        // The client may have sent a forged first hop; only the hops the
+        // proxies added count.
+        assert_eq!(client_address(PROXY, &chain, &trusted), ip("203.0.113.7"));
+        assert_eq!(client_address(PROXY, &headers(&[]), &trusted), PROXY);
+    }
+
+    #[test]
+    fn forwarded_takes_precedence() {
+        let both = headers(&[
+            ("x-forwarded-for", "198.51.100.1"),
+            (
+                "forwarded",
+                "for=192.0.2.43, for=\"[2001:db8::1]:4711\";proto=https",
+            ),
+        ]);
+        assert_eq!(client_address(PROXY, &both, &[PROXY]), ip("2001:db8::1"));
+        let obfuscated = headers(&[("forwarded", "for=_hidden")]);
+        assert_eq!(client_address(PROXY, &obfuscated, &[PROXY]), PROXY);
+    }
+}
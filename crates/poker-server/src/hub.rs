@@ -0,0 +1,1491 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use poker_analytics::Report;
+use poker_broker::{Broker, BrokerError, LocalBroker};
+use poker_core::{
+    Action, Card, Clock, Deck, Event, ParticipantId, Presence, Role, Room, RoomId, Story, Summary,
+    SystemClock, Template, Timestamp,
+};
+use poker_protocol::{ErrorCode, Invite, RoomSnapshot, ServerMessage, PROTOCOL_VERSION};
+use poker_store::{
+    MemoryStore, RoomRecord, RoundRecord, Store, StoreError, TemplateOwner, TemplateRecord,
+};
+use poker_tracker::Tracker;
+use poker_webhook::{DeliveryFailure, EventKind, RetryPolicy, Subscription, Webhook, WebhookId};
+use rand::distr::Alphanumeric;
+use rand::Rng;
+
+use crate::auth::Login;
+use crate::cluster::{room_key, LEASE};
+use crate::config::Config;
+use crate::limits::{Bucket, PerAddress};
+use crate::metrics::{Metrics, TimedStore};
+use crate::notify::{self, Notifier, Queue, RoomWebhook};
+use crate::outbox::Outbox;
+
+const ROOM_ID_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const ROOM_ID_LEN: usize = 8;
+const TOKEN_LEN: usize = 32;
+/// Most webhooks one room may have.
+const MAX_WEBHOOKS: usize = 10;
+
+/// Identifies one WebSocket connection for the lifetime of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(pub u64);
+
+impl fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "c{}", self.0)
+    }
+}
+
+/// Why a connection's request for a seat was refused.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SeatError {
+    #[error(transparent)]
+    Room(#[from] poker_core::Error),
+    #[error("session token is unknown or has expired")]
+    InvalidSession,
+    #[error("this seat was resumed from another connection")]
+    Replaced,
+    #[error("you no longer hold a seat in this room")]
+    SeatLost,
+    #[error("this room is private; ask for its passphrase")]
+    InvalidPassphrase,
+    #[error("too many wrong passphrases; try again later")]
+    TooManyGuesses,
+    #[error("{0}")]
+    InvalidWebhook(String),
+    #[error("webhook {0} does not exist")]
+    UnknownWebhook(WebhookId),
+    #[error("this room seats at most {0} participants")]
+    RoomFull(usize),
+    #[error("this room was closed")]
+    RoomClosed,
+    #[error("this room moved to another server; resume your seat")]
+    RoomMoved,
+    #[error("this invite is not for this room")]
+    InvalidInvite,
+}
+
+impl From<&SeatError> for ServerMessage {
+    fn from(error: &SeatError) -> Self {
+        match error {
+            SeatError::Room(err) => ServerMessage::from(err),
+            SeatError::InvalidSession => {
+                ServerMessage::error(ErrorCode::InvalidSession, error.to_string())
+            }
+            SeatError::Replaced => {
+                ServerMessage::error(ErrorCode::SessionReplaced, error.to_string())
+            }
+            SeatError::SeatLost => ServerMessage::error(ErrorCode::NotJoined, error.to_string()),
+            SeatError::InvalidPassphrase => {
+                ServerMessage::error(ErrorCode::InvalidPassphrase, error.to_string())
+            }
+            SeatError::TooManyGuesses => {
+                ServerMessage::error(ErrorCode::RateLimited, error.to_string())
+            }
+            SeatError::InvalidWebhook(_) => {
+                ServerMessage::error(ErrorCode::InvalidWebhook, error.to_string())
+            }
+            SeatError::UnknownWebhook(_) => {
+                ServerMessage::error(ErrorCode::UnknownWebhook, error.to_string())
+            }
+            SeatError::RoomFull(_) => ServerMessage::error(ErrorCode::RoomFull, error.to_string()),
+            SeatError::RoomClosed => {
+                ServerMessage::error(ErrorCode::RoomNotFound, error.to_string())
+            }
+            SeatError::RoomMoved => ServerMessage::error(ErrorCode::NotJoined, error.to_string()),
+            SeatError::InvalidInvite => {
+                ServerMessage::error(ErrorCode::InvalidInvite, error.to_string())
+            }
+        }
+    }
+}
+
+/// Who asks for a seat.
+#[derive(Debug, Clone, Copy)]
+pub enum Joiner<'a> {
+    /// Anyone, under the name and role they ask for.
+    Guest { name: &'a str, role: Role },
+    /// The member of the room's team whose invite this is.
+    Invited(&'a str),
+}
+
+/// Why a room template could not be saved, found or used.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error(transparent)]
+    Invalid(#[from] poker_core::Error),
+    #[error("there is no template named {0:?}")]
+    Unknown(String),
+    #[error("the template {0:?} belongs to someone else")]
+    NotOwner(String),
+    #[error("could not reach the database: {0}")]
+    Store(#[from] StoreError),
+    #[error("could not reach the rest of the cluster: {0}")]
+    Cluster(#[from] BrokerError),
+}
+
+impl From<&TemplateError> for ServerMessage {
+    fn from(error: &TemplateError) -> Self {
+        let code = match error {
+            TemplateError::Invalid(err) => ErrorCode::from(err),
+            TemplateError::Unknown(_) => ErrorCode::UnknownTemplate,
+            TemplateError::NotOwner(_) => ErrorCode::NotTemplateOwner,
+            TemplateError::Store(_) | TemplateError::Cluster(_) => ErrorCode::Unavailable,
+        };
+        ServerMessage::error(code, error.to_string())
+    }
+}
+
+/// Where a room is hosted, as far as this instance can tell.
+pub(crate) enum Location {
+    Here,
+    /// Hosted by the instance with this id.
+    Elsewhere(String),
+    Nowhere,
+}
+
+/// All rooms hosted by this server.
+pub struct Hub {
+    config: Config,
+    store: Arc<dyn Store>,
+    tracker: Option<Arc<dyn Tracker>>,
+    notifier: Notifier,
+    login: Option<Arc<Login>>,
+    clock: Arc<dyn Clock>,
+    broker: Arc<dyn Broker>,
+    /// Identifies this instance within the cluster.
+    instance: String,
+    rooms: Mutex<HashMap<RoomId, Arc<RoomHandle>>>,
+    /// Local connections relayed to rooms hosted by other instances.
+    relays: Mutex<HashMap<ConnectionId, Outbox>>,
+    next_connection: AtomicU64,
+    metrics: Arc<Metrics>,
+    /// Connections opened per client address.
+    connections: PerAddress,
+    /// Rooms created per client address.
+    room_creations: PerAddress,
+    /// Wrong passphrases tried per client address, shared with every room.
+    passphrase_guesses: Arc<PerAddress>,
+}
+
+impl Default for Hub {
+    fn default() -> Self {
+        Hub::new(Config::default())
+    }
+}
+
+impl Hub {
+    /// A hub that keeps its rooms in memory only.
+    pub fn new(config: Config) -> Self {
+        let metrics = Arc::new(Metrics::default());
+        let passphrase_guesses = Arc::new(PerAddress::new(config.passphrase_rate));
+        let notifier = Notifier::for_rooms(&config);
+        Hub {
+            config: config.clone(),
+            store: Arc::new(TimedStore {
+                inner: Arc::new(MemoryStore::new()),
+                metrics: metrics.clone(),
+            }),
+            tracker: None,
+            notifier,
+            login: None,
+            clock: Arc::new(SystemClock),
+            broker: Arc::new(LocalBroker::new()),
+            instance: random_token(),
+            rooms: Mutex::default(),
+            relays: Mutex::default(),
+            next_connection: AtomicU64::default(),
+            metrics,
+            connections: PerAddress::new(config.connection_rate),
+            room_creations: PerAddress::new(config.room_rate),
+            passphrase_guesses,
+        }
+    }
+
+    /// A hub backed by `store`, with every room it holds restored.
+    ///
+    /// Restored participants start out disconnected; their seats are held for
+    /// the grace period from `now` so clients can resume them with the tokens
+    /// they already have.
+    pub fn open(config: Config, store: Arc<dyn Store>, now: Instant) -> Result<Self, StoreError> {
+        let metrics = Arc::new(Metrics::default());
+        let store: Arc<dyn Store> = Arc::new(TimedStore {
+            inner: store,
+            metrics: metrics.clone(),
+        });
+        let passphrase_guesses = Arc::new(PerAddress::new(config.passphrase_rate));
+        let notifier = Notifier::for_rooms(&config);
+        let rooms = store
+            .load_rooms()?
+            .into_iter()
+            .map(|record| {
+                let id = record.room.id().clone();
+                (
+                    id,
+                    RoomHandle::restore(
+                        record,
+                        store.clone(),
+                        notifier.clone(),
+                        Arc::new(SystemClock),
+                        config.max_room_size,
+                        passphrase_guesses.clone(),
+                        now,
+                    ),
+                )
+            })
+            .collect();
+        Ok(Hub {
+            config: config.clone(),
+            store,
+            tracker: None,
+            notifier,
+            login: None,
+            clock: Arc::new(SystemClock),
+            broker: Arc::new(LocalBroker::new()),
+            instance: random_token(),
+            rooms: Mutex::new(rooms),
+            relays: Mutex::default(),
+            next_connection: AtomicU64::default(),
+            metrics,
+            connections: PerAddress::new(config.connection_rate),
+            room_creations: PerAddress::new(config.room_rate),
+            passphrase_guesses,
+        })
+    }
+
+    /// Connects rooms to an issue tracker to fetch stories from and write
+    /// estimates back to.
+    pub fn with_tracker(mut self, tracker: Arc<dyn Tracker>) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    /// Tells `webhook` about what happens in every room, on top of the
+    /// webhooks facilitators subscribe to their own rooms.
+    pub fn with_webhook(mut self, webhook: Arc<dyn Webhook>) -> Self {
+        self.notifier.webhook = Some(webhook);
+        self.update_notifier();
+        self
+    }
+
+    /// Changes how persistently webhook deliveries are retried.
+    pub fn with_webhook_retries(mut self, retry: RetryPolicy) -> Self {
+        self.notifier.retry = retry;
+        self.update_notifier();
+        self
+    }
+
+    fn update_notifier(&mut self) {
+        for room in self.rooms.get_mut().unwrap().values() {
+            room.lock().notifier = self.notifier.clone();
+        }
+    }
+
+    /// Requires everyone to log in through an identity provider before they
+    /// create or join a room.
+    pub fn with_login(mut self, login: Login) -> Self {
+        self.login = Some(Arc::new(login));
+        self
+    }
+
+    /// Shares rooms with the other instances connected to `broker`. Without
+    /// one, the hub keeps to itself.
+    pub fn with_broker(mut self, broker: Arc<dyn Broker>) -> Self {
+        self.broker = broker;
+        self
+    }
+
+    /// Replaces the wall clock that round timers and history run on.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        for room in self.rooms.get_mut().unwrap().values() {
+            room.lock().clock = clock.clone();
+        }
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn tracker(&self) -> Option<Arc<dyn Tracker>> {
+        self.tracker.clone()
+    }
+
+    pub fn login(&self) -> Option<Arc<Login>> {
+        self.login.clone()
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    pub fn broker(&self) -> &dyn Broker {
+        &*self.broker
+    }
+
+    /// This instance's id within the cluster.
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// The current wall-clock time according to the hub's clock.
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    pub fn next_connection_id(&self) -> ConnectionId {
+        ConnectionId(self.next_connection.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// Opens a room under a fresh, unguessable id and claims it for this
+    /// instance. With a `passphrase`, only those who know it can join.
+    pub async fn create_room(
+        &self,
+        name: &str,
+        deck: Deck,
+        passphrase: Option<&str>,
+    ) -> Result<RoomId, BrokerError> {
+        let name = match name.trim() {
+            "" => "Planning poker",
+            name => name,
+        };
+        self.open_room(passphrase, |id| Room::new(id, name, deck))
+            .await
+    }
+
+    /// Opens a room set up from the saved template called `template`, like
+    /// [`create_room`](Self::create_room). Without a `name`, the room is
+    /// named after the template. Returns it with an invite for each member
+    /// of the team, which is what seats them in their template role.
+    pub async fn create_room_from_template(
+        &self,
+        name: &str,
+        template: &str,
+        passphrase: Option<&str>,
+    ) -> Result<(RoomId, Vec<Invite>), TemplateError> {
+        let template = self
+            .store
+            .template(template.trim())?
+            .ok_or_else(|| TemplateError::Unknown(template.trim().to_owned()))?
+            .template;
+        let name = match name.trim() {
+            "" => template.name.as_str(),
+            name => name,
+        };
+        let id = self
+            .open_room(passphrase, |id| Room::from_template(id, name, &template))
+            .await?;
+        let invites = match self.room(&id) {
+            Some(handle) => handle.invite_team(),
+            None => Vec::new(),
+        };
+        Ok((id, invites))
+    }
+
+    async fn open_room(
+        &self,
+        passphrase: Option<&str>,
+        room: impl FnOnce(RoomId) -> Room,
+    ) -> Result<RoomId, BrokerError> {
+        // Hashing is slow on purpose, so it runs off the async workers.
+        let passphrase = match passphrase.filter(|p| !p.is_empty()) {
+            Some(passphrase) => {
+                let passphrase = passphrase.to_owned();
+                let hash =
+                    tokio::task::spawn_blocking(move || poker_auth::hash_passphrase(&passphrase));
+                Some(hash.await.expect("hashing a passphrase does not panic"))
+            }
+            None => None,
+        };
+        let id = loop {
+            let id = random_room_id();
+            if self.rooms.lock().unwrap().contains_key(&id) {
+                continue;
+            }
+            if self
+                .broker
+                .set_if_absent(&room_key(&id), &self.instance, LEASE)
+                .await?
+            {
+                break id;
+            }
+        };
+        let room = room(id.clone());
+        let handle = RoomHandle::new(
+            room,
+            passphrase,
+            self.store.clone(),
+            self.notifier.clone(),
+            self.clock.clone(),
+            self.config.max_room_size,
+            self.passphrase_guesses.clone(),
+        );
+        handle.lock().persist();
+        self.rooms.lock().unwrap().insert(id.clone(), handle);
+        tracing::info!(room = %id, "created room");
+        Ok(id)
+    }
+
+    /// Saves a room template for anyone on the server to start rooms from.
+    /// A template of the same name is replaced only if `claim` owns it; a new
+    /// one belongs to `claim`, or to a fresh token without one. Returns the
+    /// template as saved, with its owner.
+    pub fn save_template(
+        &self,
+        template: Template,
+        claim: Option<TemplateOwner>,
+    ) -> Result<TemplateRecord, TemplateError> {
+        let template = template.normalize()?;
+        let owner = match self.store.template(&template.name)? {
+            Some(TemplateRecord {
+                owner: Some(owner), ..
+            }) if claim.as_ref() != Some(&owner) => {
+                return Err(TemplateError::NotOwner(template.name));
+            }
+            _ => claim.unwrap_or_else(|| TemplateOwner::Token(random_token())),
+        };
+        let record = TemplateRecord {
+            template,
+            owner: Some(owner),
+        };
+        self.store.save_template(&record)?;
+        tracing::info!(template = %record.template.name, "saved room template");
+        Ok(record)
+    }
+
+    /// Deletes the template called `name` if `claim` owns it.
+    pub fn delete_template(
+        &self,
+        name: &str,
+        claim: Option<&TemplateOwner>,
+    ) -> Result<(), TemplateError> {
+        let name = name.trim();
+        match self.store.template(name)? {
+            None => return Err(TemplateError::Unknown(name.to_owned())),
+            Some(TemplateRecord {
+                owner: Some(owner), ..
+            }) if claim != Some(&owner) => {
+                return Err(TemplateError::NotOwner(name.to_owned()));
+            }
+            Some(_) => {}
+        }
+        if !self.store.delete_template(name)? {
+            return Err(TemplateError::Unknown(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Whether `claim` owns the template called `name`.
+    pub fn owns_template(&self, name: &str, claim: &TemplateOwner) -> Result<bool, StoreError> {
+        let record = self.store.template(name)?;
+        Ok(record.is_some_and(|record| record.owner.as_ref() == Some(claim)))
+    }
+
+    /// Every saved template, by name.
+    pub fn templates(&self) -> Result<Vec<Template>, TemplateError> {
+        Ok(self.store.templates()?)
+    }
+
+    pub fn room(&self, id: &RoomId) -> Option<Arc<RoomHandle>> {
+        self.rooms.lock().unwrap().get(id).cloned()
+    }
+
+    /// Finds the instance hosting a room. A stored room that nobody hosts,
+    /// e.g. because its instance went away, is taken over.
+    pub(crate) async fn locate(&self, id: &RoomId) -> Result<Location, BrokerError> {
+        if self.room(id).is_some() {
+            return Ok(Location::Here);
+        }
+        let key = room_key(id);
+        if let Some(host) = self.broker.get(&key).await? {
+            return Ok(self.elsewhere(host));
+        }
+        let record = match self.store.load_room(id) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!(room = %id, "failed to look for a stored room: {err}");
+                None
+            }
+        };
+        let Some(record) = record else {
+            return Ok(Location::Nowhere);
+        };
+        if !self
+            .broker
+            .set_if_absent(&key, &self.instance, LEASE)
+            .await?
+        {
+            // Another instance took it over first.
+            return Ok(match self.broker.get(&key).await? {
+                Some(host) => self.elsewhere(host),
+                None => Location::Nowhere,
+            });
+        }
+        tracing::info!(room = %id, "taking over room");
+        let handle = RoomHandle::restore(
+            record,
+            self.store.clone(),
+            self.notifier.clone(),
+            self.clock.clone(),
+            self.config.max_room_size,
+            self.passphrase_guesses.clone(),
+            Instant::now(),
+        );
+        self.rooms
+            .lock()
+            .unwrap()
+            .entry(id.clone())
+            .or_insert(handle);
+        Ok(Location::Here)
+    }
+
+    fn elsewhere(&self, host: String) -> Location {
+        if host == self.instance {
+            // Claimed by this instance but gone from it.
+            Location::Nowhere
+        } else {
+            Location::Elsewhere(host)
+        }
+    }
+
+    /// Claims the rooms restored from the store, letting go of those that
+    /// another instance already hosts.
+    pub(crate) async fn claim_rooms(&self) -> Result<(), BrokerError> {
+        let ids: Vec<_> = self.rooms.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            let key = room_key(&id);
+            if self
+                .broker
+                .set_if_absent(&key, &self.instance, LEASE)
+                .await?
+            {
+                continue;
+            }
+            if self.broker.get(&key).await?.as_ref() != Some(&self.instance) {
+                self.rooms.lock().unwrap().remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Extends the claims on every room hosted here. A claim that lapsed is
+    /// taken again if nobody else took it; a room another instance took over
+    /// in the meantime is evicted here, and its clients resume their seats
+    /// there. Called periodically by the server; tests call it directly.
+    pub async fn renew_claims(&self) {
+        let ids: Vec<_> = self.rooms.lock().unwrap().keys().cloned().collect();
+        for id in ids {
+            let key = room_key(&id);
+            let held = match self.broker.renew(&key, &self.instance, LEASE).await {
+                Ok(true) => continue,
+                Ok(false) => self.broker.set_if_absent(&key, &self.instance, LEASE).await,
+                Err(err) => Err(err),
+            };
+            match held {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::warn!(room = %id, "lost the claim on room to another instance");
+                    let evicted = self.rooms.lock().unwrap().remove(&id);
+                    if let Some(room) = evicted {
+                        room.evict();
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(room = %id, "failed to renew the claim on room: {err}");
+                }
+            }
+        }
+    }
+
+    /// Routes replies for `conn` from the instance it is relayed to.
+    pub(crate) fn start_relay(&self, conn: ConnectionId, outbox: Outbox) {
+        self.relays.lock().unwrap().insert(conn, outbox);
+    }
+
+    pub(crate) fn end_relay(&self, conn: ConnectionId) {
+        self.relays.lock().unwrap().remove(&conn);
+    }
+
+    /// Passes a relayed reply to its local connection.
+    pub(crate) fn deliver(&self, conn: ConnectionId, msg: ServerMessage) {
+        if let Some(outbox) = self.relays.lock().unwrap().get(&conn) {
+            outbox.send(msg);
+        }
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.rooms.lock().unwrap().len()
+    }
+
+    /// A room that was closed for standing empty.
+    pub fn archived_room(&self, id: &RoomId) -> Result<Option<RoomRecord>, StoreError> {
+        self.store.archived_room(id)
+    }
+
+    /// Like [`RoomHandle::admits`], for a room that was closed: its
+    /// passphrase is checked against `peer`'s allowance of wrong guesses.
+    pub async fn admits_archived(
+        &self,
+        record: &RoomRecord,
+        passphrase: Option<&str>,
+        peer: IpAddr,
+        now: Instant,
+    ) -> Result<bool, SeatError> {
+        let Some(hash) = record.passphrase.clone() else {
+            return Ok(true);
+        };
+        let Some(candidate) = passphrase.map(str::to_owned) else {
+            return Ok(false);
+        };
+        if !self.passphrase_guesses.allow(peer, now) {
+            return Err(SeatError::TooManyGuesses);
+        }
+        let right =
+            tokio::task::spawn_blocking(move || poker_auth::verify_passphrase(&hash, &candidate))
+                .await
+                .expect("verifying a passphrase does not panic");
+        if right {
+            self.passphrase_guesses.refund(peer);
+        }
+        Ok(right)
+    }
+
+    /// Sums up the estimation history of a room, live or closed. `None` when
+    /// the room neither exists nor left any history behind.
+    pub fn report(&self, id: &RoomId) -> Result<Option<Report>, StoreError> {
+        let stories = match self.room(id) {
+            Some(room) => Some(room.stories()),
+            None => self
+                .store
+                .archived_room(id)?
+                .map(|record| record.room.stories().to_vec()),
+        };
+        let rounds = self.store.rounds(id)?;
+        if stories.is_none() && rounds.is_empty() {
+            return Ok(None);
+        }
+        let stories = stories.unwrap_or_default();
+        Ok(Some(poker_analytics::report(id.clone(), &rounds, &stories)))
+    }
+
+    /// Sums up the estimation history of every room started from the
+    /// template called `team`, live or closed. `None` when the team has
+    /// neither rooms nor history.
+    pub fn team_report(&self, team: &str) -> Result<Option<Report>, StoreError> {
+        let rounds = self.store.team_rounds(team)?;
+        let rooms: Vec<_> = self.rooms.lock().unwrap().values().cloned().collect();
+        let mut backlogs: Vec<_> = rooms
+            .iter()
+            .filter_map(|room| {
+                let state = room.lock();
+                (state.room.team() == Some(team))
+                    .then(|| (state.room.id().clone(), state.room.stories().to_vec()))
+            })
+            .collect();
+        backlogs.extend(
+            self.store
+                .archived_team_rooms(team)?
+                .into_iter()
+                .map(|record| (record.room.id().clone(), record.room.stories().to_vec())),
+        );
+        if rounds.is_empty() && backlogs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(poker_analytics::team_report(
+            team.to_owned(),
+            &rounds,
+            &backlogs,
+        )))
+    }
+
+    /// Whether a client at `addr` may open another connection.
+    pub(crate) fn allow_connection(&self, addr: IpAddr) -> bool {
+        self.connections.allow(addr, Instant::now())
+    }
+
+    /// Whether a client at `addr` may create another room.
+    pub(crate) fn allow_room_creation(&self, addr: IpAddr) -> bool {
+        self.room_creations.allow(addr, Instant::now())
+    }
+
+    /// Marks silent clients as idle, frees seats whose grace period ran out,
+    /// ends round timers that reached their deadline and closes rooms nobody
+    /// has been in for the idle room TTL. Called periodically by the server;
+    /// tests call it with a chosen `now` and move the hub's clock by hand.
+    pub fn sweep(&self, now: Instant) {
+        let rooms: Vec<_> = self.rooms.lock().unwrap().values().cloned().collect();
+        let wall = self.clock.now();
+        for room in rooms {
+            room.sweep(now, wall, &self.config);
+        }
+        self.close_idle_rooms(now);
+        self.connections.forget_idle(now);
+        self.room_creations.forget_idle(now);
+        self.passphrase_guesses.forget_idle(now);
+    }
+
+    /// Drops rooms that stood empty for the idle room TTL. Their final state
+    /// is archived so that their backlog still shows up in reports, and
+    /// their claims are given up so that no instance relays to them.
+    fn close_idle_rooms(&self, now: Instant) {
+        let mut closed = Vec::new();
+        self.rooms.lock().unwrap().retain(|id, room| {
+            if !room.close_if_vacant(now, self.config.idle_room_ttl) {
+                return true;
+            }
+            closed.push((id.clone(), room.clone()));
+            false
+        });
+        if closed.is_empty() {
+            return;
+        }
+        for (id, room) in &closed {
+            tracing::info!(room = %id, "closing idle room");
+            if let Err(err) = self.store.archive_room(&room.lock().record()) {
+                tracing::warn!(room = %id, "failed to archive closed room: {err}");
+            }
+        }
+        let broker = self.broker.clone();
+        let instance = self.instance.clone();
+        tokio::spawn(async move {
+            for (id, _) in closed {
+                let key = room_key(&id);
+                let released = match broker.get(&key).await {
+                    // Only give up the claim if it is still ours.
+                    Ok(Some(host)) if host == instance => broker.delete(&key).await.map(drop),
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = released {
+                    tracing::warn!(room = %id, "failed to release the claim on closed room: {err}");
+                }
+            }
+        });
+    }
+}
+
+fn random_room_id() -> RoomId {
+    let mut rng = rand::rng();
+    let id: String = (0..ROOM_ID_LEN)
+        .map(|_| ROOM_ID_ALPHABET[rng.random_range(0..ROOM_ID_ALPHABET.len())] as char)
+        .collect();
+    RoomId::new(id)
+}
+
+fn random_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+/// A room together with the connections seated in it.
+///
+/// Every mutation happens under one lock and its events are queued to all
+/// members before the lock is released, so every client observes the same
+/// order of events.
+pub struct RoomHandle {
+    state: Mutex<RoomState>,
+}
+
+struct RoomState {
+    room: Room,
+    members: HashMap<ParticipantId, Member>,
+    /// Argon2 hash of the join passphrase of a private room.
+    passphrase: Option<String>,
+    store: Arc<dyn Store>,
+    notifier: Notifier,
+    /// Notifications on their way to the server's webhook.
+    notifications: Queue,
+    /// The hub's wall clock, for timestamps in the room and its history.
+    clock: Arc<dyn Clock>,
+    /// Receivers the facilitator subscribed to the room's notifications.
+    webhooks: Vec<RoomWebhook>,
+    /// Names of the invited team members, by the token of their invite.
+    invites: BTreeMap<String, String>,
+    /// The handle holding this state, for webhook deliveries to report back
+    /// to.
+    handle: Weak<RoomHandle>,
+    /// Most participants the room seats.
+    capacity: usize,
+    /// Wrong passphrases tried against the room.
+    guesses: Bucket,
+    /// Wrong passphrases tried per client address, across all rooms.
+    addresses: Arc<PerAddress>,
+    /// When the sweep first found the room without anyone seated.
+    vacant_since: Option<Instant>,
+    /// Set once the room was dropped from the hub; nobody can take a seat
+    /// any more.
+    closed: bool,
+}
+
+/// Transport-side bookkeeping for one seat.
+struct Member {
+    token: String,
+    /// The connection currently holding the seat, if any.
+    link: Option<Link>,
+    /// Last time the client was heard from, or when it disconnected.
+    last_seen: Instant,
+}
+
+struct Link {
+    conn: ConnectionId,
+    outbox: Outbox,
+}
+
+impl RoomState {
+    fn broadcast_events(&mut self) {
+        self.broadcast_events_except(None);
+    }
+
+    /// Persists the room and fans out pending events to every connected
+    /// member but `skip`, who is about to receive a snapshot that already
+    /// includes them. Storing first means a client never sees a change that a
+    /// restart could lose.
+    fn broadcast_events_except(&mut self, skip: Option<ParticipantId>) {
+        let events = self.room.drain_events();
+        if events.is_empty() {
+            return;
+        }
+        for event in &events {
+            if let Event::Revealed { votes, summary } = event {
+                self.record_round(votes, summary);
+            }
+        }
+        self.persist();
+        self.notify(&events);
+        for event in events {
+            let kicked = match event {
+                Event::ParticipantKicked(id) => Some(id),
+                _ => None,
+            };
+            let msg = ServerMessage::from(event);
+            for (&id, member) in &self.members {
+                if let (Some(link), false) = (&member.link, skip == Some(id)) {
+                    link.outbox.send(msg.clone());
+                }
+            }
+            // The kicked participant has been told; their seat and token die here.
+            if let Some(id) = kicked {
+                self.members.remove(&id);
+            }
+        }
+    }
+
+    /// Sends webhooks what they want to hear about `events`. Delivery happens
+    /// in the background.
+    fn notify(&self, events: &[Event]) {
+        if self.notifier.webhook.is_none() && self.webhooks.is_empty() {
+            return;
+        }
+        let notifications = notify::notifications(&self.room, events, self.clock.now());
+        self.notifier.dispatch(
+            &self.handle,
+            &self.notifications,
+            &self.webhooks,
+            notifications,
+            &self.clock,
+        );
+    }
+
+    /// The room and the tokens of its seated participants, as stored.
+    fn record(&self) -> RoomRecord {
+        RoomRecord {
+            room: self.room.clone(),
+            tokens: self
+                .members
+                .iter()
+                .filter(|(&id, _)| self.room.participant(id).is_some())
+                .map(|(&id, member)| (id, member.token.clone()))
+                .collect(),
+            passphrase: self.passphrase.clone(),
+            webhooks: self
+                .webhooks
+                .iter()
+                .map(|webhook| webhook.subscription.clone())
+                .collect(),
+            invites: self.invites.clone(),
+        }
+    }
+
+    /// Writes the room and the tokens of its seated participants to the store.
+    fn persist(&self) {
+        if let Err(err) = self.store.save_room(&self.record()) {
+            tracing::warn!(room = %self.room.id(), "failed to persist room: {err}");
+        }
+    }
+
+    fn record_round(&self, votes: &BTreeMap<ParticipantId, Card>, summary: &Summary) {
+        let round = RoundRecord {
+            room: self.room.id().clone(),
+            team: self.room.team().map(str::to_owned),
+            story: self.room.round().story().map(str::to_owned),
+            story_id: self.room.current_story().map(|s| s.id),
+            revealed_at: self.clock.now(),
+            votes: self.room.recorded_votes(votes),
+            summary: summary.clone(),
+        };
+        if let Err(err) = self.store.record_round(&round) {
+            tracing::warn!(room = %self.room.id(), "failed to record round: {err}");
+        }
+    }
+
+    fn send_to(&self, id: ParticipantId, msg: ServerMessage) {
+        if let Some(link) = self.members.get(&id).and_then(|m| m.link.as_ref()) {
+            link.outbox.send(msg);
+        }
+    }
+
+    /// Finds the seat held by `conn`.
+    fn linked_member(
+        &mut self,
+        id: ParticipantId,
+        conn: ConnectionId,
+    ) -> Result<&mut Member, SeatError> {
+        let member = self.members.get_mut(&id).ok_or(SeatError::SeatLost)?;
+        match &member.link {
+            Some(link) if link.conn == conn => Ok(member),
+            _ => Err(SeatError::Replaced),
+        }
+    }
+
+    /// Records that the client holding a seat is alive.
+    fn mark_seen(
+        &mut self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+    ) -> Result<(), SeatError> {
+        self.linked_member(id, conn)?.last_seen = now;
+        let wall = self.clock.now();
+        self.room.set_presence(id, Presence::Connected, wall)?;
+        Ok(())
+    }
+
+    fn welcome(&self, id: ParticipantId) -> ServerMessage {
+        ServerMessage::Welcome {
+            version: PROTOCOL_VERSION,
+            you: id,
+            token: self.members[&id].token.clone(),
+            your_vote: self.room.round().vote_of(id).cloned(),
+            room: Box::new(RoomSnapshot::of(&self.room)),
+        }
+    }
+}
+
+impl RoomHandle {
+    fn new(
+        room: Room,
+        passphrase: Option<String>,
+        store: Arc<dyn Store>,
+        notifier: Notifier,
+        clock: Arc<dyn Clock>,
+        capacity: usize,
+        addresses: Arc<PerAddress>,
+    ) -> Arc<Self> {
+        Arc::new_cyclic(|handle| RoomHandle {
+            state: Mutex::new(RoomState {
+                room,
+                members: HashMap::new(),
+                passphrase,
+                store,
+                notifier,
+                notifications: Queue::default(),
+                clock,
+                webhooks: Vec::new(),
+                invites: BTreeMap::new(),
+                handle: handle.clone(),
+                capacity,
+                guesses: Bucket::full(addresses.rate(), Instant::now()),
+                addresses,
+                vacant_since: None,
+                closed: false,
+            }),
+        })
+    }
+
+    /// Rebuilds a room loaded from the store with every seat unlinked.
+    fn restore(
+        record: RoomRecord,
+        store: Arc<dyn Store>,
+        notifier: Notifier,
+        clock: Arc<dyn Clock>,
+        capacity: usize,
+        addresses: Arc<PerAddress>,
+        now: Instant,
+    ) -> Arc<Self> {
+        let RoomRecord {
+            mut room,
+            tokens,
+            passphrase,
+            webhooks,
+            invites,
+        } = record;
+        room.disconnect_all();
+        // A seat nobody can resume would never be freed; drop it right away.
+        let orphans: Vec<_> = room
+            .participants()
+            .map(|p| p.id)
+            .filter(|id| !tokens.contains_key(id))
+            .collect();
+        let wall = clock.now();
+        for id in orphans {
+            let _ = room.leave(id, wall);
+        }
+        room.drain_events();
+        let members = tokens
+            .into_iter()
+            .filter(|(id, _)| room.participant(*id).is_some())
+            .map(|(id, token)| {
+                let member = Member {
+                    token,
+                    link: None,
+                    last_seen: now,
+                };
+                (id, member)
+            })
+            .collect();
+        let webhooks = webhooks
+            .into_iter()
+            .filter_map(|subscription| {
+                match RoomWebhook::new(subscription, &notifier.allowed_hosts) {
+                    Ok(webhook) => Some(webhook),
+                    Err(err) => {
+                        tracing::warn!(room = %room.id(), "dropping stored webhook: {err}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Arc::new_cyclic(|handle| RoomHandle {
+            state: Mutex::new(RoomState {
+                room,
+                members,
+                passphrase,
+                store,
+                notifier,
+                notifications: Queue::default(),
+                clock,
+                webhooks,
+                invites,
+                handle: handle.clone(),
+                capacity,
+                guesses: Bucket::full(addresses.rate(), Instant::now()),
+                addresses,
+                vacant_since: None,
+                closed: false,
+            }),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RoomState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Hands out an invite to each member of the room's team.
+    fn invite_team(&self) -> Vec<Invite> {
+        let mut state = self.lock();
+        let invites: Vec<_> = state
+            .room
+            .invitees()
+            .into_iter()
+            .map(|name| Invite {
+                name: name.to_owned(),
+                token: random_token(),
+            })
+            .collect();
+        state.invites = invites
+            .iter()
+            .map(|invite| (invite.token.clone(), invite.name.clone()))
+            .collect();
+        state.persist();
+        invites
+    }
+
+    /// Seats a new participant. Everyone already in the room is told about
+    /// them; the newcomer receives a [`ServerMessage::Welcome`] instead.
+    /// Private rooms first check `passphrase`, as tried from `peer`.
+    pub async fn join(
+        &self,
+        joiner: Joiner<'_>,
+        passphrase: Option<&str>,
+        peer: Option<IpAddr>,
+        conn: ConnectionId,
+        outbox: Outbox,
+        now: Instant,
+    ) -> Result<ParticipantId, SeatError> {
+        if !self.check_passphrase(passphrase, peer, now).await? {
+            return Err(SeatError::InvalidPassphrase);
+        }
+        let mut state = self.lock();
+        if state.closed {
+            return Err(SeatError::RoomClosed);
+        }
+        if state.room.participants().count() >= state.capacity {
+            return Err(SeatError::RoomFull(state.capacity));
+        }
+        let id = match joiner {
+            Joiner::Guest { name, role } => state.room.join(name, role)?,
+            Joiner::Invited(token) => {
+                let member = state
+                    .invites
+                    .get(token)
+                    .cloned()
+                    .ok_or(SeatError::InvalidInvite)?;
+                state.room.join_member(&member)?
+            }
+        };
+        state.members.insert(
+            id,
+            Member {
+                token: random_token(),
+                link: Some(Link { conn, outbox }),
+                last_seen: now,
+            },
+        );
+        state.broadcast_events_except(Some(id));
+        let welcome = state.welcome(id);
+        state.send_to(id, welcome);
+        Ok(id)
+    }
+
+    /// Hands the seat matching `token` to a new connection. A connection that
+    /// still holds the seat is told it has been replaced.
+    pub fn resume(
+        &self,
+        token: &str,
+        conn: ConnectionId,
+        outbox: Outbox,
+        now: Instant,
+    ) -> Result<ParticipantId, SeatError> {
+        let mut state = self.lock();
+        let (&id, member) = state
+            .members
+            .iter_mut()
+            .find(|(_, m)| m.token == token)
+            .ok_or(SeatError::InvalidSession)?;
+        if let Some(old) = member.link.replace(Link { conn, outbox }) {
+            old.outbox.send(ServerMessage::from(&SeatError::Replaced));
+        }
+        member.last_seen = now;
+        let wall = state.clock.now();
+        state.room.set_presence(id, Presence::Connected, wall)?;
+        state.broadcast_events_except(Some(id));
+        let welcome = state.welcome(id);
+        state.send_to(id, welcome);
+        Ok(id)
+    }
+
+    /// Gives up a seat for good.
+    pub fn leave(&self, id: ParticipantId, conn: ConnectionId) -> Result<(), SeatError> {
+        let mut state = self.lock();
+        state.linked_member(id, conn)?;
+        state.members.remove(&id);
+        let wall = state.clock.now();
+        state.room.leave(id, wall)?;
+        state.broadcast_events();
+        Ok(())
+    }
+
+    /// Detaches a dropped connection, holding its seat for the grace period.
+    pub fn disconnect(&self, id: ParticipantId, conn: ConnectionId, now: Instant) {
+        let mut state = self.lock();
+        let Ok(member) = state.linked_member(id, conn) else {
+            return;
+        };
+        member.link = None;
+        member.last_seen = now;
+        let wall = state.clock.now();
+        if state.room.set_presence(id, Presence::Gone, wall).is_ok() {
+            state.broadcast_events();
+        }
+    }
+
+    /// Records that the client holding the seat is alive.
+    pub fn touch(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+    ) -> Result<(), SeatError> {
+        let mut state = self.lock();
+        state.mark_seen(id, conn, now)?;
+        state.broadcast_events();
+        Ok(())
+    }
+
+    /// Runs a room transition on behalf of a seated connection and fans out
+    /// the events it produced.
+    pub fn apply<T>(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+        f: impl FnOnce(&mut Room) -> poker_core::Result<T>,
+    ) -> Result<T, SeatError> {
+        let mut state = self.lock();
+        state.mark_seen(id, conn, now)?;
+        let result = f(&mut state.room);
+        state.broadcast_events();
+        Ok(result?)
+    }
+
+    /// Subscribes `url` to the room's notifications, or only to those about
+    /// `events`, on behalf of the facilitator. The subscription handed back
+    /// holds the secret its payloads are signed with.
+    pub fn add_webhook(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+        url: &str,
+        events: Vec<EventKind>,
+    ) -> Result<Subscription, SeatError> {
+        self.manage_webhooks(id, conn, now, |state| {
+            if state.webhooks.len() >= MAX_WEBHOOKS {
+                return Err(SeatError::InvalidWebhook(format!(
+                    "a room can have at most {MAX_WEBHOOKS} webhooks"
+                )));
+            }
+            let next = state
+                .webhooks
+                .iter()
+                .map(|webhook| webhook.subscription.id.0)
+                .max()
+                .unwrap_or(0);
+            let subscription = Subscription {
+                id: WebhookId(next + 1),
+                url: url.trim().to_owned(),
+                secret: random_token(),
+                events,
+                last_failure: None,
+            };
+            let webhook = RoomWebhook::new(subscription.clone(), &state.notifier.allowed_hosts)
+                .map_err(|err| SeatError::InvalidWebhook(err.to_string()))?;
+            state.webhooks.push(webhook);
+            state.persist();
+            Ok(subscription)
+        })
+    }
+
+    /// Unsubscribes a webhook on behalf of the facilitator.
+    pub fn remove_webhook(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+        webhook: WebhookId,
+    ) -> Result<(), SeatError> {
+        self.manage_webhooks(id, conn, now, |state| {
+            let index = state
+                .webhooks
+                .iter()
+                .position(|w| w.subscription.id == webhook)
+                .ok_or(SeatError::UnknownWebhook(webhook))?;
+            state.webhooks.remove(index);
+            state.persist();
+            Ok(())
+        })
+    }
+
+    /// The room's webhooks, for the facilitator to look at.
+    pub fn webhooks(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+    ) -> Result<Vec<Subscription>, SeatError> {
+        self.manage_webhooks(id, conn, now, |state| {
+            Ok(state
+                .webhooks
+                .iter()
+                .map(|webhook| webhook.subscription.clone())
+                .collect())
+        })
+    }
+
+    /// Runs `f` if the seat held by `conn` is the facilitator's.
+    fn manage_webhooks<T>(
+        &self,
+        id: ParticipantId,
+        conn: ConnectionId,
+        now: Instant,
+        f: impl FnOnce(&mut RoomState) -> Result<T, SeatError>,
+    ) -> Result<T, SeatError> {
+        let mut state = self.lock();
+        state.mark_seen(id, conn, now)?;
+        let result = match state.room.authorize(id, Action::ManageWebhooks) {
+            Ok(()) => f(&mut state),
+            Err(err) => Err(err.into()),
+        };
+        state.broadcast_events();
+        result
+    }
+
+    /// Records that a notification could not be delivered to a webhook and
+    /// tells the facilitator.
+    pub(crate) fn webhook_failed(&self, webhook: WebhookId, failure: DeliveryFailure) {
+        let mut state = self.lock();
+        let Some(subscribed) = state
+            .webhooks
+            .iter_mut()
+            .find(|w| w.subscription.id == webhook)
+        else {
+            return;
+        };
+        subscribed.subscription.last_failure = Some(failure.clone());
+        state.persist();
+        if let Some(facilitator) = state.room.facilitator() {
+            state.send_to(
+                facilitator,
+                ServerMessage::WebhookFailed { webhook, failure },
+            );
+        }
+    }
+
+    /// Whether someone at `peer` presenting these credentials may read the
+    /// room's data outside of it: anyone for an open room, and for a private
+    /// room those holding a seat token or knowing the passphrase.
+    pub async fn admits(
+        &self,
+        token: Option<&str>,
+        passphrase: Option<&str>,
+        peer: IpAddr,
+        now: Instant,
+    ) -> Result<bool, SeatError> {
+        let seated = token.is_some_and(|token| {
+            let state = self.lock();
+            state.members.values().any(|m| m.token == token)
+        });
+        if seated {
+            return Ok(true);
+        }
+        self.check_passphrase(passphrase, Some(peer), now).await
+    }
+
+    /// Whether `candidate` is the passphrase of the room, or the room has
+    /// none. Every guess draws on the room's and `peer`'s allowance of wrong
+    /// passphrases, and is given back if it was right. Verifying is slow on
+    /// purpose, so it runs off the async workers.
+    async fn check_passphrase(
+        &self,
+        candidate: Option<&str>,
+        peer: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<bool, SeatError> {
+        let (hash, addresses) = {
+            let mut state = self.lock();
+            let Some(hash) = state.passphrase.clone() else {
+                return Ok(true);
+            };
+            if candidate.is_none() {
+                return Ok(false);
+            }
+            let rate = state.addresses.rate();
+            if !state.guesses.take(rate, now) {
+                return Err(SeatError::TooManyGuesses);
+            }
+            if peer.is_some_and(|peer| !state.addresses.allow(peer, now)) {
+                state.guesses.refund(rate);
+                return Err(SeatError::TooManyGuesses);
+            }
+            (hash, state.addresses.clone())
+        };
+        let candidate = candidate.unwrap_or_default().to_owned();
+        let right =
+            tokio::task::spawn_blocking(move || poker_auth::verify_passphrase(&hash, &candidate))
+                .await
+                .expect("verifying a passphrase does not panic");
+        if right {
+            self.lock().guesses.refund(addresses.rate());
+            if let Some(peer) = peer {
+                addresses.refund(peer);
+            }
+        }
+        Ok(right)
+    }
+
+    pub fn id(&self) -> RoomId {
+        self.lock().room.id().clone()
+    }
+
+    pub fn snapshot(&self) -> RoomSnapshot {
+        RoomSnapshot::of(&self.lock().room)
+    }
+
+    /// The backlog with estimates and round history.
+    pub fn stories(&self) -> Vec<Story> {
+        self.lock().room.stories().to_vec()
+    }
+
+    fn sweep(&self, now: Instant, wall: Timestamp, config: &Config) {
+        let mut state = self.lock();
+        let mut idle = Vec::new();
+        let mut expired = Vec::new();
+        // While a round is open for votes over days, seats are held for
+        // everyone to come back and vote.
+        let holding = state.room.voting().is_some();
+        for (&id, member) in &state.members {
+            let silent_for = now.saturating_duration_since(member.last_seen);
+            match member.link {
+                Some(_) if silent_for >= config.idle_after => idle.push(id),
+                None if silent_for >= config.grace_period && !holding => expired.push(id),
+                _ => {}
+            }
+        }
+        for id in idle {
+            let _ = state.room.set_presence(id, Presence::Idle, wall);
+        }
+        for id in expired {
+            tracing::info!(room = %state.room.id(), participant = %id, "freeing an abandoned seat");
+            state.members.remove(&id);
+            let _ = state.room.leave(id, wall);
+        }
+        state.room.expire_timer(wall);
+        state.room.close_voting(wall);
+        state.broadcast_events();
+        if state.members.is_empty() {
+            state.vacant_since.get_or_insert(now);
+        } else {
+            state.vacant_since = None;
+        }
+    }
+
+    /// Gives up the room after another instance took it over. Seated clients
+    /// are told to resume their seats, which reaches the new host; nothing
+    /// is stored from here any more.
+    fn evict(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        for member in std::mem::take(&mut state.members).into_values() {
+            if let Some(link) = member.link {
+                link.outbox.send(ServerMessage::from(&SeatError::RoomMoved));
+            }
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Closes the room if nobody has been seated in it for `ttl`.
+    fn close_if_vacant(&self, now: Instant, ttl: Duration) -> bool {
+        let mut state = self.lock();
+        let vacant = state.members.is_empty()
+            && state
+                .vacant_since
+                .is_some_and(|since| now.saturating_duration_since(since) >= ttl);
+        state.closed |= vacant;
+        vacant
+    }
+}
@@ -0,0 +1,428 @@
+//! WebSocket server hosting OpenPlanningPoker rooms.
+//!
+//! Clients connect to `/ws` and speak the JSON protocol from
+//! [`poker_protocol`]. Rooms live in a shared [`Hub`]; each connection holds at
+//! most one seat in one room at a time. A seat outlives its connection for a
+//! grace period so a client that dropped off can resume it with its session
+//! token.
+//!
+//! A room's stories and estimates can be downloaded from
+//! `/rooms/{room}/export` as CSV or JSON, and a report on how its team
+//! estimates is served from `/rooms/{room}/report`. `/teams/{team}/report`
+//! covers every room started from the team's template. On servers that use
+//! login these require the session token as `Authorization: Bearer`, and a
+//! private room's data also requires one of its seat tokens in
+//! `X-Room-Token` or its passphrase in `X-Room-Passphrase`. A team's report
+//! is only served to whoever owns its template: the user who saved it, or on
+//! servers without login whoever passes its token in `X-Template-Token`.
+//!
+//! Servers given an OpenID Connect provider (see [`Login`]) require users to
+//! log in through `/auth/login` before they create or join a room.
+//!
+//! Several servers can share rooms through a [`poker_broker::Broker`]; each
+//! room is hosted by one of them and the others relay its clients there.
+//!
+//! Operational [`Metrics`] are served from `/metrics` in the Prometheus text
+//! format.
+//!
+//! To keep public instances usable, the [`Config`] caps message and room
+//! sizes, limits the [`Rate`] at which each connection sends messages and
+//! each client address connects and creates rooms, and closes rooms that
+//! stood empty for a while. Behind a load balancer, client addresses are
+//! taken from the `Forwarded` or `X-Forwarded-For` headers of the proxies
+//! it trusts.
+
+mod auth;
+mod cluster;
+mod config;
+mod connection;
+mod forwarded;
+mod hub;
+mod limits;
+mod metrics;
+mod notify;
+mod outbox;
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use poker_auth::Identity;
+use poker_core::RoomId;
+use poker_exchange::{ColumnMapping, Format};
+use poker_store::{RoomRecord, StoreError, TemplateOwner};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use crate::forwarded::ClientAddr;
+
+pub use auth::{Login, LoginError};
+pub use config::Config;
+pub use hub::{ConnectionId, Hub, Joiner, RoomHandle, SeatError, TemplateError};
+pub use limits::Rate;
+pub use metrics::Metrics;
+pub use outbox::Outbox;
+
+/// How often presence, grace periods and round timers are re-evaluated.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Header carrying a seat token of the private room whose data is requested.
+const ROOM_TOKEN_HEADER: &str = "x-room-token";
+
+/// Header carrying the passphrase of the private room whose data is requested.
+const ROOM_PASSPHRASE_HEADER: &str = "x-room-passphrase";
+
+/// Header carrying the token of the template whose team report is requested.
+const TEMPLATE_TOKEN_HEADER: &str = "x-template-token";
+
+/// Builds the HTTP routes served for `hub`. Connections are limited per
+/// client address, so the routes must be served with
+/// [`Router::into_make_service_with_connect_info`].
+pub fn router(hub: Arc<Hub>) -> Router {
+    Router::new()
+        .route("/ws", get(websocket))
+        .route("/rooms/{room}/export", get(export))
+        .route("/rooms/{room}/report", get(report))
+        .route("/teams/{team}/report", get(team_report))
+        .route("/auth/login", get(login))
+        .route("/auth/callback", get(callback))
+        .route("/metrics", get(metrics))
+        .with_state(hub)
+}
+
+/// Serves `hub` on an already bound listener until `shutdown` resolves.
+pub async fn serve(
+    listener: TcpListener,
+    hub: Arc<Hub>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let sweeper = tokio::spawn({
+        let hub = hub.clone();
+        async move {
+            let mut ticks = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                ticks.tick().await;
+                hub.sweep(Instant::now());
+            }
+        }
+    });
+    hub.claim_rooms().await.map_err(std::io::Error::other)?;
+    let inbox = hub
+        .broker()
+        .subscribe(&cluster::topic(hub.instance()))
+        .await
+        .map_err(std::io::Error::other)?;
+    let listener_task = tokio::spawn(cluster::listen(hub.clone(), inbox));
+    let renewer = tokio::spawn({
+        let hub = hub.clone();
+        async move {
+            let mut ticks = tokio::time::interval(cluster::LEASE / 3);
+            loop {
+                ticks.tick().await;
+                hub.renew_claims().await;
+            }
+        }
+    });
+    let app = router(hub).into_make_service_with_connect_info::<SocketAddr>();
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await;
+    sweeper.abort();
+    listener_task.abort();
+    renewer.abort();
+    result
+}
+
+async fn websocket(
+    ws: WebSocketUpgrade,
+    ClientAddr(peer): ClientAddr,
+    State(hub): State<Arc<Hub>>,
+) -> Response {
+    if !hub.allow_connection(peer) {
+        tracing::debug!(%peer, "refusing a connection over the rate limit");
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many connections from your address; try again later",
+        )
+            .into_response();
+    }
+    let max = hub.config().max_message_size;
+    ws.max_message_size(max)
+        .max_frame_size(max)
+        .on_upgrade(move |socket| connection::run(socket, hub, peer))
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default = "default_format")]
+    format: Format,
+    #[serde(flatten)]
+    mapping: ColumnMapping,
+}
+
+fn default_format() -> Format {
+    Format::Json
+}
+
+/// Serves a room's stories and estimates as a download. Column names can be
+/// overridden with `title`, `key` and `estimate` query parameters; an empty
+/// `key` leaves keys out.
+async fn export(
+    Path(room): Path<String>,
+    Query(ExportQuery {
+        format,
+        mut mapping,
+    }): Query<ExportQuery>,
+    ClientAddr(peer): ClientAddr,
+    State(hub): State<Arc<Hub>>,
+    headers: HeaderMap,
+) -> Response {
+    let room = RoomId::new(room);
+    let handle = hub.room(&room);
+    let archived = match archived_unless_live(&hub, &room, handle.as_deref()) {
+        Ok(archived) => archived,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+    let guarded = handle
+        .as_deref()
+        .map(Guarded::Live)
+        .or(archived.as_ref().map(Guarded::Archived));
+    if let Err(refusal) = authorize(&hub, &headers, guarded, peer).await {
+        return refusal;
+    }
+    let stories = match (handle, archived) {
+        (Some(handle), _) => handle.stories(),
+        (None, Some(record)) => record.room.stories().to_vec(),
+        (None, None) => {
+            return (StatusCode::NOT_FOUND, format!("room {room} does not exist")).into_response()
+        }
+    };
+    mapping.key = mapping.key.filter(|k| !k.trim().is_empty());
+    match poker_exchange::export(format, &stories, &mapping) {
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, format.content_type().to_owned()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{room}.{}\"", format.extension()),
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Serves the analytics report over a room's estimation history.
+async fn report(
+    Path(room): Path<String>,
+    ClientAddr(peer): ClientAddr,
+    State(hub): State<Arc<Hub>>,
+    headers: HeaderMap,
+) -> Response {
+    let room = RoomId::new(room);
+    let handle = hub.room(&room);
+    let archived = match archived_unless_live(&hub, &room, handle.as_deref()) {
+        Ok(archived) => archived,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+    let guarded = handle
+        .as_deref()
+        .map(Guarded::Live)
+        .or(archived.as_ref().map(Guarded::Archived));
+    if let Err(refusal) = authorize(&hub, &headers, guarded, peer).await {
+        return refusal;
+    }
+    match hub.report(&room) {
+        Ok(Some(report)) => Json(report).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, format!("room {room} does not exist")).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Serves the analytics report over the history of every room of a team.
+async fn team_report(
+    Path(team): Path<String>,
+    ClientAddr(peer): ClientAddr,
+    State(hub): State<Arc<Hub>>,
+    headers: HeaderMap,
+) -> Response {
+    let claim = match authorize(&hub, &headers, None, peer).await {
+        Ok(Some(identity)) => Some(TemplateOwner::Subject(identity.subject)),
+        Ok(None) => headers
+            .get(TEMPLATE_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|token| TemplateOwner::Token(token.to_owned())),
+        Err(refusal) => return refusal,
+    };
+    let owned = match claim {
+        Some(claim) => hub.owns_template(&team, &claim),
+        None => Ok(false),
+    };
+    match owned {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::FORBIDDEN,
+                "only whoever saved the team's template can see its report",
+            )
+                .into_response()
+        }
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+    match hub.team_report(&team) {
+        Ok(Some(report)) => Json(report).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, format!("team {team:?} has no rooms")).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// The archived state of `room` when it is not live.
+fn archived_unless_live(
+    hub: &Hub,
+    room: &RoomId,
+    live: Option<&RoomHandle>,
+) -> Result<Option<RoomRecord>, StoreError> {
+    match live {
+        Some(_) => Ok(None),
+        None => hub.archived_room(room),
+    }
+}
+
+/// The room whose data a request is after.
+#[derive(Clone, Copy)]
+enum Guarded<'a> {
+    Live(&'a RoomHandle),
+    /// A room closed for standing empty, which nobody holds a seat in.
+    Archived(&'a RoomRecord),
+}
+
+/// Checks the credentials a request for room data carries: a login session
+/// on servers that use login, and for a private `room` a seat token or the
+/// passphrase, whose guesses from `peer` are limited like those on `join`.
+/// Returns who logged in, on servers that use login.
+async fn authorize(
+    hub: &Hub,
+    headers: &HeaderMap,
+    room: Option<Guarded<'_>>,
+    peer: IpAddr,
+) -> Result<Option<Identity>, Response> {
+    let identity = if let Some(login) = hub.login() {
+        let session = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let identity = match session {
+            Some(session) => login
+                .session(hub.broker(), session, SystemTime::now())
+                .await
+                .map_err(|err| {
+                    (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response()
+                })?,
+            None => None,
+        };
+        if identity.is_none() {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                "log in through /auth/login and pass the session as a bearer token",
+            )
+                .into_response());
+        }
+        identity
+    } else {
+        None
+    };
+    let credential = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let Some(room) = room else {
+        return Ok(identity);
+    };
+    let passphrase = credential(ROOM_PASSPHRASE_HEADER);
+    let admitted = match room {
+        Guarded::Live(room) => {
+            room.admits(
+                credential(ROOM_TOKEN_HEADER),
+                passphrase,
+                peer,
+                Instant::now(),
+            )
+            .await
+        }
+        Guarded::Archived(record) => {
+            hub.admits_archived(record, passphrase, peer, Instant::now())
+                .await
+        }
+    };
+    match admitted {
+        Ok(true) => Ok(identity),
+        Ok(false) => Err((
+            StatusCode::FORBIDDEN,
+            "this room is private; pass a seat token or its passphrase",
+        )
+            .into_response()),
+        Err(err) => Err((StatusCode::TOO_MANY_REQUESTS, err.to_string()).into_response()),
+    }
+}
+
+/// Serves this instance's metrics to Prometheus.
+async fn metrics(State(hub): State<Arc<Hub>>) -> Response {
+    let body = hub.metrics().render(hub.room_count());
+    (
+        [(
+            header::CONTENT_TYPE,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response()
+}
+
+/// Sends the browser to the identity provider.
+async fn login(State(hub): State<Arc<Hub>>) -> Response {
+    let Some(login) = hub.login() else {
+        return (StatusCode::NOT_FOUND, "this server does not use login").into_response();
+    };
+    match login.start(hub.broker()).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(err) => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    state: String,
+    code: Option<String>,
+    /// Set instead of `code` when the provider refused the login.
+    error: Option<String>,
+}
+
+/// Completes a login and hands out the session token that clients pass to
+/// `authenticate`.
+async fn callback(Query(query): Query<CallbackQuery>, State(hub): State<Arc<Hub>>) -> Response {
+    let Some(login) = hub.login() else {
+        return (StatusCode::NOT_FOUND, "this server does not use login").into_response();
+    };
+    let Some(code) = query.code else {
+        let error = query.error.unwrap_or_else(|| "no code".into());
+        return (StatusCode::BAD_REQUEST, format!("login failed: {error}")).into_response();
+    };
+    match login.finish(hub.broker(), &query.state, &code).await {
+        Ok((session, identity)) => Json(json!({
+            "session": session,
+            "name": identity.name,
+            "email": identity.email,
+        }))
+        .into_response(),
+        Err(err) => {
+            tracing::warn!("login failed: {err}");
+            (StatusCode::BAD_REQUEST, format!("login failed: {err}")).into_response()
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Rate limits that keep one client from flooding a public instance.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// At most `count` events every `per`, in bursts of up to `count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub count: u32,
+    pub per: Duration,
+}
+
+impl Rate {
+    pub const fn per_second(count: u32) -> Self {
+        Rate {
+            count,
+            per: Duration::from_secs(1),
+        }
+    }
+
+    pub const fn per_minute(count: u32) -> Self {
+        Rate {
+            count,
+            per: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A token bucket holding up to `rate.count` tokens, refilled continuously
+/// at `rate`.
+#[derive(Debug, Clone)]
+pub(crate) struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    pub fn full(rate: Rate, now: Instant) -> Self {
+        Bucket {
+            tokens: f64::from(rate.count),
+            updated: now,
+        }
+    }
+
+    /// Takes a token if there is one.
+    pub fn take(&mut self, rate: Rate, now: Instant) -> bool {
+        self.refill(rate, now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Puts back a token taken for something that turned out not to count.
+    pub fn refund(&mut self, rate: Rate) {
+        self.tokens = (self.tokens + 1.0).min(f64::from(rate.count));
+    }
+
+    fn refill(&mut self, rate: Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let per_second = f64::from(rate.count) / rate.per.as_secs_f64();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * per_second).min(f64::from(rate.count));
+        self.updated = self.updated.max(now);
+    }
+
+    fn is_full(&mut self, rate: Rate, now: Instant) -> bool {
+        self.refill(rate, now);
+        self.tokens >= f64::from(rate.count)
+    }
+}
+
+/// One bucket per client address.
+pub(crate) struct PerAddress {
+    rate: Rate,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl PerAddress {
+    pub fn new(rate: Rate) -> Self {
+        PerAddress {
+            rate,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Whether `addr` may do one more thing at `now`.
+    pub fn allow(&self, addr: IpAddr, now: Instant) -> bool {
+        self.buckets
+            .lock()
+            .unwrap()
+            .entry(addr)
+            .or_insert_with(|| Bucket::full(self.rate, now))
+            .take(self.rate, now)
+    }
+
+    /// Puts back what `addr` was allowed last.
+    pub fn refund(&self, addr: IpAddr) {
+        if let Some(bucket) = self.buckets.lock().unwrap().get_mut(&addr) {
+            bucket.refund(self.rate);
+        }
+    }
+
+    pub fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    /// Forgets addresses whose bucket has refilled, so that the map only
+    /// holds recent clients.
+    pub fn forget_idle(&self, now: Instant) {
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| !bucket.is_full(self.rate, now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_allow_bursts_and_refill_over_time() {
+        let rate = Rate::per_second(2);
+        let start = Instant::now();
+        let mut bucket = Bucket::full(rate, start);
+        assert!(bucket.take(rate, start));
+        assert!(bucket.take(rate, start));
+        assert!(!bucket.take(rate, start));
+        assert!(bucket.take(rate, start + Duration::from_millis(500)));
+        assert!(!bucket.take(rate, start + Duration::from_millis(500)));
+        // Refilling stops at the burst size.
+        let later = start + Duration::from_secs(60);
+        assert!(bucket.take(rate, later));
+        assert!(bucket.take(rate, later));
+        assert!(!bucket.take(rate, later));
+        bucket.refund(rate);
+        assert!(bucket.take(rate, later));
+    }
+
+    #[test]
+    fn addresses_are_limited_separately_and_forgotten_once_refilled() {
+        let limit = PerAddress::new(Rate::per_minute(1));
+        let (a, b) = ([10, 0, 0, 1].into(), [10, 0, 0, 2].into());
+        let start = Instant::now();
+        assert!(limit.allow(a, start));
+        assert!(!limit.allow(a, start));
+        assert!(limit.allow(b, start));
+
+        limit.forget_idle(start + Duration::from_secs(30));
+        assert_eq!(limit.buckets.lock().unwrap().len(), 2);
+        limit.forget_idle(start + Duration::from_secs(60));
+        assert!(limit.buckets.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use poker_auth::{OidcClient, OidcConfig};
+use poker_broker::RedisBroker;
+use poker_core::RoomId;
+use poker_server::{Config, Hub, Login, Rate};
+use poker_store::SqliteStore;
+use poker_tracker::{JiraAuth, JiraConfig, JiraTracker};
+use poker_webhook::HttpWebhook;
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+/// Hosts OpenPlanningPoker rooms over WebSocket.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// How log lines are written.
+    #[arg(
+        long,
+        env = "POKER_LOG_FORMAT",
+        value_enum,
+        default_value_t = LogFormat::Text,
+        global = true
+    )]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    serve: Serve,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line, with the connection and room each line
+    /// concerns as fields, for log aggregators.
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the estimation report of a room, or of a team, as JSON.
+    Report {
+        /// SQLite database the server keeps its history in.
+        #[arg(long, env = "POKER_DATABASE")]
+        database: PathBuf,
+        /// Room to report on.
+        #[arg(required_unless_present = "team")]
+        room: Option<String>,
+        /// Report on every room started from this template instead.
+        #[arg(long, conflicts_with = "room")]
+        team: Option<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Serve {
+    /// Address to listen on.
+    #[arg(long, env = "POKER_BIND", default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+    /// Seconds a disconnected participant's seat is held for them to resume.
+    #[arg(long, env = "POKER_GRACE_PERIOD", default_value_t = 120, value_parser = clap::value_parser!(u64).range(1..))]
+    grace_period: u64,
+    /// Seconds without a message or ping before a participant is shown as idle.
+    #[arg(long, env = "POKER_IDLE_AFTER", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    idle_after: u64,
+    /// Messages one connection may send per second.
+    #[arg(long, env = "POKER_MESSAGE_RATE", default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+    message_rate: u32,
+    /// WebSocket connections one IP address may open per minute.
+    #[arg(long, env = "POKER_CONNECTION_RATE", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    connection_rate: u32,
+    /// Rooms one IP address may create per minute.
+    #[arg(long, env = "POKER_ROOM_RATE", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    room_rate: u32,
+    /// Wrong passphrases that may be tried per minute against one private
+    /// room, and from one IP address.
+    #[arg(long, env = "POKER_PASSPHRASE_RATE", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    passphrase_rate: u32,
+    /// Largest message a client may send, in bytes.
+    #[arg(long, env = "POKER_MAX_MESSAGE_SIZE", default_value_t = 1024 * 1024, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_message_size: usize,
+    /// Most participants one room may seat.
+    #[arg(long, env = "POKER_MAX_ROOM_SIZE", default_value_t = 100, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_room_size: usize,
+    /// Seconds a room nobody is seated in is kept before it is closed.
+    #[arg(long, env = "POKER_IDLE_ROOM_TTL", default_value_t = 24 * 60 * 60, value_parser = clap::value_parser!(u64).range(1..))]
+    idle_room_ttl: u64,
+    /// SQLite database to keep rooms and history in. Without it, everything
+    /// is lost when the server stops.
+    #[arg(long, env = "POKER_DATABASE")]
+    database: Option<PathBuf>,
+    /// Jira instance to fetch stories from and write estimates back to.
+    #[arg(long, env = "POKER_JIRA_URL")]
+    jira_url: Option<String>,
+    /// Jira user for basic authentication; without it the token is sent as a
+    /// bearer token.
+    #[arg(long, env = "POKER_JIRA_USER", requires = "jira_token")]
+    jira_user: Option<String>,
+    /// Jira API token or personal access token.
+    #[arg(long, env = "POKER_JIRA_TOKEN", hide_env_values = true)]
+    jira_token: Option<String>,
+    /// Jira field that receives estimates.
+    #[arg(
+        long,
+        env = "POKER_JIRA_ESTIMATE_FIELD",
+        default_value = "customfield_10016"
+    )]
+    jira_estimate_field: String,
+    /// OpenID Connect provider everyone must log in through, e.g.
+    /// `https://login.example.com/realms/eng`. Without it, anyone can create
+    /// and join rooms under any name.
+    #[arg(
+        long,
+        env = "POKER_OIDC_ISSUER",
+        requires_all = ["oidc_client_id", "oidc_client_secret", "oidc_redirect_url"]
+    )]
+    oidc_issuer: Option<String>,
+    /// Client id registered with the provider.
+    #[arg(long, env = "POKER_OIDC_CLIENT_ID")]
+    oidc_client_id: Option<String>,
+    /// Client secret issued by the provider.
+    #[arg(long, env = "POKER_OIDC_CLIENT_SECRET", hide_env_values = true)]
+    oidc_client_secret: Option<String>,
+    /// This server's `/auth/callback` URL as the browser reaches it, e.g.
+    /// `https://poker.example.com/auth/callback`.
+    #[arg(long, env = "POKER_OIDC_REDIRECT_URL")]
+    oidc_redirect_url: Option<String>,
+    /// Redis-compatible server shared by every instance behind the same load
+    /// balancer, e.g. `redis://:password@cache.internal:6379/0`. Without it,
+    /// this server runs on its own.
+    #[arg(long, env = "POKER_REDIS_URL", hide_env_values = true)]
+    redis_url: Option<String>,
+    /// URL that notifications about every room are posted to as JSON, e.g. a
+    /// chat tool's incoming webhook. Facilitators can add their own webhooks
+    /// to their rooms either way.
+    #[arg(long, env = "POKER_WEBHOOK_URL", hide_env_values = true)]
+    webhook_url: Option<String>,
+    /// Secret to sign the payloads posted to `--webhook-url` with.
+    #[arg(
+        long,
+        env = "POKER_WEBHOOK_SECRET",
+        hide_env_values = true,
+        requires = "webhook_url"
+    )]
+    webhook_secret: Option<String>,
+    /// Host that facilitators' room webhooks may reach even though it is not
+    /// on the public internet, e.g. an internal chat server. Repeat it or
+    /// separate hosts with commas.
+    #[arg(long, env = "POKER_WEBHOOK_ALLOW_HOSTS", value_delimiter = ',')]
+    webhook_allow_host: Vec<String>,
+    /// Address of a reverse proxy or load balancer in front of the server,
+    /// whose `Forwarded`/`X-Forwarded-For` headers are trusted to name the
+    /// client. Repeat it or separate addresses with commas.
+    #[arg(long, env = "POKER_TRUSTED_PROXIES", value_delimiter = ',')]
+    trusted_proxy: Vec<IpAddr>,
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let logs = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()));
+    match cli.log_format {
+        LogFormat::Text => logs.init(),
+        LogFormat::Json => logs.json().init(),
+    }
+    match cli.command {
+        Some(Command::Report {
+            database,
+            room,
+            team,
+        }) => report(&database, room.map(RoomId::new), team),
+        None => serve(cli.serve).await,
+    }
+}
+
+fn report(database: &Path, room: Option<RoomId>, team: Option<String>) -> std::io::Result<()> {
+    let store = SqliteStore::open(database).map_err(std::io::Error::other)?;
+    let hub = Hub::open(Config::default(), Arc::new(store), Instant::now())
+        .map_err(std::io::Error::other)?;
+    let (report, subject) = match (room, team) {
+        (Some(room), _) => (hub.report(&room), format!("room {room}")),
+        (None, Some(team)) => (hub.team_report(&team), format!("team {team:?}")),
+        (None, None) => unreachable!("clap requires a room or a team"),
+    };
+    let Some(report) = report.map_err(std::io::Error::other)? else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{subject} has no history in {}", database.display()),
+        ));
+    };
+    let json = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+    println!("{json}");
+    Ok(())
+}
+
+async fn serve(args: Serve) -> std::io::Result<()> {
+    let config = Config {
+        grace_period: Duration::from_secs(args.grace_period),
+        idle_after: Duration::from_secs(args.idle_after),
+        message_rate: Rate::per_second(args.message_rate),
+        connection_rate: Rate::per_minute(args.connection_rate),
+        room_rate: Rate::per_minute(args.room_rate),
+        passphrase_rate: Rate::per_minute(args.passphrase_rate),
+        max_message_size: args.max_message_size,
+        max_room_size: args.max_room_size,
+        idle_room_ttl: Duration::from_secs(args.idle_room_ttl),
+        webhook_allowlist: args.webhook_allow_host.clone(),
+        trusted_proxies: args.trusted_proxy.clone(),
+    };
+
+    let hub = match &args.database {
+        Some(path) => {
+            let store = SqliteStore::open(path).map_err(std::io::Error::other)?;
+            let hub = Hub::open(config, Arc::new(store), Instant::now())
+                .map_err(std::io::Error::other)?;
+            tracing::info!(database = %path.display(), rooms = hub.room_count(), "restored rooms");
+            hub
+        }
+        None => Hub::new(config),
+    };
+    let hub = match &args.redis_url {
+        Some(url) => {
+            let broker = RedisBroker::new(url).map_err(std::io::Error::other)?;
+            tracing::info!("sharing rooms through Redis");
+            hub.with_broker(Arc::new(broker))
+        }
+        None => hub,
+    };
+    let hub = match &args.jira_url {
+        Some(url) => {
+            let mut jira = JiraConfig::new(url.as_str(), args.jira_estimate_field.as_str());
+            jira.auth = match (args.jira_user.clone(), args.jira_token.clone()) {
+                (Some(user), Some(token)) => JiraAuth::Basic { user, token },
+                (None, Some(token)) => JiraAuth::Bearer(token),
+                _ => JiraAuth::None,
+            };
+            let tracker = JiraTracker::new(jira).map_err(std::io::Error::other)?;
+            tracing::info!(%url, "syncing stories with Jira");
+            hub.with_tracker(Arc::new(tracker))
+        }
+        None => hub,
+    };
+    let hub = match &args.webhook_url {
+        Some(url) => {
+            let mut webhook = HttpWebhook::new(url).map_err(std::io::Error::other)?;
+            if let Some(secret) = &args.webhook_secret {
+                webhook = webhook.with_secret(secret.as_str());
+            }
+            tracing::info!("posting notifications to a webhook");
+            hub.with_webhook(Arc::new(webhook))
+        }
+        None => hub,
+    };
+    let hub = match (
+        args.oidc_issuer,
+        args.oidc_client_id,
+        args.oidc_client_secret,
+        args.oidc_redirect_url,
+    ) {
+        (Some(issuer), Some(client_id), Some(secret), Some(redirect_url)) => {
+            let config = OidcConfig::new(issuer.as_str(), client_id, secret, redirect_url);
+            let client = OidcClient::discover(config)
+                .await
+                .map_err(std::io::Error::other)?;
+            tracing::info!(%issuer, "requiring login");
+            hub.with_login(Login::new(client))
+        }
+        _ => hub,
+    };
+
+    let listener = TcpListener::bind(args.bind).await?;
+    tracing::info!(addr = %listener.local_addr()?, "listening");
+    poker_server::serve(listener, Arc::new(hub), async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
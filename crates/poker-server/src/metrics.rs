@@ -0,0 +1,176 @@
+//! Operational metrics, served in the Prometheus text format on `/metrics`.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use poker_core::{RoomId, Template};
+use poker_store::{Result, RoomRecord, RoundRecord, Store, TemplateRecord};
+use prometheus_client::encoding::{text, EncodeLabelSet};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct StoreCall {
+    operation: &'static str,
+}
+
+/// Counters and gauges describing one server instance.
+pub struct Metrics {
+    registry: Registry,
+    rooms: Gauge,
+    clients: Gauge,
+    received: Counter,
+    sent: Counter,
+    store: Family<StoreCall, Histogram, fn() -> Histogram>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let mut registry = Registry::with_prefix("poker");
+        let rooms = Gauge::default();
+        registry.register("rooms", "Rooms hosted by this instance", rooms.clone());
+        let clients = Gauge::default();
+        registry.register(
+            "connected_clients",
+            "Open WebSocket connections",
+            clients.clone(),
+        );
+        let received = Counter::default();
+        registry.register(
+            "messages_received",
+            "Messages received from clients",
+            received.clone(),
+        );
+        let sent = Counter::default();
+        registry.register("messages_sent", "Messages sent to clients", sent.clone());
+        // From half a millisecond to about a second.
+        let store: Family<StoreCall, Histogram, fn() -> Histogram> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.0005, 2.0, 12)));
+        registry.register(
+            "store_duration_seconds",
+            "Time taken by calls to the room store",
+            store.clone(),
+        );
+        Metrics {
+            registry,
+            rooms,
+            clients,
+            received,
+            sent,
+            store,
+        }
+    }
+}
+
+impl Metrics {
+    pub(crate) fn client_connected(&self) {
+        self.clients.inc();
+    }
+
+    pub(crate) fn client_disconnected(&self) {
+        self.clients.dec();
+    }
+
+    pub(crate) fn message_received(&self) {
+        self.received.inc();
+    }
+
+    pub(crate) fn message_sent(&self) {
+        self.sent.inc();
+    }
+
+    /// Renders every metric, with `rooms` as the number of rooms hosted
+    /// right now.
+    pub fn render(&self, rooms: usize) -> String {
+        self.rooms.set(rooms as i64);
+        let mut out = String::new();
+        text::encode(&mut out, &self.registry).expect("writing to a string cannot fail");
+        out
+    }
+
+    fn time<T>(&self, operation: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.store
+            .get_or_create(&StoreCall { operation })
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+/// A store whose calls are timed.
+pub(crate) struct TimedStore {
+    pub inner: Arc<dyn Store>,
+    pub metrics: Arc<Metrics>,
+}
+
+impl Store for TimedStore {
+    fn save_room(&self, record: &RoomRecord) -> Result<()> {
+        self.metrics
+            .time("save_room", || self.inner.save_room(record))
+    }
+
+    fn delete_room(&self, id: &RoomId) -> Result<()> {
+        self.metrics
+            .time("delete_room", || self.inner.delete_room(id))
+    }
+
+    fn archive_room(&self, record: &RoomRecord) -> Result<()> {
+        self.metrics
+            .time("archive_room", || self.inner.archive_room(record))
+    }
+
+    fn archived_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        self.metrics
+            .time("archived_room", || self.inner.archived_room(id))
+    }
+
+    fn archived_team_rooms(&self, team: &str) -> Result<Vec<RoomRecord>> {
+        self.metrics.time("archived_team_rooms", || {
+            self.inner.archived_team_rooms(team)
+        })
+    }
+
+    fn load_rooms(&self) -> Result<Vec<RoomRecord>> {
+        self.metrics.time("load_rooms", || self.inner.load_rooms())
+    }
+
+    fn load_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        self.metrics.time("load_room", || self.inner.load_room(id))
+    }
+
+    fn record_round(&self, round: &RoundRecord) -> Result<()> {
+        self.metrics
+            .time("record_round", || self.inner.record_round(round))
+    }
+
+    fn rounds(&self, room: &RoomId) -> Result<Vec<RoundRecord>> {
+        self.metrics.time("rounds", || self.inner.rounds(room))
+    }
+
+    fn team_rounds(&self, team: &str) -> Result<Vec<RoundRecord>> {
+        self.metrics
+            .time("team_rounds", || self.inner.team_rounds(team))
+    }
+
+    fn save_template(&self, record: &TemplateRecord) -> Result<()> {
+        self.metrics
+            .time("save_template", || self.inner.save_template(record))
+    }
+
+    fn template(&self, name: &str) -> Result<Option<TemplateRecord>> {
+        self.metrics.time("template", || self.inner.template(name))
+    }
+
+    fn templates(&self) -> Result<Vec<Template>> {
+        self.metrics.time("templates", || self.inner.templates())
+    }
+
+    fn delete_template(&self, name: &str) -> Result<bool> {
+        self.metrics
+            .time("delete_template", || self.inner.delete_template(name))
+    }
+}
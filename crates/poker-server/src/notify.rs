@@ -0,0 +1,207 @@
+//! Telling people outside a room what happened in it.
+
+use std::sync::{Arc, OnceLock, Weak};
+
+use poker_core::{Clock, Event, Room, Timestamp};
+use poker_webhook::{
+    deliver, DeliveryFailure, HttpWebhook, Notification, NotificationEvent, RetryPolicy,
+    Subscription, Webhook, WebhookError,
+};
+
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::hub::RoomHandle;
+
+/// Where every room's notifications go besides the room's own webhooks, and
+/// how persistently deliveries are retried.
+#[derive(Clone, Default)]
+pub(crate) struct Notifier {
+    /// The server's webhook, told about every room.
+    pub webhook: Option<Arc<dyn Webhook>>,
+    pub retry: RetryPolicy,
+    /// Hosts room webhooks may reach although they are not public.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Notifier {
+    /// A notifier without a server webhook, letting room webhooks reach the
+    /// hosts `config` allows.
+    pub fn for_rooms(config: &Config) -> Self {
+        Notifier {
+            allowed_hosts: config.webhook_allowlist.clone(),
+            ..Notifier::default()
+        }
+    }
+}
+
+/// A webhook the facilitator subscribed to their room.
+pub(crate) struct RoomWebhook {
+    pub subscription: Subscription,
+    client: Arc<dyn Webhook>,
+    queue: Queue,
+}
+
+impl RoomWebhook {
+    /// Posts to the subscribed URL only while it points to a public address
+    /// or one of the `allowed` hosts.
+    pub fn new(subscription: Subscription, allowed: &[String]) -> Result<Self, WebhookError> {
+        let client = HttpWebhook::public(&subscription.url, allowed)?
+            .with_secret(subscription.secret.clone());
+        Ok(RoomWebhook {
+            subscription,
+            client: Arc::new(client),
+            queue: Queue::default(),
+        })
+    }
+}
+
+/// What `events`, which just happened in `room` at `at`, look like from
+/// outside.
+pub(crate) fn notifications(room: &Room, events: &[Event], at: Timestamp) -> Vec<Notification> {
+    let story = || room.round().story().map(str::to_owned);
+    let mut notifications: Vec<NotificationEvent> = Vec::new();
+    for event in events {
+        match event {
+            Event::VotingOpened(window) => notifications.push(NotificationEvent::VotingOpened {
+                story: story(),
+                deadline: window.deadline,
+                quorum: window.quorum,
+            }),
+            Event::VotingClosed { reason, story } => {
+                notifications.push(NotificationEvent::VotingClosed {
+                    story: story.clone(),
+                    reason: *reason,
+                    summary: None,
+                })
+            }
+            Event::Revealed { votes, summary } => {
+                // A window closing on quorum or at the deadline reveals
+                // right away; report the result with it.
+                if let Some(NotificationEvent::VotingClosed { summary: slot, .. }) =
+                    notifications.last_mut()
+                {
+                    slot.get_or_insert_with(|| summary.clone());
+                }
+                notifications.push(NotificationEvent::RoundRevealed {
+                    story: story(),
+                    votes: room.recorded_votes(votes),
+                    summary: summary.clone(),
+                });
+            }
+            Event::EstimateRecorded { story, estimate } => {
+                if let Some(story) = room.story(*story) {
+                    notifications.push(NotificationEvent::EstimateFinalized {
+                        story: story.title.clone(),
+                        key: story.key.clone(),
+                        estimate: estimate.clone(),
+                    });
+                }
+            }
+            Event::ParticipantLeft(_) if room.participants().next().is_none() => notifications
+                .push(NotificationEvent::SessionEnded {
+                    stories: room.stories().len(),
+                    estimated: room.stories().iter().filter(|s| s.is_estimated()).count(),
+                }),
+            _ => {}
+        }
+    }
+    notifications
+        .into_iter()
+        .map(|event| Notification {
+            room: room.id().clone(),
+            room_name: room.name().to_owned(),
+            at,
+            event,
+        })
+        .collect()
+}
+
+impl Notifier {
+    /// Queues `notifications` for the server's webhook, through `queue`, and
+    /// for each of the room's webhooks that wants them. Each receiver gets
+    /// its notifications one at a time, in the order they were queued, from
+    /// a task of its own. A room webhook that could not be reached is
+    /// reported back to `room`, with the time of the failure on `clock`.
+    pub fn dispatch(
+        &self,
+        room: &Weak<RoomHandle>,
+        queue: &Queue,
+        webhooks: &[RoomWebhook],
+        notifications: Vec<Notification>,
+        clock: &Arc<dyn Clock>,
+    ) {
+        if notifications.is_empty() {
+            return;
+        }
+        for webhook in webhooks {
+            let wanted = notifications
+                .iter()
+                .filter(|n| webhook.subscription.wants(n.event.kind()))
+                .cloned();
+            let id = webhook.subscription.id;
+            webhook.queue.push(wanted, |mut queued| {
+                let (client, room, retry) = (webhook.client.clone(), room.clone(), self.retry);
+                let clock = clock.clone();
+                tokio::spawn(async move {
+                    while let Some(notification) = queued.recv().await {
+                        let Err(err) = deliver(&*client, &notification, &retry).await else {
+                            continue;
+                        };
+                        tracing::warn!(room = %notification.room, webhook = %id, "webhook delivery failed: {err}");
+                        let failure = DeliveryFailure {
+                            at: clock.now(),
+                            event: notification.event.kind(),
+                            attempts: err.attempts,
+                            message: err.error.to_string(),
+                        };
+                        if let Some(room) = room.upgrade() {
+                            room.webhook_failed(id, failure);
+                        }
+                    }
+                });
+            });
+        }
+        if let Some(webhook) = &self.webhook {
+            queue.push(notifications, |mut queued| {
+                let (webhook, retry) = (webhook.clone(), self.retry);
+                tokio::spawn(async move {
+                    while let Some(notification) = queued.recv().await {
+                        if let Err(err) = deliver(&*webhook, &notification, &retry).await {
+                            tracing::warn!(room = %notification.room, "webhook delivery failed: {err}");
+                        }
+                    }
+                });
+            });
+        }
+    }
+}
+
+/// Notifications on their way to one receiver. The task delivering them is
+/// started with the first, and finishes what is left once the queue is
+/// dropped.
+#[derive(Default)]
+pub(crate) struct Queue(OnceLock<mpsc::UnboundedSender<Notification>>);
+
+impl Queue {
+    /// Appends `notifications`, calling `start` with the receiving end first
+    /// if nothing was queued before.
+    fn push(
+        &self,
+        notifications: impl IntoIterator<Item = Notification>,
+        start: impl FnOnce(mpsc::UnboundedReceiver<Notification>),
+    ) {
+        let mut notifications = notifications.into_iter().peekable();
+        if notifications.peek().is_none() {
+            return;
+        }
+        let queue = self.0.get_or_init(|| {
+            let (queue, queued) = mpsc::unbounded_channel();
+            start(queued);
+            queue
+        });
+        for notification in notifications {
+            let _ = queue.send(notification);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Bounded queues of messages on their way to clients.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use poker_protocol::ServerMessage;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Notify};
+
+/// Messages one client may have waiting before it counts as not keeping up.
+const CAPACITY: usize = 1024;
+
+/// Queue of messages waiting to be written to one client's socket.
+///
+/// Rooms queue their events while holding their lock, so sending never
+/// waits. A client that lets the queue fill up is cut off instead: the
+/// server would otherwise keep everything it does not read.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    queue: mpsc::Sender<ServerMessage>,
+    overflow: Overflow,
+}
+
+/// The receiving end of an [`Outbox`].
+pub(crate) struct Queued {
+    queue: mpsc::Receiver<ServerMessage>,
+    overflow: Overflow,
+}
+
+/// Tells whoever drives a connection that its outbox overflowed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overflow(Arc<OverflowState>);
+
+#[derive(Debug, Default)]
+struct OverflowState {
+    happened: AtomicBool,
+    notify: Notify,
+}
+
+impl Outbox {
+    pub(crate) fn new() -> (Outbox, Queued) {
+        let (queue, queued) = mpsc::channel(CAPACITY);
+        let overflow = Overflow::default();
+        let outbox = Outbox {
+            queue,
+            overflow: overflow.clone(),
+        };
+        (
+            outbox,
+            Queued {
+                queue: queued,
+                overflow,
+            },
+        )
+    }
+
+    /// Queues `msg`, unless the client is gone or too far behind.
+    pub fn send(&self, msg: ServerMessage) {
+        if let Err(TrySendError::Full(_)) = self.queue.try_send(msg) {
+            self.overflow.signal();
+        }
+    }
+
+    /// Watches for the outbox overflowing, without keeping it open.
+    pub(crate) fn overflow(&self) -> Overflow {
+        self.overflow.clone()
+    }
+}
+
+impl Queued {
+    /// The next message, or `None` once every outbox is dropped or one of
+    /// them overflowed.
+    pub async fn recv(&mut self) -> Option<ServerMessage> {
+        if self.overflow.happened() {
+            return None;
+        }
+        self.queue.recv().await
+    }
+}
+
+impl Overflow {
+    fn signal(&self) {
+        if !self.0.happened.swap(true, Ordering::Relaxed) {
+            self.0.notify.notify_waiters();
+        }
+    }
+
+    fn happened(&self) -> bool {
+        self.0.happened.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once the outbox has overflowed.
+    pub async fn wait(&self) {
+        let notified = self.0.notify.notified();
+        if self.happened() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn clients_that_fall_behind_are_cut_off() {
+        let (outbox, mut queued) = Outbox::new();
+        let overflow = outbox.overflow();
+        let waiting = tokio::spawn({
+            let overflow = overflow.clone();
+            async move { overflow.wait().await }
+        });
+        outbox.send(ServerMessage::Pong);
+        assert_eq!(queued.recv().await, Some(ServerMessage::Pong));
+        assert!(!waiting.is_finished());
+        for _ in 0..=CAPACITY {
+            outbox.send(ServerMessage::Pong);
+        }
+        assert_eq!(queued.recv().await, None);
+        // Whoever drives the connection hears of it, however late they ask.
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("the overflow is signalled")
+            .unwrap();
+        overflow.wait().await;
+    }
+}
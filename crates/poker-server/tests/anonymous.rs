@@ -0,0 +1,76 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use common::TestServer;
+use poker_core::{Card, Deck, Role, Settings};
+use poker_protocol::{ClientMessage, ServerMessage};
+use poker_server::{Config, Hub};
+use poker_store::{MemoryStore, Store};
+
+#[tokio::test]
+async fn anonymous_rooms_never_say_who_voted_what() {
+    let store = Arc::new(MemoryStore::new());
+    let hub = Hub::open(Config::default(), store.clone(), Instant::now()).unwrap();
+    let server = TestServer::start_hub(hub).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::UpdateSettings {
+            settings: Settings {
+                anonymous: true,
+                ..Settings::default()
+            },
+        })
+        .await;
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec!["Login".into()],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    for (client, card) in [(&mut alice, "3"), (&mut bob, "8")] {
+        client
+            .recv_matching(|m| matches!(m, ServerMessage::RoundStarted { .. }))
+            .await;
+        client
+            .send(ClientMessage::Vote {
+                card: Card::new(card),
+            })
+            .await;
+    }
+    for _ in 0..2 {
+        alice
+            .recv_matching(|m| matches!(m, ServerMessage::Voted { .. }))
+            .await;
+    }
+    alice.send(ClientMessage::Reveal).await;
+
+    for client in [&mut alice, &mut bob] {
+        let ServerMessage::Revealed { votes, summary } = client
+            .recv_matching(|m| matches!(m, ServerMessage::Revealed { .. }))
+            .await
+        else {
+            unreachable!();
+        };
+        assert!(votes.is_empty());
+        assert_eq!(summary.distribution.len(), 2);
+    }
+
+    let mut carol = server.connect().await;
+    let joined = carol.join(&room, "Carol", Role::Observer).await;
+    assert_eq!(joined.room.votes, None);
+    assert!(joined.room.summary.is_some());
+    assert!(joined.room.stories[0].rounds[0].votes.is_empty());
+
+    let history = store.rounds(&room).unwrap();
+    assert_eq!(history.len(), 1);
+    assert!(history[0].votes.is_empty());
+    assert_eq!(history[0].summary.distribution.len(), 2);
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use common::{create_room_with, TestServer};
+use poker_auth::stub::{StubProvider, StubUser};
+use poker_auth::{OidcClient, OidcConfig};
+use poker_core::{Deck, Role, RoomId, Settings, Template};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, PROTOCOL_VERSION};
+use poker_server::{Config, Hub, Login, Rate};
+use poker_store::{SqliteStore, TemplateOwner};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+fn join(room: &RoomId, name: &str, passphrase: Option<&str>) -> ClientMessage {
+    ClientMessage::Join {
+        version: PROTOCOL_VERSION,
+        room: room.clone(),
+        name: name.into(),
+        role: Role::Voter,
+        passphrase: passphrase.map(str::to_owned),
+        invite: None,
+    }
+}
+
+#[tokio::test]
+async fn private_rooms_take_their_passphrase() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await;
+    alice
+        .send(create_room_with("Secret").passphrase("open sesame"))
+        .await;
+    let ServerMessage::RoomCreated { room, .. } = alice.recv().await else {
+        panic!("expected room_created");
+    };
+
+    let mut mallory = server.connect().await;
+    mallory.send(join(&room, "Mallory", None)).await;
+    mallory.expect_error(ErrorCode::InvalidPassphrase).await;
+    mallory.send(join(&room, "Mallory", Some("sesame"))).await;
+    mallory.expect_error(ErrorCode::InvalidPassphrase).await;
+
+    alice.send(join(&room, "Alice", Some("open sesame"))).await;
+    let joined = alice.welcome().await;
+    assert_eq!(joined.room.participants.len(), 1);
+
+    // Rooms without a passphrase stay open to anyone with the code.
+    let open = server.create_room(Deck::fibonacci()).await;
+    mallory.send(join(&open, "Mallory", Some("ignored"))).await;
+    mallory.welcome().await;
+}
+
+#[tokio::test]
+async fn passphrase_survives_a_restart_as_a_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("poker.db");
+    let open = || {
+        let store = Arc::new(SqliteStore::open(&path).unwrap());
+        Hub::open(Config::default(), store, Instant::now()).unwrap()
+    };
+    let room = open()
+        .create_room("Secret", Deck::fibonacci(), Some("open sesame"))
+        .await
+        .unwrap();
+    let raw = std::fs::read(&path).unwrap();
+    assert!(!raw.windows(11).any(|w| w == b"open sesame"));
+
+    let server = TestServer::start_hub(open()).await;
+    let mut alice = server.connect().await;
+    alice.send(join(&room, "Alice", Some("guess"))).await;
+    alice.expect_error(ErrorCode::InvalidPassphrase).await;
+    alice.send(join(&room, "Alice", Some("open sesame"))).await;
+    alice.welcome().await;
+}
+
+#[tokio::test]
+async fn login_is_off_unless_configured() {
+    let server = TestServer::start().await;
+    assert_eq!(server.get("/auth/login").await.status, 404);
+    let mut client = server.connect().await;
+    client
+        .send(ClientMessage::Authenticate {
+            session: "anything".into(),
+        })
+        .await;
+    client.expect_error(ErrorCode::InvalidLogin).await;
+}
+
+#[tokio::test]
+async fn logged_in_users_join_under_their_real_name() {
+    let idp = StubProvider::start(
+        "poker",
+        "s3cret",
+        StubUser {
+            subject: "u-42".into(),
+            name: "Alice Example".into(),
+            email: "alice@example.com".into(),
+        },
+    )
+    .await;
+    let config = OidcConfig::new(
+        idp.issuer(),
+        "poker",
+        "s3cret",
+        "http://poker.test/auth/callback",
+    );
+    let client = OidcClient::discover(config).await.unwrap();
+    let server = TestServer::start_hub(Hub::default().with_login(Login::new(client))).await;
+    let room = server
+        .hub
+        .create_room("Team", Deck::fibonacci(), None)
+        .await
+        .unwrap();
+
+    let mut ws = server.connect().await;
+    ws.send(join(&room, "Anyone", None)).await;
+    ws.expect_error(ErrorCode::LoginRequired).await;
+    ws.send(ClientMessage::Authenticate {
+        session: "forged".into(),
+    })
+    .await;
+    ws.expect_error(ErrorCode::InvalidLogin).await;
+
+    // Play the browser: server -> provider -> back to the callback.
+    let browser = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+    let to_provider = server.get("/auth/login").await;
+    assert_eq!(to_provider.status, 303);
+    let authorize = to_provider.header("location").unwrap();
+    assert!(authorize.starts_with(&idp.issuer()));
+    let back = browser.get(authorize).send().await.unwrap();
+    let back = Url::parse(back.headers()["location"].to_str().unwrap()).unwrap();
+    assert_eq!(back.path(), "/auth/callback");
+    let callback = server
+        .get(&format!("/auth/callback?{}", back.query().unwrap()))
+        .await;
+    assert_eq!(callback.status, 200);
+    let body: serde_json::Value = serde_json::from_str(&callback.body).unwrap();
+    assert_eq!(body["name"], "Alice Example");
+
+    // The same state cannot be used twice.
+    let replay = server
+        .get(&format!("/auth/callback?{}", back.query().unwrap()))
+        .await;
+    assert_eq!(replay.status, 400);
+
+    ws.send(ClientMessage::Authenticate {
+        session: body["session"].as_str().unwrap().into(),
+    })
+    .await;
+    assert_eq!(
+        ws.recv().await,
+        ServerMessage::Authenticated {
+            name: "Alice Example".into(),
+            email: Some("alice@example.com".into()),
+        }
+    );
+    ws.send(join(&room, "Anyone", None)).await;
+    let joined = ws.welcome().await;
+    assert_eq!(joined.room.participants[0].name, "Alice Example");
+}
+
+#[tokio::test]
+async fn private_room_data_takes_a_seat_token_or_the_passphrase() {
+    let server = TestServer::start().await;
+    let room = server
+        .hub
+        .create_room("Secret", Deck::fibonacci(), Some("open sesame"))
+        .await
+        .unwrap();
+    let mut alice = server.connect().await;
+    alice.send(join(&room, "Alice", Some("open sesame"))).await;
+    let joined = alice.welcome().await;
+
+    for path in [
+        format!("/rooms/{room}/export"),
+        format!("/rooms/{room}/report"),
+    ] {
+        assert_eq!(server.get(&path).await.status, 403, "{path}");
+        let guess = [("X-Room-Passphrase", "sesame")];
+        assert_eq!(server.get_with(&path, &guess).await.status, 403, "{path}");
+        let forged = [("X-Room-Token", "forged")];
+        assert_eq!(server.get_with(&path, &forged).await.status, 403, "{path}");
+
+        let passphrase = [("X-Room-Passphrase", "open sesame")];
+        assert_eq!(
+            server.get_with(&path, &passphrase).await.status,
+            200,
+            "{path}"
+        );
+        let token = [("X-Room-Token", joined.token.as_str())];
+        assert_eq!(server.get_with(&path, &token).await.status, 200, "{path}");
+    }
+
+    let open = server.create_room(Deck::fibonacci()).await;
+    assert_eq!(
+        server.get(&format!("/rooms/{open}/export")).await.status,
+        200
+    );
+}
+
+/// Logs the provider's user in through `server` like a browser would and returns the
+/// session token.
+async fn log_in(server: &TestServer) -> String {
+    let browser = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+    let to_provider = server.get("/auth/login").await;
+    let authorize = to_provider.header("location").unwrap();
+    let back = browser.get(authorize).send().await.unwrap();
+    let back = Url::parse(back.headers()["location"].to_str().unwrap()).unwrap();
+    let callback = server
+        .get(&format!("/auth/callback?{}", back.query().unwrap()))
+        .await;
+    let body: serde_json::Value = serde_json::from_str(&callback.body).unwrap();
+    body["session"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn room_data_takes_a_login_session() {
+    let idp = StubProvider::start(
+        "poker",
+        "s3cret",
+        StubUser {
+            subject: "u-42".into(),
+            name: "Alice Example".into(),
+            email: "alice@example.com".into(),
+        },
+    )
+    .await;
+    let config = OidcConfig::new(
+        idp.issuer(),
+        "poker",
+        "s3cret",
+        "http://poker.test/auth/callback",
+    );
+    let client = OidcClient::discover(config).await.unwrap();
+    let server = TestServer::start_hub(Hub::default().with_login(Login::new(client))).await;
+    let room = server
+        .hub
+        .create_room("Team", Deck::fibonacci(), None)
+        .await
+        .unwrap();
+    let team = Template {
+        name: "Team".into(),
+        deck: Deck::fibonacci(),
+        settings: Settings::default(),
+        moderator: None,
+        members: Vec::new(),
+    };
+    let owner = TemplateOwner::Subject("u-42".into());
+    server.hub.save_template(team, Some(owner)).unwrap();
+
+    let paths = [
+        format!("/rooms/{room}/export"),
+        format!("/rooms/{room}/report"),
+        "/teams/Team/report".to_owned(),
+    ];
+    for path in &paths {
+        let anonymous = server.get(path).await;
+        assert_eq!(anonymous.status, 401, "{path}");
+        assert_eq!(anonymous.header("www-authenticate"), Some("Bearer"));
+        let forged = [("Authorization", "Bearer forged")];
+        assert_eq!(server.get_with(path, &forged).await.status, 401, "{path}");
+    }
+
+    let session = format!("Bearer {}", log_in(&server).await);
+    let logged_in = [("Authorization", session.as_str())];
+    assert_eq!(server.get_with(&paths[0], &logged_in).await.status, 200);
+    assert_eq!(server.get_with(&paths[1], &logged_in).await.status, 200);
+    // The team has no rooms, which is only told to its template's owner.
+    assert_eq!(server.get_with(&paths[2], &logged_in).await.status, 404);
+}
+
+#[tokio::test]
+async fn wrong_passphrases_are_limited() {
+    let config = Config {
+        passphrase_rate: Rate::per_minute(2),
+        ..Config::default()
+    };
+    let server = TestServer::start_with(config).await;
+    let secret = server
+        .hub
+        .create_room("Secret", Deck::fibonacci(), Some("open sesame"))
+        .await
+        .unwrap();
+    let other = server
+        .hub
+        .create_room("Other", Deck::fibonacci(), Some("swordfish"))
+        .await
+        .unwrap();
+
+    // Right passphrases don't count.
+    for name in ["Alice", "Bob", "Carol"] {
+        let mut client = server.connect().await;
+        client.send(join(&secret, name, Some("open sesame"))).await;
+        client.welcome().await;
+    }
+
+    let mut mallory = server.connect().await;
+    for guess in ["sesame", "open says me"] {
+        mallory.send(join(&secret, "Mallory", Some(guess))).await;
+        mallory.expect_error(ErrorCode::InvalidPassphrase).await;
+    }
+    mallory
+        .send(join(&secret, "Mallory", Some("open sesame")))
+        .await;
+    mallory.expect_error(ErrorCode::RateLimited).await;
+    // The address is out of guesses for every room.
+    mallory
+        .send(join(&other, "Mallory", Some("swordfish")))
+        .await;
+    mallory.expect_error(ErrorCode::RateLimited).await;
+    let download = [("X-Room-Passphrase", "swordfish")];
+    let refused = server
+        .get_with(&format!("/rooms/{other}/report"), &download)
+        .await;
+    assert_eq!(refused.status, 429);
+}
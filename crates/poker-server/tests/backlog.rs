@@ -0,0 +1,234 @@
+mod common;
+
+use common::TestServer;
+use poker_core::{Card, Deck, NewStory, Role, StoryId};
+use poker_exchange::{ColumnMapping, Format};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+
+fn vote(label: &str) -> ClientMessage {
+    ClientMessage::Vote {
+        card: Card::new(label),
+    }
+}
+
+#[tokio::test]
+async fn facilitator_works_through_the_backlog() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    let bob_id = bob.join(&room, "Bob", Role::Voter).await.you;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec!["Login".into(), "Search".into()],
+        })
+        .await;
+    let ServerMessage::StoriesAdded { stories } = bob.recv().await else {
+        panic!("expected stories_added");
+    };
+    let ids: Vec<StoryId> = stories.iter().map(|s| s.id).collect();
+    assert_eq!(stories[1].title, "Search");
+
+    alice.send(ClientMessage::NextStory).await;
+    assert_eq!(
+        bob.recv_matching(|m| matches!(m, ServerMessage::RoundStarted { .. }))
+            .await,
+        ServerMessage::RoundStarted {
+            story: Some("Login".into()),
+            story_id: Some(ids[0]),
+        }
+    );
+
+    // A split vote, then a re-vote that agrees.
+    for (a, b) in [("3", "13"), ("5", "5")] {
+        alice.send(vote(a)).await;
+        bob.send(vote(b)).await;
+        let bob_voted = ServerMessage::Voted {
+            participant: bob_id,
+        };
+        alice.recv_matching(|m| *m == bob_voted).await;
+        alice.send(ClientMessage::Reveal).await;
+        bob.recv_matching(|m| matches!(m, ServerMessage::Revealed { .. }))
+            .await;
+        alice.send(ClientMessage::Reset).await;
+    }
+    bob.recv_matching(|m| matches!(m, ServerMessage::RoundReset))
+        .await;
+    alice.send(vote("5")).await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("5"),
+        })
+        .await;
+    assert_eq!(
+        bob.recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+            .await,
+        ServerMessage::EstimateRecorded {
+            story: ids[0],
+            estimate: Card::new("5"),
+        }
+    );
+
+    let mut carol = server.connect().await;
+    let joined = carol.join(&room, "Carol", Role::Observer).await;
+    assert_eq!(joined.room.current_story, Some(ids[0]));
+    let login = &joined.room.stories[0];
+    assert_eq!(login.estimate, Some(Card::new("5")));
+    assert_eq!(login.rounds.len(), 3);
+    assert_eq!(login.rounds[0].votes.len(), 2);
+    assert_eq!(login.rounds[2].votes.len(), 1);
+    assert!(joined.room.stories[1].rounds.is_empty());
+
+    alice.send(ClientMessage::NextStory).await;
+    alice
+        .recv_matching(|m| {
+            *m == ServerMessage::RoundStarted {
+                story: Some("Search".into()),
+                story_id: Some(ids[1]),
+            }
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn backlog_changes_are_facilitator_only() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+
+    for msg in [
+        ClientMessage::AddStories {
+            stories: vec!["Login".into()],
+        },
+        ClientMessage::RemoveStory { story: StoryId(1) },
+        ClientMessage::StartStory { story: StoryId(1) },
+        ClientMessage::NextStory,
+        ClientMessage::RecordEstimate {
+            estimate: Card::new("1"),
+        },
+    ] {
+        bob.send(msg).await;
+        assert!(matches!(
+            bob.recv().await,
+            ServerMessage::Error {
+                code: ErrorCode::NotFacilitator,
+                ..
+            }
+        ));
+    }
+
+    alice.recv().await;
+    alice.send(ClientMessage::NextStory).await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::BacklogDone,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn stories_import_from_csv_with_a_column_mapping() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+
+    alice
+        .send(ClientMessage::ImportStories {
+            format: Format::Csv,
+            data: "Issue key,Summary\nWEB-1,Login\nWEB-2,Search\n".into(),
+            mapping: ColumnMapping {
+                title: "Summary".into(),
+                key: Some("Issue key".into()),
+                ..ColumnMapping::default()
+            },
+        })
+        .await;
+    let ServerMessage::StoriesAdded { stories } = alice.recv().await else {
+        panic!("expected stories_added");
+    };
+    let imported: Vec<_> = stories
+        .iter()
+        .map(|s| (s.key.as_deref(), s.title.as_str()))
+        .collect();
+    assert_eq!(
+        imported,
+        [(Some("WEB-1"), "Login"), (Some("WEB-2"), "Search")]
+    );
+
+    alice
+        .send(ClientMessage::ImportStories {
+            format: Format::Csv,
+            data: "Name\nLogin\n".into(),
+            mapping: ColumnMapping::default(),
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidImport,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn results_export_as_csv_and_json() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec![NewStory::new("Login").with_key("WEB-1"), "Search".into()],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    alice.send(vote("8")).await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("8"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+        .await;
+
+    let csv = server
+        .get(&format!(
+            "/rooms/{room}/export?format=csv&title=Summary&estimate=Story%20Points"
+        ))
+        .await;
+    assert_eq!(csv.status, 200);
+    assert_eq!(csv.header("content-type"), Some("text/csv; charset=utf-8"));
+    assert!(csv.header("content-disposition").unwrap().contains(".csv"));
+    let mut lines = csv.body.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("key,Summary,Story Points,"));
+    assert!(lines.next().unwrap().starts_with("WEB-1,Login,8,"));
+    assert!(lines.next().unwrap().starts_with(",Search,,"));
+
+    let json = server.get(&format!("/rooms/{room}/export")).await;
+    assert_eq!(json.status, 200);
+    let stories: serde_json::Value = serde_json::from_str(&json.body).unwrap();
+    assert_eq!(stories[0]["estimate"], "8");
+    assert_eq!(
+        stories[0]["rounds"][0]["distribution"],
+        serde_json::json!([{"card": "8", "count": 1}])
+    );
+
+    let missing = server.get("/rooms/nope/export").await;
+    assert_eq!(missing.status, 404);
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{create_room_with, TestServer};
+use poker_broker::stub::StubRedis;
+use poker_broker::{Broker, LocalBroker, RedisBroker};
+use poker_core::{Card, Deck, Presence, Role, RoomId};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, PROTOCOL_VERSION};
+use poker_server::{Config, Hub, Rate};
+
+/// Two servers sharing `broker`, as if behind one load balancer.
+async fn cluster(broker: Arc<dyn Broker>) -> (TestServer, TestServer) {
+    let a = TestServer::start_hub(Hub::default().with_broker(broker.clone())).await;
+    let b = TestServer::start_hub(Hub::default().with_broker(broker)).await;
+    (a, b)
+}
+
+/// Alice on `a` and Bob on `b` play a round in a room hosted by `a`.
+async fn play_a_round(a: &TestServer, b: &TestServer) {
+    let room = a.create_room(Deck::fibonacci()).await;
+    let mut alice = a.connect().await;
+    let alice_id = alice.join(&room, "Alice", Role::Voter).await.you;
+    let mut bob = b.connect().await;
+    let joined = bob.join(&room, "Bob", Role::Voter).await;
+    let names: Vec<_> = joined.room.participants.iter().map(|p| &p.name).collect();
+    assert_eq!(names, ["Alice", "Bob"]);
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::ParticipantJoined { participant } if participant.id == joined.you
+    ));
+
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("5"),
+        })
+        .await;
+    assert_eq!(
+        bob.recv().await,
+        ServerMessage::Voted {
+            participant: alice_id
+        }
+    );
+    bob.send(ClientMessage::Vote {
+        card: Card::new("8"),
+    })
+    .await;
+    let bob_voted = ServerMessage::Voted {
+        participant: joined.you,
+    };
+    assert_eq!(
+        alice.recv_matching(|msg| *msg == bob_voted).await,
+        bob_voted
+    );
+    alice.send(ClientMessage::Reveal).await;
+    let revealed = |msg: &ServerMessage| matches!(msg, ServerMessage::Revealed { .. });
+    let seen_by_alice = alice.recv_matching(revealed).await;
+    let seen_by_bob = bob.recv_matching(revealed).await;
+    assert_eq!(seen_by_alice, seen_by_bob);
+    assert!(a.hub.room(&room).is_some());
+    assert!(b.hub.room(&room).is_none());
+}
+
+#[tokio::test]
+async fn clients_on_different_instances_share_one_room() {
+    let (a, b) = cluster(Arc::new(LocalBroker::new())).await;
+    play_a_round(&a, &b).await;
+}
+
+#[tokio::test]
+async fn instances_share_rooms_through_redis() {
+    let redis = StubRedis::start().await;
+    let broker = |url: &str| -> Arc<dyn Broker> { Arc::new(RedisBroker::new(url).unwrap()) };
+    let a = TestServer::start_hub(Hub::default().with_broker(broker(&redis.url()))).await;
+    let b = TestServer::start_hub(Hub::default().with_broker(broker(&redis.url()))).await;
+    play_a_round(&a, &b).await;
+}
+
+#[tokio::test]
+async fn relayed_clients_resume_their_seat() {
+    let (a, b) = cluster(Arc::new(LocalBroker::new())).await;
+    let room = a.create_room(Deck::fibonacci()).await;
+    let mut alice = a.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = b.connect().await;
+    let joined = bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    bob.close().await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::PresenceChanged {
+            participant: joined.you,
+            presence: Presence::Gone,
+        }
+    );
+
+    let mut bob = b.connect().await;
+    let resumed = bob.resume(&room, &joined.token).await;
+    assert_eq!(resumed.you, joined.you);
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::PresenceChanged {
+            participant: joined.you,
+            presence: Presence::Connected,
+        }
+    );
+}
+
+#[tokio::test]
+async fn unknown_rooms_are_not_found_on_any_instance() {
+    let (_a, b) = cluster(Arc::new(LocalBroker::new())).await;
+    let mut client = b.connect().await;
+    client
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: RoomId::new("nowhere"),
+            name: "Carol".into(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::RoomNotFound,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn rooms_taken_over_elsewhere_are_evicted() {
+    let broker = Arc::new(LocalBroker::new());
+    let (a, b) = cluster(broker.clone()).await;
+    let room = a.create_room(Deck::fibonacci()).await;
+    let mut alice = a.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+
+    a.hub.renew_claims().await;
+    assert!(a.hub.room(&room).is_some());
+
+    // As if `a` had been cut off from the broker for longer than its lease.
+    broker
+        .set(
+            &format!("poker:room:{room}"),
+            b.hub.instance(),
+            Duration::from_secs(30),
+        )
+        .await
+        .unwrap();
+    a.hub.renew_claims().await;
+    assert!(a.hub.room(&room).is_none());
+    assert_eq!(
+        broker
+            .get(&format!("poker:room:{room}"))
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(b.hub.instance())
+    );
+    match alice.recv().await {
+        ServerMessage::Error { code, message } => {
+            assert_eq!(code, ErrorCode::NotJoined);
+            assert!(message.contains("resume"), "{message}");
+        }
+        other => panic!("expected the room to move, got {other:?}"),
+    }
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("5"),
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotJoined,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn relayed_clients_are_limited_by_their_address() {
+    let redis = StubRedis::start().await;
+    let hub = || {
+        let config = Config {
+            room_rate: Rate::per_minute(1),
+            ..Config::default()
+        };
+        Hub::new(config).with_broker(Arc::new(RedisBroker::new(&redis.url()).unwrap()))
+    };
+    let a = TestServer::start_hub(hub()).await;
+    let b = TestServer::start_hub(hub()).await;
+    let room = a
+        .hub
+        .create_room("Sprint", Deck::fibonacci(), None)
+        .await
+        .unwrap();
+    let mut bob = b.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+
+    // Bob's messages now go to `a`, which counts them against his address.
+    bob.send(create_room_with("Sprint 2")).await;
+    assert!(matches!(
+        bob.recv().await,
+        ServerMessage::RoomCreated { .. }
+    ));
+    bob.send(create_room_with("Sprint 3")).await;
+    bob.expect_error(ErrorCode::RateLimited).await;
+}
+
+#[tokio::test]
+async fn closed_rooms_give_up_their_claim() {
+    let broker: Arc<dyn Broker> = Arc::new(LocalBroker::new());
+    let ttl = Duration::from_secs(60 * 60);
+    let config = Config {
+        idle_room_ttl: ttl,
+        ..Config::default()
+    };
+    let a = TestServer::start_hub(Hub::new(config).with_broker(broker.clone())).await;
+    let b = TestServer::start_hub(Hub::default().with_broker(broker.clone())).await;
+    let room = a.create_room(Deck::fibonacci()).await;
+    let key = format!("poker:room:{room}");
+    assert!(broker.get(&key).await.unwrap().is_some());
+
+    let now = Instant::now();
+    a.hub.sweep(now);
+    a.hub.sweep(now + ttl);
+    assert_eq!(a.hub.room_count(), 0);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while broker.get(&key).await.unwrap().is_some() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the claim is released");
+
+    let mut bob = b.connect().await;
+    bob.send(ClientMessage::Join {
+        version: PROTOCOL_VERSION,
+        room,
+        name: "Bob".into(),
+        role: Role::Voter,
+        passphrase: None,
+        invite: None,
+    })
+    .await;
+    bob.expect_error(ErrorCode::RoomNotFound).await;
+}
@@ -0,0 +1,308 @@
+//! In-process server and WebSocket client shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use poker_core::{Card, Deck, ParticipantId, Role, RoomId};
+use poker_protocol::{ClientMessage, ErrorCode, RoomSnapshot, ServerMessage, PROTOCOL_VERSION};
+use poker_server::{Config, Hub};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub hub: Arc<Hub>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        Self::start_with(Config::default()).await
+    }
+
+    pub async fn start_with(config: Config) -> Self {
+        Self::start_hub(Hub::new(config)).await
+    }
+
+    pub async fn start_hub(hub: Hub) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hub = Arc::new(hub);
+        tokio::spawn(poker_server::serve(
+            listener,
+            hub.clone(),
+            std::future::pending(),
+        ));
+        TestServer { addr, hub }
+    }
+
+    pub async fn connect(&self) -> TestClient {
+        let url = format!("ws://{}/ws", self.addr);
+        let (ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        TestClient { ws }
+    }
+
+    /// Issues a plain HTTP GET and returns the status, headers and body.
+    pub async fn get(&self, path: &str) -> HttpResponse {
+        self.get_with(path, &[]).await
+    }
+
+    /// Like [`TestServer::get`], sending extra request headers.
+    pub async fn get_with(&self, path: &str, headers: &[(&str, &str)]) -> HttpResponse {
+        let mut stream = TcpStream::connect(self.addr).await.unwrap();
+        let extra: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect();
+        let request = format!(
+            "GET {path} HTTP/1.1\r\nHost: {}\r\n{extra}Connection: close\r\n\r\n",
+            self.addr
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut raw = Vec::new();
+        tokio::time::timeout(RECV_TIMEOUT, stream.read_to_end(&mut raw))
+            .await
+            .expect("timed out waiting for an HTTP response")
+            .unwrap();
+        let raw = String::from_utf8(raw).unwrap();
+        let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        let status = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_owned()))
+            .collect();
+        HttpResponse {
+            status,
+            headers,
+            body: body.to_owned(),
+        }
+    }
+
+    /// Creates a room over a throwaway connection.
+    pub async fn create_room(&self, deck: Deck) -> RoomId {
+        let mut client = self.connect().await;
+        client.send(create_room_with("Test room").deck(deck)).await;
+        match client.recv().await {
+            ServerMessage::RoomCreated { room, .. } => room,
+            other => panic!("expected room_created, got {other:?}"),
+        }
+    }
+}
+
+/// A `create_room` request for a room called `name`, with the default deck
+/// unless the builder methods say otherwise.
+pub fn create_room_with(name: &str) -> CreateRoom {
+    CreateRoom {
+        name: name.into(),
+        deck: Deck::default(),
+        passphrase: None,
+        template: None,
+    }
+}
+
+pub struct CreateRoom {
+    name: String,
+    deck: Deck,
+    passphrase: Option<String>,
+    template: Option<String>,
+}
+
+impl CreateRoom {
+    pub fn deck(mut self, deck: Deck) -> Self {
+        self.deck = deck;
+        self
+    }
+
+    pub fn passphrase(mut self, passphrase: &str) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    pub fn template(mut self, template: &str) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+}
+
+impl From<CreateRoom> for ClientMessage {
+    fn from(room: CreateRoom) -> Self {
+        ClientMessage::CreateRoom {
+            version: PROTOCOL_VERSION,
+            name: room.name,
+            deck: room.deck,
+            passphrase: room.passphrase,
+            template: room.template,
+        }
+    }
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    /// Lower-cased names.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// The contents of a `welcome` message.
+pub struct Joined {
+    pub you: ParticipantId,
+    pub token: String,
+    pub your_vote: Option<Card>,
+    pub room: RoomSnapshot,
+}
+
+pub struct TestClient {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl TestClient {
+    pub async fn send(&mut self, msg: impl Into<ClientMessage>) {
+        let text = serde_json::to_string(&msg.into()).unwrap();
+        self.send_raw(&text).await;
+    }
+
+    pub async fn send_raw(&mut self, text: &str) {
+        self.ws.send(Message::Text(text.into())).await.unwrap();
+    }
+
+    pub async fn recv(&mut self) -> ServerMessage {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.ws.next())
+                .await
+                .expect("timed out waiting for a server message")
+                .expect("connection closed")
+                .unwrap();
+            if let Message::Text(text) = frame {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Skips messages until one satisfies `wanted`.
+    pub async fn recv_matching(
+        &mut self,
+        wanted: impl Fn(&ServerMessage) -> bool,
+    ) -> ServerMessage {
+        loop {
+            let msg = self.recv().await;
+            if wanted(&msg) {
+                return msg;
+            }
+        }
+    }
+
+    /// Asserts that the next message is an error with `code`.
+    pub async fn expect_error(&mut self, code: ErrorCode) {
+        match self.recv().await {
+            ServerMessage::Error { code: got, .. } => assert_eq!(got, code),
+            other => panic!("expected {code:?}, got {other:?}"),
+        }
+    }
+
+    /// Asserts that the server sends nothing for a short while.
+    pub async fn expect_silence(&mut self) {
+        if let Ok(Some(frame)) =
+            tokio::time::timeout(Duration::from_millis(200), self.ws.next()).await
+        {
+            panic!("expected no message, got {frame:?}");
+        }
+    }
+
+    pub async fn join(&mut self, room: &RoomId, name: &str, role: Role) -> Joined {
+        self.send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: name.into(),
+            role,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+        self.welcome().await
+    }
+
+    /// Joins with an invite handed out when the room was started from a
+    /// template.
+    pub async fn join_invited(&mut self, room: &RoomId, invite: &str) -> Joined {
+        self.send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: String::new(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: Some(invite.into()),
+        })
+        .await;
+        self.welcome().await
+    }
+
+    pub async fn resume(&mut self, room: &RoomId, token: &str) -> Joined {
+        self.send(ClientMessage::Resume {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            token: token.into(),
+        })
+        .await;
+        self.welcome().await
+    }
+
+    pub async fn welcome(&mut self) -> Joined {
+        match self.recv().await {
+            ServerMessage::Welcome {
+                you,
+                token,
+                your_vote,
+                room,
+                ..
+            } => Joined {
+                you,
+                token,
+                your_vote,
+                room: *room,
+            },
+            other => panic!("expected welcome, got {other:?}"),
+        }
+    }
+
+    /// Waits for the server to close the connection, skipping anything it
+    /// sends first.
+    pub async fn expect_closed(&mut self) {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.ws.next())
+                .await
+                .expect("timed out waiting for the connection to close");
+            match frame {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        self.ws.close(None).await.unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{create_room_with, TestServer};
+use poker_core::{Deck, Role};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, PROTOCOL_VERSION};
+use poker_server::{Config, Rate};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+#[tokio::test]
+async fn connections_that_send_too_fast_are_refused() {
+    let server = TestServer::start_with(Config {
+        message_rate: Rate::per_minute(3),
+        ..Config::default()
+    })
+    .await;
+    let mut alice = server.connect().await;
+    for _ in 0..3 {
+        alice.send(ClientMessage::ListTemplates).await;
+        assert!(matches!(
+            alice.recv().await,
+            ServerMessage::Templates { .. }
+        ));
+    }
+    alice.send(ClientMessage::ListTemplates).await;
+    alice.expect_error(ErrorCode::RateLimited).await;
+
+    // The limit is per connection.
+    let mut bob = server.connect().await;
+    bob.send(ClientMessage::ListTemplates).await;
+    assert!(matches!(bob.recv().await, ServerMessage::Templates { .. }));
+}
+
+#[tokio::test]
+async fn connections_that_keep_flooding_are_closed() {
+    let server = TestServer::start_with(Config {
+        message_rate: Rate::per_minute(3),
+        ..Config::default()
+    })
+    .await;
+    let mut alice = server.connect().await;
+    for _ in 0..3 {
+        alice.send(ClientMessage::Ping).await;
+    }
+    // Going over the limit is answered once, not once per message.
+    for _ in 0..3 {
+        alice.send(ClientMessage::Ping).await;
+    }
+    for _ in 0..3 {
+        assert_eq!(alice.recv().await, ServerMessage::Pong);
+    }
+    alice.expect_error(ErrorCode::RateLimited).await;
+    alice.expect_silence().await;
+
+    // Another burst while throttled ends the connection.
+    alice.send(ClientMessage::Ping).await;
+    alice.expect_closed().await;
+}
+
+#[tokio::test]
+async fn addresses_that_connect_too_often_are_turned_away() {
+    let server = TestServer::start_with(Config {
+        connection_rate: Rate::per_minute(2),
+        ..Config::default()
+    })
+    .await;
+    let _alice = server.connect().await;
+    let _bob = server.connect().await;
+    let url = format!("ws://{}/ws", server.addr);
+    match tokio_tungstenite::connect_async(url).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 429);
+        }
+        Err(other) => panic!("expected 429, got {other}"),
+        Ok(_) => panic!("expected the connection to be refused"),
+    }
+}
+
+#[tokio::test]
+async fn clients_behind_a_trusted_proxy_are_limited_one_by_one() {
+    let server = TestServer::start_with(Config {
+        connection_rate: Rate::per_minute(1),
+        trusted_proxies: vec!["127.0.0.1".parse().unwrap()],
+        ..Config::default()
+    })
+    .await;
+    let connect = |client: &'static str| {
+        let mut request = format!("ws://{}/ws", server.addr)
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", HeaderValue::from_static(client));
+        tokio_tungstenite::connect_async(request)
+    };
+    let _alice = connect("203.0.113.1").await.unwrap();
+    let _bob = connect("203.0.113.2").await.unwrap();
+    match connect("203.0.113.1").await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 429);
+        }
+        Err(other) => panic!("expected 429, got {other}"),
+        Ok(_) => panic!("expected the connection to be refused"),
+    }
+}
+
+#[tokio::test]
+async fn addresses_that_create_too_many_rooms_are_refused() {
+    let server = TestServer::start_with(Config {
+        room_rate: Rate::per_minute(1),
+        ..Config::default()
+    })
+    .await;
+    let mut alice = server.connect().await;
+    alice.send(create_room_with("Sprint 12")).await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::RoomCreated { .. }
+    ));
+
+    // Reconnecting doesn't help; the limit is per address.
+    let mut again = server.connect().await;
+    again.send(create_room_with("Sprint 12")).await;
+    again.expect_error(ErrorCode::RateLimited).await;
+    assert_eq!(server.hub.room_count(), 1);
+}
+
+#[tokio::test]
+async fn oversized_messages_close_the_connection() {
+    let server = TestServer::start_with(Config {
+        max_message_size: 1024,
+        ..Config::default()
+    })
+    .await;
+    let mut alice = server.connect().await;
+    let name = "a".repeat(2048);
+    alice.send(create_room_with(&name)).await;
+    alice.expect_closed().await;
+    assert_eq!(server.hub.room_count(), 0);
+}
+
+#[tokio::test]
+async fn full_rooms_turn_newcomers_away() {
+    let server = TestServer::start_with(Config {
+        max_room_size: 2,
+        ..Config::default()
+    })
+    .await;
+    let room = server.create_room(Deck::default()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Observer).await;
+
+    let mut carol = server.connect().await;
+    carol
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: "Carol".into(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+    carol.expect_error(ErrorCode::RoomFull).await;
+
+    // A seat that is handed back can be taken.
+    bob.send(ClientMessage::Leave).await;
+    assert_eq!(bob.recv().await, ServerMessage::Left);
+    carol.join(&room, "Carol", Role::Voter).await;
+}
+
+#[tokio::test]
+async fn rooms_left_empty_are_closed() {
+    let ttl = Duration::from_secs(60 * 60);
+    let server = TestServer::start_with(Config {
+        idle_room_ttl: ttl,
+        ..Config::default()
+    })
+    .await;
+    let empty = server.create_room(Deck::default()).await;
+    let busy = server.create_room(Deck::default()).await;
+    let mut alice = server.connect().await;
+    alice.join(&busy, "Alice", Role::Voter).await;
+
+    let now = Instant::now();
+    server.hub.sweep(now);
+    server.hub.sweep(now + ttl / 2);
+    assert_eq!(server.hub.room_count(), 2);
+    server.hub.sweep(now + ttl);
+    assert_eq!(server.hub.room_count(), 1);
+
+    let mut bob = server.connect().await;
+    bob.send(ClientMessage::Join {
+        version: PROTOCOL_VERSION,
+        room: empty,
+        name: "Bob".into(),
+        role: Role::Voter,
+        passphrase: None,
+        invite: None,
+    })
+    .await;
+    bob.expect_error(ErrorCode::RoomNotFound).await;
+    bob.join(&busy, "Bob", Role::Voter).await;
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use std::time::Duration;
+
+use common::TestServer;
+use poker_core::{Card, Deck, Role, RoomId};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, PROTOCOL_VERSION};
+
+/// The value of a sample in a Prometheus text exposition.
+fn sample(body: &str, name: &str) -> Option<f64> {
+    body.lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+/// Scrapes until `name` reaches `value`; the server learns about closed
+/// connections a moment after they close.
+async fn settle(server: &TestServer, name: &str, value: f64) -> String {
+    for _ in 0..50 {
+        let body = server.get("/metrics").await.body;
+        if sample(&body, name) == Some(value) {
+            return body;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{name} never reached {value}");
+}
+
+#[tokio::test]
+async fn metrics_describe_rooms_clients_messages_and_storage() {
+    let server = TestServer::start().await;
+    let metrics = server.get("/metrics").await;
+    assert_eq!(metrics.status, 200);
+    assert!(metrics
+        .header("content-type")
+        .unwrap()
+        .starts_with("application/openmetrics-text"));
+    assert_eq!(sample(&metrics.body, "poker_rooms"), Some(0.0));
+
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("5"),
+        })
+        .await;
+    alice.recv().await;
+
+    let body = settle(&server, "poker_connected_clients", 1.0).await;
+    assert_eq!(sample(&body, "poker_rooms"), Some(1.0));
+    // create_room, join and vote.
+    assert_eq!(sample(&body, "poker_messages_received_total"), Some(3.0));
+    // room_created, welcome and voted.
+    assert_eq!(sample(&body, "poker_messages_sent_total"), Some(3.0));
+    let saves = sample(
+        &body,
+        "poker_store_duration_seconds_count{operation=\"save_room\"}",
+    );
+    assert!(saves.unwrap() >= 3.0);
+
+    alice.close().await;
+    settle(&server, "poker_connected_clients", 0.0).await;
+}
+
+#[tokio::test]
+async fn refused_joins_never_look_for_the_room() {
+    let server = TestServer::start().await;
+    let lookups = "poker_store_duration_seconds_count{operation=\"load_room\"}";
+    let join = |version| ClientMessage::Join {
+        version,
+        room: RoomId::new("nope"),
+        name: "Mallory".into(),
+        role: Role::Voter,
+        passphrase: None,
+        invite: None,
+    };
+    let mut client = server.connect().await;
+    client.send(join(PROTOCOL_VERSION + 1)).await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::UnsupportedVersion,
+            ..
+        }
+    ));
+    let body = server.get("/metrics").await.body;
+    assert_eq!(sample(&body, lookups).unwrap_or_default(), 0.0);
+
+    client.send(join(PROTOCOL_VERSION)).await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::RoomNotFound,
+            ..
+        }
+    ));
+    let body = server.get("/metrics").await.body;
+    assert_eq!(sample(&body, lookups), Some(1.0));
+}
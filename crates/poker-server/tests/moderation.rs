@@ -0,0 +1,181 @@
+mod common;
+
+use common::TestServer;
+use poker_core::{Card, Deck, Phase, Role, Settings};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+
+fn vote(label: &str) -> ClientMessage {
+    ClientMessage::Vote {
+        card: Card::new(label),
+    }
+}
+
+fn is_error(msg: &ServerMessage, expected: ErrorCode) -> bool {
+    matches!(msg, ServerMessage::Error { code, .. } if *code == expected)
+}
+
+#[tokio::test]
+async fn creator_is_facilitator_and_others_cannot_moderate() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let alice_id = alice.join(&room, "Alice", Role::Voter).await.you;
+    let mut bob = server.connect().await;
+    let joined = bob.join(&room, "Bob", Role::Voter).await;
+    assert_eq!(joined.room.facilitator, Some(alice_id));
+    alice.recv().await;
+
+    bob.send(vote("3")).await;
+    bob.recv().await;
+    alice.recv().await;
+    for msg in [
+        ClientMessage::Reveal,
+        ClientMessage::Reset,
+        ClientMessage::StartRound { story: None },
+        ClientMessage::Kick {
+            participant: alice_id,
+        },
+        ClientMessage::SetDeck {
+            deck: Deck::t_shirt(),
+        },
+        ClientMessage::UpdateSettings {
+            settings: Settings {
+                auto_reveal: true,
+                ..Settings::default()
+            },
+        },
+        ClientMessage::HandOver {
+            participant: joined.you,
+        },
+    ] {
+        bob.send(msg).await;
+        assert!(is_error(&bob.recv().await, ErrorCode::NotFacilitator));
+    }
+    alice.expect_silence().await;
+}
+
+#[tokio::test]
+async fn kicked_participant_loses_seat_and_token() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    let bob_seat = bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::Kick {
+            participant: bob_seat.you,
+        })
+        .await;
+    let kicked = ServerMessage::ParticipantKicked {
+        participant: bob_seat.you,
+    };
+    assert_eq!(alice.recv().await, kicked);
+    assert_eq!(bob.recv().await, kicked);
+
+    bob.send(vote("3")).await;
+    assert!(is_error(&bob.recv().await, ErrorCode::NotJoined));
+    bob.send(ClientMessage::Resume {
+        version: poker_protocol::PROTOCOL_VERSION,
+        room: room.clone(),
+        token: bob_seat.token,
+    })
+    .await;
+    assert!(is_error(&bob.recv().await, ErrorCode::InvalidSession));
+    bob.join(&room, "Bob", Role::Voter).await;
+}
+
+#[tokio::test]
+async fn hand_over_moves_moderation() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    let bob_id = bob.join(&room, "Bob", Role::Voter).await.you;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::HandOver {
+            participant: bob_id,
+        })
+        .await;
+    let changed = ServerMessage::FacilitatorChanged {
+        participant: bob_id,
+    };
+    assert_eq!(alice.recv().await, changed);
+    assert_eq!(bob.recv().await, changed);
+
+    alice.send(ClientMessage::Reset).await;
+    assert!(is_error(&alice.recv().await, ErrorCode::NotFacilitator));
+    bob.send(ClientMessage::Reset).await;
+    assert_eq!(bob.recv().await, ServerMessage::RoundReset);
+}
+
+#[tokio::test]
+async fn facilitator_changes_deck() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice.send(vote("5")).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::SetDeck {
+            deck: Deck::t_shirt(),
+        })
+        .await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::DeckChanged {
+            deck: Deck::t_shirt(),
+            cards: Deck::t_shirt().cards().to_vec(),
+        }
+    );
+    alice.send(vote("5")).await;
+    assert!(is_error(&alice.recv().await, ErrorCode::InvalidCard));
+}
+
+#[tokio::test]
+async fn auto_reveal_once_everyone_voted() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    let settings = Settings {
+        auto_reveal: true,
+        ..Settings::default()
+    };
+    alice
+        .send(ClientMessage::UpdateSettings {
+            settings: settings.clone(),
+        })
+        .await;
+    let changed = ServerMessage::SettingsChanged { settings };
+    assert_eq!(alice.recv().await, changed);
+    assert_eq!(bob.recv().await, changed);
+
+    alice.send(vote("3")).await;
+    alice.recv().await;
+    bob.recv().await;
+    bob.send(vote("3")).await;
+    for client in [&mut alice, &mut bob] {
+        assert!(matches!(client.recv().await, ServerMessage::Voted { .. }));
+        let ServerMessage::Revealed { summary, .. } = client.recv().await else {
+            panic!("expected automatic reveal");
+        };
+        assert!(summary.numeric.unwrap().consensus);
+    }
+
+    let mut carol = server.connect().await;
+    let joined = carol.join(&room, "Carol", Role::Voter).await;
+    assert_eq!(joined.room.phase, Phase::Revealed);
+    assert!(joined.room.settings.auto_reveal);
+}
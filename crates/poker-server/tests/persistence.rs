@@ -0,0 +1,138 @@
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use common::TestServer;
+use poker_core::{Card, Deck, Phase, Presence, Role};
+use poker_protocol::{ClientMessage, ServerMessage};
+use poker_server::{Config, Hub};
+use poker_store::{SqliteStore, Store};
+
+async fn server_on(path: &Path) -> TestServer {
+    let store = Arc::new(SqliteStore::open(path).unwrap());
+    TestServer::start_hub(Hub::open(Config::default(), store, Instant::now()).unwrap()).await
+}
+
+#[tokio::test]
+async fn rooms_and_seats_survive_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("poker.db");
+
+    let first = server_on(&path).await;
+    let room = first.create_room(Deck::t_shirt()).await;
+    let mut alice = first.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = first.connect().await;
+    let bob_id = bob.join(&room, "Bob", Role::Observer).await.you;
+    alice
+        .send(ClientMessage::StartRound {
+            story: Some("Login page".into()),
+        })
+        .await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("M"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::Voted { .. }))
+        .await;
+
+    let second = server_on(&path).await;
+    assert_eq!(second.hub.room_count(), 1);
+    let mut alice = second.connect().await;
+    let resumed = alice.resume(&room, &joined.token).await;
+    assert_eq!(resumed.you, joined.you);
+    assert_eq!(resumed.your_vote, Some(Card::new("M")));
+    assert_eq!(resumed.room.name, "Test room");
+    assert_eq!(resumed.room.story.as_deref(), Some("Login page"));
+    assert_eq!(resumed.room.phase, Phase::Voting);
+    assert_eq!(resumed.room.cards.len(), Deck::t_shirt().cards().len());
+    let bob = resumed
+        .room
+        .participants
+        .iter()
+        .find(|p| p.id == bob_id)
+        .unwrap();
+    assert_eq!(bob.role, Role::Observer);
+    assert_eq!(bob.presence, Presence::Gone);
+}
+
+#[tokio::test]
+async fn revealed_rounds_are_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("poker.db");
+
+    let server = server_on(&path).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::StartRound {
+            story: Some("Search".into()),
+        })
+        .await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("8"),
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::Revealed { .. }))
+        .await;
+
+    let rounds = SqliteStore::open(&path).unwrap().rounds(&room).unwrap();
+    assert_eq!(rounds.len(), 1);
+    assert_eq!(rounds[0].story.as_deref(), Some("Search"));
+    assert_eq!(rounds[0].votes[0].name, "Alice");
+    assert_eq!(rounds[0].votes[0].card, Card::new("8"));
+    assert_eq!(rounds[0].summary.vote_count(), 1);
+}
+
+#[tokio::test]
+async fn backlog_and_estimates_survive_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("poker.db");
+
+    let first = server_on(&path).await;
+    let room = first.create_room(Deck::fibonacci()).await;
+    let mut alice = first.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec!["Login".into(), "Search".into()],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("3"),
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("3"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+        .await;
+
+    let second = server_on(&path).await;
+    let mut alice = second.connect().await;
+    let resumed = alice.resume(&room, &joined.token).await;
+    let titles: Vec<_> = resumed.room.stories.iter().map(|s| &s.title).collect();
+    assert_eq!(titles, ["Login", "Search"]);
+    assert_eq!(resumed.room.stories[0].estimate, Some(Card::new("3")));
+    assert_eq!(resumed.room.stories[0].rounds.len(), 1);
+
+    alice.send(ClientMessage::NextStory).await;
+    let ServerMessage::RoundStarted { story_id, .. } = alice.recv().await else {
+        panic!("expected round_started");
+    };
+    assert_eq!(story_id, Some(resumed.room.stories[1].id));
+}
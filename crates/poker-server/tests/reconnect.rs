@@ -0,0 +1,235 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use poker_core::{Card, Deck, Presence, Role};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+use poker_server::Config;
+
+const GRACE: Duration = Duration::from_secs(60);
+const IDLE: Duration = Duration::from_secs(20);
+
+async fn server() -> TestServer {
+    TestServer::start_with(Config {
+        grace_period: GRACE,
+        idle_after: IDLE,
+        ..Config::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn reconnect_within_grace_restores_seat_name_and_hidden_vote() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("5"),
+        })
+        .await;
+    alice.recv().await;
+    bob.recv().await;
+
+    alice.close().await;
+    assert_eq!(
+        bob.recv().await,
+        ServerMessage::PresenceChanged {
+            participant: joined.you,
+            presence: Presence::Gone,
+        }
+    );
+    server.hub.sweep(Instant::now() + IDLE / 2);
+
+    let mut alice = server.connect().await;
+    let resumed = alice.resume(&room, &joined.token).await;
+    assert_eq!(resumed.you, joined.you);
+    assert_eq!(resumed.token, joined.token);
+    assert_eq!(resumed.your_vote, Some(Card::new("5")));
+    let me = resumed
+        .room
+        .participants
+        .iter()
+        .find(|p| p.id == joined.you)
+        .unwrap();
+    assert_eq!(me.name, "Alice");
+    assert_eq!(me.presence, Presence::Connected);
+    assert!(me.voted);
+    assert_eq!(
+        bob.recv().await,
+        ServerMessage::PresenceChanged {
+            participant: joined.you,
+            presence: Presence::Connected,
+        }
+    );
+}
+
+#[tokio::test]
+async fn seat_is_freed_once_grace_expires() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice.close().await;
+    assert!(matches!(
+        bob.recv().await,
+        ServerMessage::PresenceChanged {
+            presence: Presence::Gone,
+            ..
+        }
+    ));
+    server.hub.sweep(Instant::now() + GRACE);
+    let left = ServerMessage::ParticipantLeft {
+        participant: joined.you,
+    };
+    assert_eq!(bob.recv_matching(|m| *m == left).await, left);
+
+    let mut alice = server.connect().await;
+    alice
+        .send(ClientMessage::Resume {
+            version: poker_protocol::PROTOCOL_VERSION,
+            room,
+            token: joined.token,
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidSession,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn explicit_leave_cannot_be_resumed() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    alice.send(ClientMessage::Leave).await;
+    assert_eq!(alice.recv().await, ServerMessage::Left);
+
+    alice
+        .send(ClientMessage::Resume {
+            version: poker_protocol::PROTOCOL_VERSION,
+            room,
+            token: joined.token,
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidSession,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn silent_clients_turn_idle_until_they_ping() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let alice_id = alice.join(&room, "Alice", Role::Voter).await.you;
+    let mut bob = server.connect().await;
+    let bob_id = bob.join(&room, "Bob", Role::Voter).await.you;
+    alice.recv().await;
+
+    server.hub.sweep(Instant::now() + IDLE);
+    let idle = |participant| ServerMessage::PresenceChanged {
+        participant,
+        presence: Presence::Idle,
+    };
+    // Both went silent, so both turn idle, in no particular order.
+    for client in [&mut alice, &mut bob] {
+        let seen = [client.recv().await, client.recv().await];
+        assert!(seen.contains(&idle(alice_id)));
+        assert!(seen.contains(&idle(bob_id)));
+    }
+
+    alice.send(ClientMessage::Ping).await;
+    let back = ServerMessage::PresenceChanged {
+        participant: alice_id,
+        presence: Presence::Connected,
+    };
+    assert_eq!(alice.recv().await, back);
+    assert_eq!(alice.recv().await, ServerMessage::Pong);
+    assert_eq!(bob.recv().await, back);
+}
+
+#[tokio::test]
+async fn resuming_from_a_second_connection_replaces_the_first() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut laptop = server.connect().await;
+    let joined = laptop.join(&room, "Alice", Role::Voter).await;
+
+    let mut phone = server.connect().await;
+    let resumed = phone.resume(&room, &joined.token).await;
+    assert_eq!(resumed.you, joined.you);
+    assert!(matches!(
+        laptop.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::SessionReplaced,
+            ..
+        }
+    ));
+
+    laptop
+        .send(ClientMessage::Vote {
+            card: Card::new("3"),
+        })
+        .await;
+    assert!(matches!(
+        laptop.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotJoined | ErrorCode::SessionReplaced,
+            ..
+        }
+    ));
+
+    // The stale connection going away must not touch the resumed seat.
+    laptop.close().await;
+    phone
+        .send(ClientMessage::Vote {
+            card: Card::new("3"),
+        })
+        .await;
+    assert_eq!(
+        phone.recv().await,
+        ServerMessage::Voted {
+            participant: joined.you
+        }
+    );
+}
+
+#[tokio::test]
+async fn unknown_token_is_rejected() {
+    let server = server().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut client = server.connect().await;
+    client
+        .send(ClientMessage::Resume {
+            version: poker_protocol::PROTOCOL_VERSION,
+            room,
+            token: "not-a-token".into(),
+        })
+        .await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidSession,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{create_room_with, TestServer};
+use poker_analytics::{Report, Scope};
+use poker_core::{Card, Deck, ManualClock, Role, Settings, Template, Timestamp};
+use poker_protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use poker_server::{Config, Hub};
+use poker_store::TemplateOwner;
+
+const START: Timestamp = Timestamp(1_700_000_000_000);
+
+fn vote(label: &str) -> ClientMessage {
+    ClientMessage::Vote {
+        card: Card::new(label),
+    }
+}
+
+#[tokio::test]
+async fn report_sums_up_the_rooms_history() {
+    let clock = Arc::new(ManualClock::new(START));
+    let server = TestServer::start_hub(Hub::default().with_clock(clock)).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec!["Login".into()],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    bob.recv_matching(|m| matches!(m, ServerMessage::RoundStarted { .. }))
+        .await;
+    for (first, second) in [("3", "8"), ("5", "5")] {
+        alice.send(vote(first)).await;
+        bob.send(vote(second)).await;
+        for _ in 0..2 {
+            alice
+                .recv_matching(|m| matches!(m, ServerMessage::Voted { .. }))
+                .await;
+        }
+        alice.send(ClientMessage::Reveal).await;
+        alice
+            .recv_matching(|m| matches!(m, ServerMessage::Revealed { .. }))
+            .await;
+        if first != second {
+            alice.send(ClientMessage::Reset).await;
+            bob.recv_matching(|m| *m == ServerMessage::RoundReset).await;
+        }
+    }
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("5"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+        .await;
+
+    let response = server.get(&format!("/rooms/{room}/report")).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    let report: Report = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(report.rounds, 2);
+    assert_eq!(report.stories, 1);
+    assert_eq!(report.first_round_consensus_rate, Some(0.0));
+    assert_eq!(report.average_revotes, Some(1.0));
+    assert_eq!(report.estimates.len(), 1);
+    assert_eq!(report.estimates[0].estimate, Card::new("5"));
+    assert_eq!(report.estimates[0].rounds, 2);
+    // History is stamped by the hub's clock.
+    assert_eq!(report.estimates[0].estimated_at, START);
+
+    let missing = server.get("/rooms/nope/report").await;
+    assert_eq!(missing.status, 404);
+}
+
+#[tokio::test]
+async fn closed_rooms_keep_their_report_and_export() {
+    let ttl = Duration::from_secs(60 * 60);
+    let server = TestServer::start_with(Config {
+        idle_room_ttl: ttl,
+        ..Config::default()
+    })
+    .await;
+    let mut alice = server.connect().await;
+    alice
+        .send(create_room_with("Sprint").passphrase("swordfish"))
+        .await;
+    let ServerMessage::RoomCreated { room, .. } = alice.recv().await else {
+        panic!("expected room_created");
+    };
+    alice
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: "Alice".into(),
+            role: Role::Voter,
+            passphrase: Some("swordfish".into()),
+            invite: None,
+        })
+        .await;
+    alice.welcome().await;
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec!["Login".into()],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    alice.send(vote("5")).await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("5"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+        .await;
+    alice.send(ClientMessage::Leave).await;
+    alice.recv_matching(|m| *m == ServerMessage::Left).await;
+
+    let now = Instant::now();
+    server.hub.sweep(now);
+    server.hub.sweep(now + ttl);
+    assert_eq!(server.hub.room_count(), 0);
+
+    // The backlog outlives the room, and so does its passphrase.
+    let path = format!("/rooms/{room}/report");
+    assert_eq!(server.get(&path).await.status, 403);
+    let passphrase = [("X-Room-Passphrase", "swordfish")];
+    let response = server.get_with(&path, &passphrase).await;
+    assert_eq!(response.status, 200);
+    let report: Report = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(report.rounds, 1);
+    assert_eq!(report.estimates.len(), 1);
+    assert_eq!(report.estimates[0].title, "Login");
+    assert_eq!(report.estimates[0].estimate, Card::new("5"));
+
+    let export = format!("/rooms/{room}/export?format=csv");
+    assert_eq!(server.get(&export).await.status, 403);
+    let download = server.get_with(&export, &passphrase).await;
+    assert_eq!(download.status, 200);
+    assert!(download.body.contains("Login"), "{}", download.body);
+}
+
+#[tokio::test]
+async fn team_reports_cover_every_room_of_the_team() {
+    let server = TestServer::start().await;
+    let saved = server
+        .hub
+        .save_template(
+            Template {
+                name: "Web".into(),
+                deck: Deck::fibonacci(),
+                settings: Settings::default(),
+                moderator: None,
+                members: Vec::new(),
+            },
+            None,
+        )
+        .unwrap();
+    let Some(TemplateOwner::Token(token)) = saved.owner else {
+        panic!("expected a template token");
+    };
+    let owner = [("X-Template-Token", token.as_str())];
+    let mut sprints = Vec::new();
+    for name in ["Sprint 1", "Sprint 2"] {
+        let (room, _) = server
+            .hub
+            .create_room_from_template(name, "Web", None)
+            .await
+            .unwrap();
+        let mut alice = server.connect().await;
+        alice.join(&room, "Alice", Role::Voter).await;
+        alice.send(vote("5")).await;
+        alice.send(ClientMessage::Reveal).await;
+        alice
+            .recv_matching(|m| matches!(m, ServerMessage::Revealed { .. }))
+            .await;
+        sprints.push(room);
+    }
+    // Rooms outside the team stay out of its report.
+    let other = server.create_room(Deck::fibonacci()).await;
+
+    // Only the template's owner gets to see how the team estimates.
+    assert_eq!(server.get("/teams/Web/report").await.status, 403);
+    let response = server.get_with("/teams/Web/report", &owner).await;
+    assert_eq!(response.status, 200);
+    let report: Report = serde_json::from_str(&response.body).unwrap();
+    sprints.sort();
+    assert_eq!(
+        report.scope,
+        Scope::Team {
+            team: "Web".into(),
+            rooms: sprints,
+        }
+    );
+    assert_eq!(report.rounds, 2);
+    assert_eq!(report.stories, 2);
+    assert!(!response.body.contains(other.as_str()));
+
+    assert_eq!(
+        server.get_with("/teams/Apps/report", &owner).await.status,
+        403
+    );
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use common::{create_room_with, TestServer};
+use poker_core::{Deck, Member, Role, Settings, Template};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, PROTOCOL_VERSION};
+
+fn team() -> Template {
+    Template {
+        name: " Team Rocket ".into(),
+        deck: Deck::t_shirt(),
+        settings: Settings {
+            anonymous: true,
+            timer_seconds: Some(120),
+            ..Settings::default()
+        },
+        moderator: Some("Carol".into()),
+        members: vec![
+            Member {
+                name: "Alice".into(),
+                role: Role::Voter,
+            },
+            Member {
+                name: "Pat".into(),
+                role: Role::Observer,
+            },
+            Member {
+                name: "Carol".into(),
+                role: Role::Voter,
+            },
+        ],
+    }
+}
+
+#[tokio::test]
+async fn rooms_start_from_a_saved_template() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await;
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: team(),
+            token: None,
+        })
+        .await;
+    let ServerMessage::TemplateSaved { template, .. } = alice.recv().await else {
+        panic!("expected template_saved");
+    };
+    assert_eq!(template.name, "Team Rocket");
+    alice.send(ClientMessage::ListTemplates).await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::Templates {
+            templates: vec![template.clone()]
+        }
+    );
+
+    alice
+        .send(create_room_with("").template("Team Rocket"))
+        .await;
+    let ServerMessage::RoomCreated { room, invites } = alice.recv().await else {
+        panic!("expected room_created");
+    };
+    let names: Vec<_> = invites.iter().map(|invite| invite.name.as_str()).collect();
+    assert_eq!(names, ["Alice", "Pat", "Carol"]);
+    let invite = |name: &str| {
+        let invite = invites.iter().find(|invite| invite.name == name);
+        invite.unwrap().token.clone()
+    };
+    let joined = alice.join_invited(&room, &invite("Alice")).await;
+    assert_eq!(joined.room.name, "Team Rocket");
+    assert_eq!(joined.room.deck, Deck::t_shirt());
+    assert_eq!(joined.room.settings, template.settings);
+    assert_eq!(joined.room.moderator.as_deref(), Some("Carol"));
+    assert_eq!(joined.room.members, template.members);
+
+    let mut pat = server.connect().await;
+    let pat_seat = pat.join_invited(&room, &invite("Pat")).await;
+    let me = pat_seat
+        .room
+        .participants
+        .iter()
+        .find(|p| p.id == pat_seat.you);
+    assert_eq!(me.unwrap().name, "Pat");
+    assert_eq!(me.unwrap().role, Role::Observer);
+
+    // Passing the invite around does not give Pat a second seat, or vote.
+    let mut again = server.connect().await;
+    again
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: String::new(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: Some(invite("Pat")),
+        })
+        .await;
+    again.expect_error(ErrorCode::AlreadyJoined).await;
+    let seats = server
+        .hub
+        .room(&room)
+        .unwrap()
+        .snapshot()
+        .participants
+        .len();
+    assert_eq!(seats, 2);
+
+    // Calling yourself Carol does not make you the moderator.
+    let mut mallory = server.connect().await;
+    let impostor = mallory.join(&room, "Carol", Role::Voter).await;
+    assert_ne!(impostor.room.facilitator, Some(impostor.you));
+    let mut guesser = server.connect().await;
+    guesser
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: room.clone(),
+            name: String::new(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: Some("guess".into()),
+        })
+        .await;
+    guesser.expect_error(ErrorCode::InvalidInvite).await;
+
+    let mut carol = server.connect().await;
+    let carol_seat = carol.join_invited(&room, &invite("Carol")).await;
+    alice
+        .recv_matching(|msg| {
+            *msg == ServerMessage::FacilitatorChanged {
+                participant: carol_seat.you,
+            }
+        })
+        .await;
+
+    carol
+        .send(ClientMessage::StartTimer { seconds: None })
+        .await;
+    let ServerMessage::TimerStarted {
+        started_at,
+        deadline,
+    } = carol.recv().await
+    else {
+        panic!("expected timer_started");
+    };
+    assert_eq!(deadline.0 - started_at.0, 120_000);
+}
+
+#[tokio::test]
+async fn templates_are_checked_and_can_be_deleted() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await;
+
+    let mut unnamed = team();
+    unnamed.name = " ".into();
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: unnamed,
+            token: None,
+        })
+        .await;
+    alice.expect_error(ErrorCode::InvalidTemplate).await;
+    let mut twice = team();
+    twice.members[1].name = "alice".into();
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: twice,
+            token: None,
+        })
+        .await;
+    alice.expect_error(ErrorCode::InvalidTemplate).await;
+    alice
+        .send(create_room_with("").template("Team Rocket"))
+        .await;
+    alice.expect_error(ErrorCode::UnknownTemplate).await;
+
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: team(),
+            token: None,
+        })
+        .await;
+    let ServerMessage::TemplateSaved { token, .. } = alice.recv().await else {
+        panic!("expected template_saved");
+    };
+    alice
+        .send(ClientMessage::DeleteTemplate {
+            name: "Team Rocket".into(),
+            token,
+        })
+        .await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::TemplateDeleted {
+            name: "Team Rocket".into()
+        }
+    );
+    alice
+        .send(ClientMessage::DeleteTemplate {
+            name: "Team Rocket".into(),
+            token: None,
+        })
+        .await;
+    alice.expect_error(ErrorCode::UnknownTemplate).await;
+    alice.send(ClientMessage::ListTemplates).await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::Templates {
+            templates: Vec::new()
+        }
+    );
+}
+
+#[tokio::test]
+async fn only_whoever_saved_a_template_can_change_it() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect().await;
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: team(),
+            token: None,
+        })
+        .await;
+    let ServerMessage::TemplateSaved {
+        token: Some(token), ..
+    } = alice.recv().await
+    else {
+        panic!("expected template_saved with a token");
+    };
+
+    let mut mallory = server.connect().await;
+    for claim in [None, Some("guess".to_owned())] {
+        let mut takeover = team();
+        takeover.moderator = Some("Mallory".into());
+        mallory
+            .send(ClientMessage::SaveTemplate {
+                template: takeover,
+                token: claim.clone(),
+            })
+            .await;
+        mallory.expect_error(ErrorCode::NotTemplateOwner).await;
+        mallory
+            .send(ClientMessage::DeleteTemplate {
+                name: "Team Rocket".into(),
+                token: claim,
+            })
+            .await;
+        mallory.expect_error(ErrorCode::NotTemplateOwner).await;
+    }
+
+    let mut renamed = team();
+    renamed.moderator = None;
+    alice
+        .send(ClientMessage::SaveTemplate {
+            template: renamed,
+            token: Some(token.clone()),
+        })
+        .await;
+    let ServerMessage::TemplateSaved {
+        template,
+        token: kept,
+    } = alice.recv().await
+    else {
+        panic!("expected template_saved");
+    };
+    assert_eq!(template.moderator, None);
+    assert_eq!(kept, Some(token));
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use poker_core::{Card, Deck, ManualClock, Phase, Role, Settings, TimerExpiry, Timestamp};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+use poker_server::{Config, Hub};
+
+const START: Timestamp = Timestamp(1_700_000_000_000);
+
+async fn start() -> (TestServer, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new(START));
+    let hub = Hub::new(Config::default()).with_clock(clock.clone());
+    (TestServer::start_hub(hub).await, clock)
+}
+
+#[tokio::test]
+async fn timer_is_broadcast_and_expires_on_the_servers_clock() {
+    let (server, clock) = start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::StartTimer { seconds: Some(60) })
+        .await;
+    let started = ServerMessage::TimerStarted {
+        started_at: START,
+        deadline: Timestamp(START.0 + 60_000),
+    };
+    assert_eq!(alice.recv().await, started);
+    assert_eq!(bob.recv().await, started);
+
+    let mut carol = server.connect().await;
+    let joined = carol.join(&room, "Carol", Role::Voter).await;
+    assert_eq!(
+        joined.room.timer.map(|t| t.deadline),
+        Some(Timestamp(START.0 + 60_000))
+    );
+    alice.recv().await;
+    bob.recv().await;
+
+    clock.advance(Duration::from_secs(59));
+    server.hub.sweep(Instant::now());
+    bob.expect_silence().await;
+
+    clock.advance(Duration::from_secs(1));
+    server.hub.sweep(Instant::now());
+    for client in [&mut alice, &mut bob, &mut carol] {
+        assert_eq!(client.recv().await, ServerMessage::TimerExpired);
+    }
+    assert_eq!(server.hub.room(&room).unwrap().snapshot().timer, None);
+    assert_eq!(
+        server.hub.room(&room).unwrap().snapshot().phase,
+        Phase::Voting
+    );
+}
+
+#[tokio::test]
+async fn expiry_reveals_when_configured() {
+    let (server, clock) = start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::UpdateSettings {
+            settings: Settings {
+                on_timer_expiry: TimerExpiry::Reveal,
+                ..Settings::default()
+            },
+        })
+        .await;
+    alice
+        .send(ClientMessage::StartTimer { seconds: Some(30) })
+        .await;
+    bob.recv_matching(|m| matches!(m, ServerMessage::TimerStarted { .. }))
+        .await;
+    bob.send(ClientMessage::Vote {
+        card: Card::new("8"),
+    })
+    .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::Voted { .. }))
+        .await;
+
+    clock.advance(Duration::from_secs(30));
+    server.hub.sweep(Instant::now());
+    alice
+        .recv_matching(|m| *m == ServerMessage::TimerExpired)
+        .await;
+    let ServerMessage::Revealed { votes, .. } = alice.recv().await else {
+        panic!("expected the votes to be revealed");
+    };
+    assert_eq!(votes.len(), 1);
+    assert_eq!(votes[0].card, Card::new("8"));
+}
+
+#[tokio::test]
+async fn only_the_facilitator_runs_the_timer() {
+    let (server, _clock) = start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    bob.send(ClientMessage::StartTimer { seconds: Some(30) })
+        .await;
+    assert!(matches!(
+        bob.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotFacilitator,
+            ..
+        }
+    ));
+    alice
+        .send(ClientMessage::StartTimer { seconds: Some(0) })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidTimer,
+            ..
+        }
+    ));
+
+    alice
+        .send(ClientMessage::StartTimer { seconds: Some(30) })
+        .await;
+    alice.recv().await;
+    bob.recv().await;
+    alice.send(ClientMessage::StopTimer).await;
+    assert_eq!(alice.recv().await, ServerMessage::TimerStopped);
+    assert_eq!(bob.recv().await, ServerMessage::TimerStopped);
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use common::TestServer;
+use poker_core::{Card, Deck, NewStory, Role};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+use poker_server::Hub;
+use poker_tracker::{JiraConfig, JiraTracker};
+use serde_json::{json, Value};
+
+type Updates = Arc<Mutex<Vec<(String, Value)>>>;
+
+/// A Jira stand-in with two issues; updating any other issue fails.
+async fn mock_jira() -> (SocketAddr, Updates) {
+    let updates = Updates::default();
+    let app = Router::new()
+        .route(
+            "/rest/api/2/search",
+            get(|| async {
+                Json(json!({
+                    "total": 2,
+                    "issues": [
+                        {"key": "WEB-1", "fields": {"summary": "Login"}},
+                        {"key": "WEB-2", "fields": {"summary": "Search"}}
+                    ]
+                }))
+            }),
+        )
+        .route(
+            "/rest/api/2/issue/{key}",
+            put(
+                |State(updates): State<Updates>,
+                 Path(key): Path<String>,
+                 Json(body): Json<Value>| async move {
+                    if key != "WEB-1" && key != "WEB-2" {
+                        return StatusCode::NOT_FOUND;
+                    }
+                    updates.lock().unwrap().push((key, body));
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .with_state(updates.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr, updates)
+}
+
+async fn server_with_jira(addr: SocketAddr) -> TestServer {
+    let jira = JiraTracker::new(JiraConfig::new(format!("http://{addr}"), "story_points")).unwrap();
+    TestServer::start_hub(Hub::default().with_tracker(Arc::new(jira))).await
+}
+
+#[tokio::test]
+async fn stories_come_from_the_tracker_and_estimates_go_back() {
+    let (addr, updates) = mock_jira().await;
+    let server = server_with_jira(addr).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+
+    alice
+        .send(ClientMessage::FetchStories {
+            query: "project = WEB".into(),
+        })
+        .await;
+    let ServerMessage::StoriesAdded { stories } = alice.recv().await else {
+        panic!("expected stories_added");
+    };
+    let keys: Vec<_> = stories.iter().map(|s| s.key.as_deref()).collect();
+    assert_eq!(keys, [Some("WEB-1"), Some("WEB-2")]);
+
+    alice.send(ClientMessage::NextStory).await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("3"),
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("3"),
+        })
+        .await;
+    alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateRecorded { .. }))
+        .await;
+
+    // The write-back happens in the background.
+    for _ in 0..100 {
+        if !updates.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        *updates.lock().unwrap(),
+        [("WEB-1".into(), json!({"fields": {"story_points": 3.0}}))]
+    );
+}
+
+#[tokio::test]
+async fn failed_write_back_is_reported_to_the_facilitator() {
+    let (addr, _updates) = mock_jira().await;
+    let server = server_with_jira(addr).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec![NewStory::new("Gone").with_key("WEB-404")],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("8"),
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("8"),
+        })
+        .await;
+    let failed = alice
+        .recv_matching(|m| matches!(m, ServerMessage::EstimateSyncFailed { .. }))
+        .await;
+    let ServerMessage::EstimateSyncFailed { message, .. } = failed else {
+        unreachable!();
+    };
+    assert!(message.contains("404"), "{message}");
+}
+
+#[tokio::test]
+async fn fetching_needs_a_tracker_and_the_facilitator() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::FetchStories {
+            query: "project = WEB".into(),
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::TrackerUnavailable,
+            ..
+        }
+    ));
+
+    let (addr, _updates) = mock_jira().await;
+    let server = server_with_jira(addr).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    bob.send(ClientMessage::FetchStories {
+        query: "project = WEB".into(),
+    })
+    .await;
+    assert!(matches!(
+        bob.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotFacilitator,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use common::TestServer;
+use poker_core::{Card, Deck, ManualClock, Presence, Role, Timestamp, VotingClosed};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+use poker_server::{Config, Hub};
+use poker_webhook::{Notification, NotificationEvent, Webhook};
+use tokio::sync::mpsc;
+
+const START: Timestamp = Timestamp(1_700_000_000_000);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const GRACE: Duration = Duration::from_secs(60);
+
+/// Hands every notification to the test.
+struct Recorder(mpsc::UnboundedSender<Notification>);
+
+#[async_trait]
+impl Webhook for Recorder {
+    async fn send(&self, notification: &Notification) -> poker_webhook::Result<()> {
+        let _ = self.0.send(notification.clone());
+        Ok(())
+    }
+}
+
+async fn start() -> (
+    TestServer,
+    Arc<ManualClock>,
+    mpsc::UnboundedReceiver<Notification>,
+) {
+    let clock = Arc::new(ManualClock::new(START));
+    let (tx, notifications) = mpsc::unbounded_channel();
+    let hub = Hub::new(Config {
+        grace_period: GRACE,
+        ..Config::default()
+    })
+    .with_clock(clock.clone())
+    .with_webhook(Arc::new(Recorder(tx)));
+    (TestServer::start_hub(hub).await, clock, notifications)
+}
+
+#[tokio::test]
+async fn open_round_holds_seats_and_reveals_at_the_deadline() {
+    let (server, clock, mut notifications) = start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    let bob_seat = bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    alice
+        .send(ClientMessage::OpenVoting {
+            seconds: DAY.as_secs(),
+            quorum: None,
+        })
+        .await;
+    let opened = ServerMessage::VotingOpened {
+        opened_at: START,
+        deadline: Timestamp(START.0 + 86_400_000),
+        quorum: None,
+    };
+    assert_eq!(alice.recv().await, opened);
+    assert_eq!(bob.recv().await, opened);
+    assert!(matches!(
+        notifications.recv().await.unwrap().event,
+        NotificationEvent::VotingOpened { quorum: None, .. }
+    ));
+
+    bob.send(ClientMessage::Vote {
+        card: Card::new("8"),
+    })
+    .await;
+    bob.recv().await;
+    bob.close().await;
+    alice.recv().await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::PresenceChanged {
+            participant: bob_seat.you,
+            presence: Presence::Gone,
+        }
+    );
+
+    // Long past the grace period, Bob's seat and vote are still there.
+    server.hub.sweep(Instant::now() + GRACE * 10);
+    let mut bob = server.connect().await;
+    let resumed = bob.resume(&room, &bob_seat.token).await;
+    assert_eq!(resumed.your_vote, Some(Card::new("8")));
+    assert!(resumed.room.voting.is_some());
+    bob.close().await;
+
+    clock.advance(DAY);
+    server.hub.sweep(Instant::now());
+    let closed = alice
+        .recv_matching(|msg| matches!(msg, ServerMessage::VotingClosed { .. }))
+        .await;
+    assert_eq!(
+        closed,
+        ServerMessage::VotingClosed {
+            reason: VotingClosed::Deadline
+        }
+    );
+    assert!(matches!(alice.recv().await, ServerMessage::Revealed { .. }));
+    let NotificationEvent::VotingClosed {
+        reason, summary, ..
+    } = notifications.recv().await.unwrap().event
+    else {
+        panic!("expected the window to close");
+    };
+    assert_eq!(reason, VotingClosed::Deadline);
+    assert_eq!(summary.unwrap().distribution.len(), 1);
+}
+
+#[tokio::test]
+async fn open_round_reveals_early_on_quorum() {
+    let (server, _clock, mut notifications) = start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&room, "Bob", Role::Voter).await;
+    let mut carol = server.connect().await;
+    carol.join(&room, "Carol", Role::Voter).await;
+
+    alice
+        .send(ClientMessage::OpenVoting {
+            seconds: DAY.as_secs(),
+            quorum: Some(0),
+        })
+        .await;
+    assert!(matches!(
+        alice
+            .recv_matching(|msg| matches!(msg, ServerMessage::Error { .. }))
+            .await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidQuorum,
+            ..
+        }
+    ));
+    alice
+        .send(ClientMessage::OpenVoting {
+            seconds: DAY.as_secs(),
+            quorum: Some(2),
+        })
+        .await;
+    for (client, card) in [(&mut alice, "3"), (&mut bob, "5")] {
+        client
+            .send(ClientMessage::Vote {
+                card: Card::new(card),
+            })
+            .await;
+    }
+    let revealed = carol
+        .recv_matching(|msg| matches!(msg, ServerMessage::VotingClosed { .. }))
+        .await;
+    assert_eq!(
+        revealed,
+        ServerMessage::VotingClosed {
+            reason: VotingClosed::Quorum
+        }
+    );
+    assert!(matches!(carol.recv().await, ServerMessage::Revealed { .. }));
+
+    notifications.recv().await.unwrap();
+    let closed = notifications.recv().await.unwrap();
+    assert_eq!(closed.room, room);
+    assert!(matches!(
+        closed.event,
+        NotificationEvent::VotingClosed {
+            reason: VotingClosed::Quorum,
+            summary: Some(_),
+            ..
+        }
+    ));
+}
@@ -0,0 +1,296 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use common::TestServer;
+use poker_core::{Card, Deck, NewStory, Role};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage};
+use poker_server::{Config, Hub};
+use poker_webhook::{EventKind, Notification, NotificationEvent, RetryPolicy, SIGNATURE_HEADER};
+use tokio::sync::mpsc;
+
+/// A payload as it arrived, with its signature header.
+type Received = (Option<String>, Bytes);
+
+/// Takes posts to `/hook`, slowly for revealed rounds, and fails every post
+/// to `/broken`.
+async fn receiver() -> (SocketAddr, mpsc::UnboundedReceiver<Received>) {
+    let (tx, rx) = mpsc::unbounded_channel::<Received>();
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(tx): State<mpsc::UnboundedSender<Received>>,
+                 headers: HeaderMap,
+                 body: Bytes| async move {
+                    let signature = headers
+                        .get(SIGNATURE_HEADER)
+                        .map(|value| value.to_str().unwrap().to_owned());
+                    if body.windows(14).any(|w| w == b"round_revealed") {
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                    }
+                    let _ = tx.send((signature, body));
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .route(
+            "/broken",
+            post(|| async { (StatusCode::SERVICE_UNAVAILABLE, "try later") }),
+        )
+        .with_state(tx);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr, rx)
+}
+
+/// A server whose room webhooks may reach the test receivers.
+fn local_webhooks() -> Config {
+    Config {
+        webhook_allowlist: vec!["127.0.0.1".into()],
+        ..Config::default()
+    }
+}
+
+/// The next payload, after checking it was signed with `secret`.
+async fn next_signed(
+    received: &mut mpsc::UnboundedReceiver<Received>,
+    secret: &str,
+) -> Notification {
+    let (signature, body) = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("timed out waiting for a notification")
+        .unwrap();
+    assert!(poker_webhook::verify(secret, &body, &signature.unwrap()));
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn facilitators_subscribe_their_room_to_signed_notifications() {
+    let (addr, mut received) = receiver().await;
+    let server = TestServer::start_with(local_webhooks()).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    let bob_seat = bob.join(&room, "Bob", Role::Voter).await;
+    alice.recv().await;
+
+    let subscribe = ClientMessage::AddWebhook {
+        url: format!("http://{addr}/hook"),
+        events: vec![
+            EventKind::RoundRevealed,
+            EventKind::EstimateFinalized,
+            EventKind::SessionEnded,
+        ],
+    };
+    bob.send(subscribe.clone()).await;
+    assert!(matches!(
+        bob.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotFacilitator,
+            ..
+        }
+    ));
+    alice.send(subscribe).await;
+    let ServerMessage::WebhookAdded { webhook, secret } = alice.recv().await else {
+        panic!("expected webhook_added");
+    };
+    assert_eq!(webhook.events.len(), 3);
+    bob.expect_silence().await;
+
+    alice
+        .send(ClientMessage::AddStories {
+            stories: vec![NewStory {
+                title: "Login".into(),
+                key: Some("WEB-1".into()),
+            }],
+        })
+        .await;
+    alice.send(ClientMessage::NextStory).await;
+    // A vote that arrives before the round starts would be cleared by it.
+    bob.recv_matching(|msg| matches!(msg, ServerMessage::RoundStarted { .. }))
+        .await;
+    for (client, card) in [(&mut alice, "3"), (&mut bob, "5")] {
+        client
+            .send(ClientMessage::Vote {
+                card: Card::new(card),
+            })
+            .await;
+    }
+    alice
+        .recv_matching(|msg| {
+            *msg == ServerMessage::Voted {
+                participant: bob_seat.you,
+            }
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    alice
+        .send(ClientMessage::RecordEstimate {
+            estimate: Card::new("5"),
+        })
+        .await;
+    // The estimate is delivered only once the slower reveal got through.
+    let revealed = next_signed(&mut received, &secret).await;
+    assert_eq!(revealed.room, room);
+    let NotificationEvent::RoundRevealed { story, votes, .. } = revealed.event else {
+        panic!("expected round_revealed, got {revealed:?}");
+    };
+    assert_eq!(story.as_deref(), Some("Login"));
+    assert_eq!(votes.len(), 2);
+    assert_eq!(
+        next_signed(&mut received, &secret).await.event,
+        NotificationEvent::EstimateFinalized {
+            story: "Login".into(),
+            key: Some("WEB-1".into()),
+            estimate: Card::new("5"),
+        }
+    );
+
+    bob.send(ClientMessage::Leave).await;
+    alice.send(ClientMessage::Leave).await;
+    assert_eq!(
+        next_signed(&mut received, &secret).await.event,
+        NotificationEvent::SessionEnded {
+            stories: 1,
+            estimated: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_shown_to_the_facilitator() {
+    let (addr, _received) = receiver().await;
+    let hub = Hub::new(local_webhooks()).with_webhook_retries(RetryPolicy {
+        attempts: 2,
+        backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(10),
+    });
+    let server = TestServer::start_hub(hub).await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+
+    alice
+        .send(ClientMessage::AddWebhook {
+            url: "mailto:team@example.com".into(),
+            events: Vec::new(),
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidWebhook,
+            ..
+        }
+    ));
+    alice
+        .send(ClientMessage::AddWebhook {
+            url: format!("http://{addr}/broken"),
+            events: Vec::new(),
+        })
+        .await;
+    let ServerMessage::WebhookAdded { webhook, .. } = alice.recv().await else {
+        panic!("expected webhook_added");
+    };
+
+    alice
+        .send(ClientMessage::Vote {
+            card: Card::new("8"),
+        })
+        .await;
+    alice.send(ClientMessage::Reveal).await;
+    let failed = alice
+        .recv_matching(|msg| matches!(msg, ServerMessage::WebhookFailed { .. }))
+        .await;
+    let ServerMessage::WebhookFailed {
+        webhook: failed,
+        failure,
+    } = failed
+    else {
+        unreachable!();
+    };
+    assert_eq!(failed, webhook.id);
+    assert_eq!(failure.event, EventKind::RoundRevealed);
+    assert_eq!(failure.attempts, 2);
+    assert!(failure.message.contains("503"));
+    assert!(!failure.message.contains("try later"));
+
+    alice.send(ClientMessage::ListWebhooks).await;
+    let ServerMessage::Webhooks { webhooks } = alice.recv().await else {
+        panic!("expected webhooks");
+    };
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].last_failure.as_ref(), Some(&failure));
+
+    alice
+        .send(ClientMessage::RemoveWebhook {
+            webhook: webhook.id,
+        })
+        .await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::WebhookRemoved {
+            webhook: webhook.id
+        }
+    );
+    alice
+        .send(ClientMessage::RemoveWebhook {
+            webhook: webhook.id,
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::UnknownWebhook,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn room_webhooks_stay_off_the_server_network() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+
+    for url in [
+        "http://169.254.169.254/latest/meta-data/",
+        "http://127.0.0.1:9090/metrics",
+        "http://localhost/hook",
+        "http://[::1]/hook",
+    ] {
+        alice
+            .send(ClientMessage::AddWebhook {
+                url: url.into(),
+                events: Vec::new(),
+            })
+            .await;
+        assert!(
+            matches!(
+                alice.recv().await,
+                ServerMessage::Error {
+                    code: ErrorCode::InvalidWebhook,
+                    ..
+                }
+            ),
+            "{url}"
+        );
+    }
+    alice.send(ClientMessage::ListWebhooks).await;
+    assert_eq!(
+        alice.recv().await,
+        ServerMessage::Webhooks {
+            webhooks: Vec::new()
+        }
+    );
+}
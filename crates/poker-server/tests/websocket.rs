@@ -0,0 +1,240 @@
+mod common;
+
+use common::TestServer;
+use poker_core::{Card, Deck, Phase, Role, RoomId};
+use poker_protocol::{ClientMessage, ErrorCode, ServerMessage, VoteView, PROTOCOL_VERSION};
+
+fn vote(label: &str) -> ClientMessage {
+    ClientMessage::Vote {
+        card: Card::new(label),
+    }
+}
+
+#[tokio::test]
+async fn create_and_join_room() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::t_shirt()).await;
+    let mut alice = server.connect().await;
+    let joined = alice.join(&room, "Alice", Role::Voter).await;
+    let snapshot = joined.room;
+    assert_eq!(snapshot.id, room);
+    assert_eq!(snapshot.deck, Deck::t_shirt());
+    assert_eq!(snapshot.cards, Deck::t_shirt().cards());
+    assert_eq!(snapshot.phase, Phase::Voting);
+    assert_eq!(snapshot.participants.len(), 1);
+    assert_eq!(snapshot.participants[0].id, joined.you);
+    assert_eq!(server.hub.room_count(), 1);
+}
+
+#[tokio::test]
+async fn others_see_join_vote_reveal_and_reset_live() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let alice_id = alice.join(&room, "Alice", Role::Voter).await.you;
+    let mut bob = server.connect().await;
+    let joined = bob.join(&room, "Bob", Role::Voter).await;
+    let bob_id = joined.you;
+    assert_eq!(joined.room.participants.len(), 2);
+
+    let ServerMessage::ParticipantJoined { participant } = alice.recv().await else {
+        panic!("alice should see bob join");
+    };
+    assert_eq!(participant.id, bob_id);
+    assert_eq!(participant.name, "Bob");
+
+    alice.send(vote("5")).await;
+    let hidden = ServerMessage::Voted {
+        participant: alice_id,
+    };
+    assert_eq!(alice.recv().await, hidden);
+    assert_eq!(bob.recv().await, hidden);
+
+    bob.send(vote("8")).await;
+    bob.recv().await;
+    alice.recv().await;
+
+    alice.send(ClientMessage::Reveal).await;
+    for client in [&mut alice, &mut bob] {
+        let ServerMessage::Revealed { votes, summary } = client.recv().await else {
+            panic!("expected reveal");
+        };
+        assert_eq!(
+            votes,
+            vec![
+                VoteView {
+                    participant: alice_id,
+                    card: Card::new("5")
+                },
+                VoteView {
+                    participant: bob_id,
+                    card: Card::new("8")
+                },
+            ]
+        );
+        assert_eq!(summary.numeric.unwrap().average, 6.5);
+    }
+
+    alice.send(ClientMessage::Reset).await;
+    assert_eq!(alice.recv().await, ServerMessage::RoundReset);
+    assert_eq!(bob.recv().await, ServerMessage::RoundReset);
+}
+
+#[tokio::test]
+async fn explicit_leave_frees_the_connection() {
+    let server = TestServer::start().await;
+    let first = server.create_room(Deck::fibonacci()).await;
+    let second = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    alice.join(&first, "Alice", Role::Voter).await;
+    alice
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: second.clone(),
+            name: "Alice".into(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::AlreadyJoined,
+            ..
+        }
+    ));
+
+    alice.send(ClientMessage::Leave).await;
+    assert_eq!(alice.recv().await, ServerMessage::Left);
+    alice.join(&second, "Alice", Role::Voter).await;
+}
+
+#[tokio::test]
+async fn rejected_actions_only_reach_the_sender() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut olga = server.connect().await;
+    olga.join(&room, "Olga", Role::Observer).await;
+    let mut alice = server.connect().await;
+    alice.join(&room, "Alice", Role::Voter).await;
+    olga.recv().await;
+
+    olga.send(vote("5")).await;
+    assert!(matches!(
+        olga.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::ObserverCannotVote,
+            ..
+        }
+    ));
+    alice.send(vote("7")).await;
+    assert!(matches!(
+        alice.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidCard,
+            ..
+        }
+    ));
+    olga.expect_silence().await;
+}
+
+#[tokio::test]
+async fn late_joiner_sees_hidden_votes_as_flags_only() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut alice = server.connect().await;
+    let alice_id = alice.join(&room, "Alice", Role::Voter).await.you;
+    alice.send(vote("13")).await;
+    alice.recv().await;
+
+    let mut bob = server.connect().await;
+    let snapshot = bob.join(&room, "Bob", Role::Voter).await.room;
+    let alice_view = snapshot
+        .participants
+        .iter()
+        .find(|p| p.id == alice_id)
+        .unwrap();
+    assert!(alice_view.voted);
+    assert_eq!(snapshot.votes, None);
+    assert_eq!(snapshot.summary, None);
+}
+
+#[tokio::test]
+async fn unsupported_version_is_refused() {
+    let server = TestServer::start().await;
+    let room = server.create_room(Deck::fibonacci()).await;
+    let mut client = server.connect().await;
+    client
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION + 1,
+            room,
+            name: "Future".into(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::UnsupportedVersion,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn malformed_and_premature_messages_are_reported() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    client.send_raw("{\"type\":\"dance\"}").await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::InvalidMessage,
+            ..
+        }
+    ));
+    client.send(ClientMessage::Reveal).await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::NotJoined,
+            ..
+        }
+    ));
+    client
+        .send(ClientMessage::Join {
+            version: PROTOCOL_VERSION,
+            room: RoomId::new("nope"),
+            name: "Alice".into(),
+            role: Role::Voter,
+            passphrase: None,
+            invite: None,
+        })
+        .await;
+    assert!(matches!(
+        client.recv().await,
+        ServerMessage::Error {
+            code: ErrorCode::RoomNotFound,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn rooms_are_independent() {
+    let server = TestServer::start().await;
+    let first = server.create_room(Deck::fibonacci()).await;
+    let second = server.create_room(Deck::fibonacci()).await;
+    assert_ne!(first, second);
+    let mut alice = server.connect().await;
+    alice.join(&first, "Alice", Role::Voter).await;
+    let mut bob = server.connect().await;
+    bob.join(&second, "Bob", Role::Voter).await;
+
+    bob.send(vote("3")).await;
+    bob.recv().await;
+    alice.expect_silence().await;
+}
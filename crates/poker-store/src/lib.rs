@@ -0,0 +1,269 @@
+//! Persistence for OpenPlanningPoker.
+//!
+//! The server talks to storage only through the [`Store`] trait, which keeps
+//! rooms, the history of their rounds and the teams' room templates. Two
+//! implementations ship with the crate: [`MemoryStore`], the default, which
+//! forgets everything on restart, and [`SqliteStore`] (behind the `sqlite`
+//! feature) for self-hosted instances.
+
+mod memory;
+mod record;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use poker_core::{RoomId, Template};
+
+pub use memory::MemoryStore;
+pub use record::{RoomRecord, RoundRecord, TemplateOwner, TemplateRecord};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("stored data is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("database schema version {found} is newer than this build supports ({supported})")]
+    SchemaTooNew { found: u32, supported: u32 },
+}
+
+pub type Result<T, E = StoreError> = std::result::Result<T, E>;
+
+/// Durable home for rooms and the history of their rounds.
+///
+/// Calls are synchronous and expected to be quick; the server makes them
+/// while holding a room's lock so that stored state never runs ahead of what
+/// clients were told.
+pub trait Store: Send + Sync {
+    /// Inserts or replaces the current state of a room.
+    fn save_room(&self, record: &RoomRecord) -> Result<()>;
+
+    fn delete_room(&self, id: &RoomId) -> Result<()>;
+
+    /// Replaces the stored state of a room that was closed with its final
+    /// state, kept to report on but no longer restored.
+    fn archive_room(&self, record: &RoomRecord) -> Result<()>;
+
+    /// One closed room.
+    fn archived_room(&self, id: &RoomId) -> Result<Option<RoomRecord>>;
+
+    /// The closed rooms of a team.
+    fn archived_team_rooms(&self, team: &str) -> Result<Vec<RoomRecord>>;
+
+    /// Every stored room, e.g. to restore them on startup.
+    fn load_rooms(&self) -> Result<Vec<RoomRecord>>;
+
+    /// One stored room, e.g. to take it over from an instance that is gone.
+    fn load_room(&self, id: &RoomId) -> Result<Option<RoomRecord>>;
+
+    /// Appends a revealed round to the history. History outlives the room.
+    fn record_round(&self, round: &RoundRecord) -> Result<()>;
+
+    /// The revealed rounds of a room, oldest first.
+    fn rounds(&self, room: &RoomId) -> Result<Vec<RoundRecord>>;
+
+    /// The revealed rounds of every room of a team, oldest first.
+    fn team_rounds(&self, team: &str) -> Result<Vec<RoundRecord>>;
+
+    /// Inserts or replaces the template with the same name.
+    fn save_template(&self, record: &TemplateRecord) -> Result<()>;
+
+    fn template(&self, name: &str) -> Result<Option<TemplateRecord>>;
+
+    /// Every saved template, by name.
+    fn templates(&self) -> Result<Vec<Template>>;
+
+    /// Returns whether there was a template by that name.
+    fn delete_template(&self, name: &str) -> Result<bool>;
+}
+
+#[cfg(test)]
+pub(crate) mod contract {
+    //! Behaviour every [`Store`] implementation must share.
+
+    use std::collections::BTreeMap;
+
+    use poker_core::{
+        Card, Deck, Member, RecordedVote, Role, Room, RoomId, Settings, StoryId, Summary, Timestamp,
+    };
+    use poker_webhook::{EventKind, Subscription, WebhookId};
+
+    use super::*;
+
+    fn room(id: &str) -> RoomRecord {
+        let mut room = Room::new(RoomId::new(id), "Sprint", Deck::t_shirt());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        room.vote(alice, Card::new("M"), Timestamp(0)).unwrap();
+        room.drain_events();
+        RoomRecord {
+            room,
+            tokens: BTreeMap::from([(alice, "secret".to_owned())]),
+            passphrase: Some("$argon2id$hash".to_owned()),
+            webhooks: vec![Subscription {
+                id: WebhookId(1),
+                url: "https://chat.example.com/hook".into(),
+                secret: "signing-key".into(),
+                events: vec![EventKind::RoundRevealed],
+                last_failure: None,
+            }],
+            invites: BTreeMap::from([("invite".to_owned(), "Bob".to_owned())]),
+        }
+    }
+
+    fn round(room: &str, story: &str, at: u64) -> RoundRecord {
+        let card = Card::new("5");
+        RoundRecord {
+            room: RoomId::new(room),
+            team: (room != "c").then(|| "Web".to_owned()),
+            story: Some(story.to_owned()),
+            story_id: Some(StoryId(at)),
+            revealed_at: Timestamp(at),
+            votes: vec![RecordedVote {
+                participant: poker_core::ParticipantId(1),
+                name: "Alice".into(),
+                card: card.clone(),
+            }],
+            summary: Summary::new(&Deck::fibonacci(), [&card]),
+        }
+    }
+
+    pub fn rooms_round_trip(store: &dyn Store) {
+        assert!(store.load_rooms().unwrap().is_empty());
+        store.save_room(&room("a")).unwrap();
+        store.save_room(&room("b")).unwrap();
+        let mut updated = room("a");
+        updated.tokens.clear();
+        updated.passphrase = None;
+        updated.webhooks.clear();
+        store.save_room(&updated).unwrap();
+
+        let mut rooms = store.load_rooms().unwrap();
+        rooms.sort_by(|x, y| x.room.id().cmp(y.room.id()));
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].room.id(), &RoomId::new("a"));
+        assert!(rooms[0].tokens.is_empty());
+        assert_eq!(rooms[0].passphrase, None);
+        assert!(rooms[0].webhooks.is_empty());
+        assert_eq!(rooms[1].webhooks[0].secret, "signing-key");
+        assert_eq!(rooms[1].passphrase.as_deref(), Some("$argon2id$hash"));
+        assert_eq!(rooms[1].tokens.values().next().unwrap(), "secret");
+        let b = store.load_room(&RoomId::new("b")).unwrap().unwrap();
+        assert_eq!(b.room.id(), &RoomId::new("b"));
+        assert_eq!(b.passphrase, rooms[1].passphrase);
+        assert!(store.load_room(&RoomId::new("missing")).unwrap().is_none());
+        let alice = rooms[1].room.participants().next().unwrap().id;
+        assert_eq!(rooms[1].room.round().vote_of(alice), Some(&Card::new("M")));
+        assert_eq!(rooms[1].room.deck(), &Deck::t_shirt());
+
+        store.delete_room(&RoomId::new("a")).unwrap();
+        store.delete_room(&RoomId::new("missing")).unwrap();
+        assert_eq!(store.load_rooms().unwrap().len(), 1);
+    }
+
+    pub fn rounds_are_appended_per_room(store: &dyn Store) {
+        store.record_round(&round("a", "second", 20)).unwrap();
+        store.record_round(&round("b", "other", 15)).unwrap();
+        store.record_round(&round("a", "first", 10)).unwrap();
+
+        let rounds = store.rounds(&RoomId::new("a")).unwrap();
+        let stories: Vec<_> = rounds.iter().map(|r| r.story.as_deref()).collect();
+        assert_eq!(stories, [Some("first"), Some("second")]);
+        assert_eq!(rounds[0], round("a", "first", 10));
+        assert!(store.rounds(&RoomId::new("c")).unwrap().is_empty());
+    }
+
+    pub fn rounds_are_found_per_team(store: &dyn Store) {
+        store.record_round(&round("a", "second", 20)).unwrap();
+        store.record_round(&round("b", "first", 15)).unwrap();
+        store.record_round(&round("c", "elsewhere", 10)).unwrap();
+
+        let rounds = store.team_rounds("Web").unwrap();
+        let rooms: Vec<_> = rounds.iter().map(|r| r.room.as_str()).collect();
+        assert_eq!(rooms, ["b", "a"]);
+        assert_eq!(rounds[1], round("a", "second", 20));
+        assert!(store.team_rounds("Apps").unwrap().is_empty());
+    }
+
+    fn template(name: &str) -> Template {
+        Template {
+            name: name.into(),
+            deck: Deck::t_shirt(),
+            settings: Settings {
+                anonymous: true,
+                timer_seconds: Some(120),
+                ..Settings::default()
+            },
+            moderator: Some("Alice".into()),
+            members: vec![Member {
+                name: "Bob".into(),
+                role: Role::Observer,
+            }],
+        }
+    }
+
+    pub fn templates_round_trip(store: &dyn Store) {
+        let owned = |template, owner: &str| TemplateRecord {
+            template,
+            owner: Some(TemplateOwner::Subject(owner.into())),
+        };
+        assert!(store.templates().unwrap().is_empty());
+        store
+            .save_template(&owned(template("Web"), "alice"))
+            .unwrap();
+        let apps = TemplateRecord {
+            template: template("Apps"),
+            owner: None,
+        };
+        store.save_template(&apps).unwrap();
+        let mut updated = template("Web");
+        updated.members.clear();
+        let updated = TemplateRecord {
+            template: updated,
+            owner: Some(TemplateOwner::Token("secret".into())),
+        };
+        store.save_template(&updated).unwrap();
+
+        let names: Vec<_> = store
+            .templates()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["Apps", "Web"]);
+        assert_eq!(store.template("Web").unwrap(), Some(updated));
+        assert_eq!(store.template("Apps").unwrap(), Some(apps));
+        assert_eq!(store.template("web").unwrap(), None);
+
+        assert!(store.delete_template("Web").unwrap());
+        assert!(!store.delete_template("Web").unwrap());
+        assert_eq!(store.templates().unwrap().len(), 1);
+    }
+
+    pub fn history_outlives_room(store: &dyn Store) {
+        store.save_room(&room("a")).unwrap();
+        store.record_round(&round("a", "story", 1)).unwrap();
+        store.delete_room(&RoomId::new("a")).unwrap();
+        assert_eq!(store.rounds(&RoomId::new("a")).unwrap().len(), 1);
+    }
+
+    pub fn archived_rooms_are_not_restored(store: &dyn Store) {
+        let mut web = room("a");
+        web.room = Room::from_template(RoomId::new("a"), "Sprint", &template("Web"));
+        store.save_room(&web).unwrap();
+        store.save_room(&room("b")).unwrap();
+        store.archive_room(&web).unwrap();
+        store.archive_room(&room("b")).unwrap();
+
+        assert!(store.load_rooms().unwrap().is_empty());
+        assert!(store.load_room(&RoomId::new("a")).unwrap().is_none());
+        let archived = store.archived_room(&RoomId::new("a")).unwrap().unwrap();
+        assert_eq!(archived.passphrase, web.passphrase);
+        assert!(store.archived_room(&RoomId::new("c")).unwrap().is_none());
+        let team = store.archived_team_rooms("Web").unwrap();
+        assert_eq!(team.len(), 1);
+        assert_eq!(team[0].room.id(), &RoomId::new("a"));
+        assert!(store.archived_team_rooms("Apps").unwrap().is_empty());
+    }
+}
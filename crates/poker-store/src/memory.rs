@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use poker_core::{RoomId, Template};
+
+use crate::{Result, RoomRecord, RoundRecord, Store, TemplateRecord};
+
+/// Keeps everything in process memory. Used by default and in tests.
+#[derive(Default)]
+pub struct MemoryStore {
+    rooms: Mutex<HashMap<RoomId, RoomRecord>>,
+    archived: Mutex<HashMap<RoomId, RoomRecord>>,
+    rounds: Mutex<Vec<RoundRecord>>,
+    templates: Mutex<BTreeMap<String, TemplateRecord>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Store for MemoryStore {
+    fn save_room(&self, record: &RoomRecord) -> Result<()> {
+        self.rooms
+            .lock()
+            .unwrap()
+            .insert(record.room.id().clone(), record.clone());
+        Ok(())
+    }
+
+    fn delete_room(&self, id: &RoomId) -> Result<()> {
+        self.rooms.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn archive_room(&self, record: &RoomRecord) -> Result<()> {
+        let id = record.room.id().clone();
+        self.rooms.lock().unwrap().remove(&id);
+        self.archived.lock().unwrap().insert(id, record.clone());
+        Ok(())
+    }
+
+    fn archived_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        Ok(self.archived.lock().unwrap().get(id).cloned())
+    }
+
+    fn archived_team_rooms(&self, team: &str) -> Result<Vec<RoomRecord>> {
+        Ok(self
+            .archived
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| record.room.team() == Some(team))
+            .cloned()
+            .collect())
+    }
+
+    fn load_rooms(&self) -> Result<Vec<RoomRecord>> {
+        Ok(self.rooms.lock().unwrap().values().cloned().collect())
+    }
+
+    fn load_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        Ok(self.rooms.lock().unwrap().get(id).cloned())
+    }
+
+    fn record_round(&self, round: &RoundRecord) -> Result<()> {
+        self.rounds.lock().unwrap().push(round.clone());
+        Ok(())
+    }
+
+    fn rounds(&self, room: &RoomId) -> Result<Vec<RoundRecord>> {
+        let mut rounds: Vec<_> = self
+            .rounds
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| &r.room == room)
+            .cloned()
+            .collect();
+        rounds.sort_by_key(|r| r.revealed_at);
+        Ok(rounds)
+    }
+
+    fn team_rounds(&self, team: &str) -> Result<Vec<RoundRecord>> {
+        let mut rounds: Vec<_> = self
+            .rounds
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.team.as_deref() == Some(team))
+            .cloned()
+            .collect();
+        rounds.sort_by_key(|r| r.revealed_at);
+        Ok(rounds)
+    }
+
+    fn save_template(&self, record: &TemplateRecord) -> Result<()> {
+        self.templates
+            .lock()
+            .unwrap()
+            .insert(record.template.name.clone(), record.clone());
+        Ok(())
+    }
+
+    fn template(&self, name: &str) -> Result<Option<TemplateRecord>> {
+        Ok(self.templates.lock().unwrap().get(name).cloned())
+    }
+
+    fn templates(&self) -> Result<Vec<Template>> {
+        let templates = self.templates.lock().unwrap();
+        Ok(templates.values().map(|r| r.template.clone()).collect())
+    }
+
+    fn delete_template(&self, name: &str) -> Result<bool> {
+        Ok(self.templates.lock().unwrap().remove(name).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract;
+
+    #[test]
+    fn rooms_round_trip() {
+        contract::rooms_round_trip(&MemoryStore::new());
+    }
+
+    #[test]
+    fn rounds_are_appended_per_room() {
+        contract::rounds_are_appended_per_room(&MemoryStore::new());
+    }
+
+    #[test]
+    fn rounds_are_found_per_team() {
+        contract::rounds_are_found_per_team(&MemoryStore::new());
+    }
+
+    #[test]
+    fn history_outlives_room() {
+        contract::history_outlives_room(&MemoryStore::new());
+    }
+
+    #[test]
+    fn archived_rooms_are_not_restored() {
+        contract::archived_rooms_are_not_restored(&MemoryStore::new());
+    }
+
+    #[test]
+    fn templates_round_trip() {
+        contract::templates_round_trip(&MemoryStore::new());
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use poker_core::{
+    ParticipantId, RecordedVote, Room, RoomId, StoryId, Summary, Template, Timestamp,
+};
+use poker_webhook::Subscription;
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to bring a room back after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomRecord {
+    pub room: Room,
+    /// Resume tokens of the seated participants, so they can reclaim their
+    /// seats from the restarted server.
+    pub tokens: BTreeMap<ParticipantId, String>,
+    /// Argon2 hash of the join passphrase of a private room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+    /// Receivers the facilitator subscribed to the room's notifications.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Subscription>,
+    /// Names of the team members invited to a room started from a template,
+    /// by the token of their invite.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub invites: BTreeMap<String, String>,
+}
+
+/// A revealed round, as kept in the estimation history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    pub room: RoomId,
+    /// The team the room belongs to, if it was started from a template.
+    #[serde(default)]
+    pub team: Option<String>,
+    pub story: Option<String>,
+    /// Set when the round was about a backlog story.
+    pub story_id: Option<StoryId>,
+    pub revealed_at: Timestamp,
+    pub votes: Vec<RecordedVote>,
+    pub summary: Summary,
+}
+
+/// A room template and who may change it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateRecord {
+    pub template: Template,
+    /// Who may replace or delete the template. Unset for templates saved
+    /// before they had owners; whoever saves one next becomes its owner.
+    pub owner: Option<TemplateOwner>,
+}
+
+/// Whoever saved a template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateOwner {
+    /// A user logged in through the identity provider, by their subject.
+    Subject(String),
+    /// On servers without login, whoever holds the token handed out when
+    /// the template was first saved.
+    Token(String),
+}
@@ -0,0 +1,405 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use poker_core::{RoomId, StoryId, Template, Timestamp};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{Result, RoomRecord, RoundRecord, Store, StoreError, TemplateRecord};
+
+/// Schema changes, applied in order. The database's `user_version` records
+/// how many have run; append new steps, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: rooms and revealed-round history.
+    "CREATE TABLE rooms (
+         id         TEXT PRIMARY KEY,
+         record     TEXT NOT NULL,
+         updated_at INTEGER NOT NULL
+     );
+     CREATE TABLE rounds (
+         id          INTEGER PRIMARY KEY AUTOINCREMENT,
+         room_id     TEXT NOT NULL,
+         story       TEXT,
+         revealed_at INTEGER NOT NULL,
+         votes       TEXT NOT NULL,
+         summary     TEXT NOT NULL
+     );
+     CREATE INDEX rounds_by_room ON rounds (room_id, revealed_at);",
+    // 2: link rounds to backlog stories.
+    "ALTER TABLE rounds ADD COLUMN story_id INTEGER;",
+    // 3: room templates.
+    "CREATE TABLE templates (
+         name       TEXT PRIMARY KEY,
+         template   TEXT NOT NULL,
+         updated_at INTEGER NOT NULL
+     );",
+    // 4: the team each round's room belongs to.
+    "ALTER TABLE rounds ADD COLUMN team TEXT;
+     CREATE INDEX rounds_by_team ON rounds (team, revealed_at);",
+    // 5: who may change each template.
+    "ALTER TABLE templates ADD COLUMN owner TEXT;",
+    // 6: rooms closed for being idle, kept for their reports.
+    "CREATE TABLE archived_rooms (
+         id          TEXT PRIMARY KEY,
+         team        TEXT,
+         record      TEXT NOT NULL,
+         archived_at INTEGER NOT NULL
+     );
+     CREATE INDEX archived_rooms_by_team ON archived_rooms (team);",
+];
+
+/// Stores everything in a single SQLite database file.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path` and brings its schema up to
+    /// date.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A private database that lives as long as the store.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The schema version the database is at.
+    pub fn schema_version(&self) -> Result<u32> {
+        user_version(&self.conn.lock().unwrap())
+    }
+
+    /// The rounds matching `filter`, a condition on the single parameter
+    /// `value`, oldest first.
+    fn query_rounds(&self, filter: &str, value: &str) -> Result<Vec<RoundRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT room_id, team, story, story_id, revealed_at, votes, summary FROM rounds
+             WHERE {filter} ORDER BY revealed_at, id"
+        ))?;
+        let rows = stmt.query_map([value], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<u64>>(3)?,
+                row.get::<_, u64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+        let mut rounds = Vec::new();
+        for row in rows {
+            let (room, team, story, story_id, revealed_at, votes, summary) = row?;
+            rounds.push(RoundRecord {
+                room: RoomId::new(room),
+                team,
+                story,
+                story_id: story_id.map(StoryId),
+                revealed_at: Timestamp(revealed_at),
+                votes: serde_json::from_str(&votes)?,
+                summary: serde_json::from_str(&summary)?,
+            });
+        }
+        Ok(rounds)
+    }
+}
+
+fn user_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction()?;
+    let found = user_version(&tx)?;
+    let supported = MIGRATIONS.len() as u32;
+    if found > supported {
+        return Err(StoreError::SchemaTooNew { found, supported });
+    }
+    for step in &MIGRATIONS[found as usize..] {
+        tx.execute_batch(step)?;
+    }
+    tx.pragma_update(None, "user_version", supported)?;
+    tx.commit()?;
+    Ok(())
+}
+
+impl Store for SqliteStore {
+    fn save_room(&self, record: &RoomRecord) -> Result<()> {
+        let json = serde_json::to_string(record)?;
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO rooms (id, record, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET record = excluded.record,
+                                            updated_at = excluded.updated_at",
+            params![
+                record.room.id().as_str(),
+                json,
+                Timestamp::now().as_millis()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_room(&self, id: &RoomId) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM rooms WHERE id = ?1", [id.as_str()])?;
+        Ok(())
+    }
+
+    fn archive_room(&self, record: &RoomRecord) -> Result<()> {
+        let json = serde_json::to_string(record)?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM rooms WHERE id = ?1",
+            [record.room.id().as_str()],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO archived_rooms (id, team, record, archived_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                record.room.id().as_str(),
+                record.room.team(),
+                json,
+                Timestamp::now().as_millis()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn archived_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        let json = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT record FROM archived_rooms WHERE id = ?1",
+                [id.as_str()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    fn archived_team_rooms(&self, team: &str) -> Result<Vec<RoomRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT record FROM archived_rooms WHERE team = ?1")?;
+        let rows = stmt.query_map([team], |row| row.get::<_, String>(0))?;
+        let mut rooms = Vec::new();
+        for json in rows {
+            rooms.push(serde_json::from_str(&json?)?);
+        }
+        Ok(rooms)
+    }
+
+    fn load_rooms(&self) -> Result<Vec<RoomRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT record FROM rooms")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut rooms = Vec::new();
+        for json in rows {
+            rooms.push(serde_json::from_str(&json?)?);
+        }
+        Ok(rooms)
+    }
+
+    fn load_room(&self, id: &RoomId) -> Result<Option<RoomRecord>> {
+        let json = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT record FROM rooms WHERE id = ?1",
+                [id.as_str()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    fn record_round(&self, round: &RoundRecord) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO rounds (room_id, team, story, story_id, revealed_at, votes, summary)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                round.room.as_str(),
+                round.team,
+                round.story,
+                round.story_id.map(|id| id.0),
+                round.revealed_at.as_millis(),
+                serde_json::to_string(&round.votes)?,
+                serde_json::to_string(&round.summary)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn rounds(&self, room: &RoomId) -> Result<Vec<RoundRecord>> {
+        self.query_rounds("room_id = ?1", room.as_str())
+    }
+
+    fn team_rounds(&self, team: &str) -> Result<Vec<RoundRecord>> {
+        self.query_rounds("team = ?1", team)
+    }
+
+    fn save_template(&self, record: &TemplateRecord) -> Result<()> {
+        let json = serde_json::to_string(&record.template)?;
+        let owner = record
+            .owner
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO templates (name, template, owner, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (name) DO UPDATE SET template = excluded.template,
+                                              owner = excluded.owner,
+                                              updated_at = excluded.updated_at",
+            params![
+                record.template.name,
+                json,
+                owner,
+                Timestamp::now().as_millis()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn template(&self, name: &str) -> Result<Option<TemplateRecord>> {
+        let row = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT template, owner FROM templates WHERE name = ?1",
+                [name],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+        let Some((template, owner)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(TemplateRecord {
+            template: serde_json::from_str(&template)?,
+            owner: owner
+                .map(|owner| serde_json::from_str(&owner))
+                .transpose()?,
+        }))
+    }
+
+    fn templates(&self) -> Result<Vec<Template>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT template FROM templates ORDER BY name")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut templates = Vec::new();
+        for json in rows {
+            templates.push(serde_json::from_str(&json?)?);
+        }
+        Ok(templates)
+    }
+
+    fn delete_template(&self, name: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM templates WHERE name = ?1", [name])?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poker_core::{Deck, Summary};
+
+    use super::*;
+    use crate::contract;
+
+    #[test]
+    fn rooms_round_trip() {
+        contract::rooms_round_trip(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn rounds_are_appended_per_room() {
+        contract::rounds_are_appended_per_room(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn rounds_are_found_per_team() {
+        contract::rounds_are_found_per_team(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn history_outlives_room() {
+        contract::history_outlives_room(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn archived_rooms_are_not_restored() {
+        contract::archived_rooms_are_not_restored(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn templates_round_trip() {
+        contract::templates_round_trip(&SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn data_survives_reopening_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("poker.db");
+        {
+            let store = SqliteStore::open(&path).unwrap();
+            contract::rooms_round_trip(&store);
+        }
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len() as u32);
+        assert_eq!(store.load_rooms().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn upgrades_an_older_schema_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("poker.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO rounds (room_id, story, revealed_at, votes, summary)
+             VALUES ('a', 'Old', 5, '[]', ?1)",
+            [serde_json::to_string(&Summary::new(&Deck::fibonacci(), [])).unwrap()],
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len() as u32);
+        let rounds = store.rounds(&RoomId::new("a")).unwrap();
+        assert_eq!(rounds[0].story.as_deref(), Some("Old"));
+        assert_eq!(rounds[0].story_id, None);
+    }
+
+    #[test]
+    fn refuses_a_schema_from_the_future() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("poker.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", 99).unwrap();
+        drop(conn);
+        assert!(matches!(
+            SqliteStore::open(&path),
+            Err(StoreError::SchemaTooNew { found: 99, .. })
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use poker_core::{Card, NewStory};
+use reqwest::{Client, RequestBuilder, Response, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{Result, Tracker, TrackerError};
+
+/// Upper bound on the stories one fetch pulls in, however many match.
+const MAX_STORIES: usize = 500;
+
+#[derive(Debug, Clone)]
+pub enum JiraAuth {
+    None,
+    /// User name or e-mail with an API token (Cloud) or password (Server).
+    Basic {
+        user: String,
+        token: String,
+    },
+    /// A personal access token.
+    Bearer(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct JiraConfig {
+    /// Root of the Jira instance, e.g. `https://example.atlassian.net`.
+    pub base_url: String,
+    pub auth: JiraAuth,
+    /// Field that receives estimates, e.g. `customfield_10016` for story
+    /// points.
+    pub estimate_field: String,
+    /// Issues requested per search page.
+    pub page_size: usize,
+}
+
+impl JiraConfig {
+    pub fn new(base_url: impl Into<String>, estimate_field: impl Into<String>) -> Self {
+        JiraConfig {
+            base_url: base_url.into(),
+            auth: JiraAuth::None,
+            estimate_field: estimate_field.into(),
+            page_size: 50,
+        }
+    }
+}
+
+/// Talks to Jira's REST API, version 2.
+pub struct JiraTracker {
+    config: JiraConfig,
+    base: Url,
+    client: Client,
+}
+
+impl JiraTracker {
+    pub fn new(config: JiraConfig) -> Result<Self> {
+        let mut base = Url::parse(&config.base_url)
+            .map_err(|err| TrackerError::InvalidUrl(format!("{}: {err}", config.base_url)))?;
+        if base.cannot_be_a_base() {
+            return Err(TrackerError::InvalidUrl(config.base_url));
+        }
+        // Make relative joins land below the configured path.
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        Ok(JiraTracker {
+            config,
+            base,
+            client: Client::new(),
+        })
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("checked in JiraTracker::new")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.config.auth {
+            JiraAuth::None => request,
+            JiraAuth::Basic { user, token } => request.basic_auth(user, Some(token)),
+            JiraAuth::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchPage {
+    #[serde(default)]
+    total: usize,
+    issues: Vec<Issue>,
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    key: String,
+    fields: IssueFields,
+}
+
+#[derive(Deserialize)]
+struct IssueFields {
+    #[serde(default)]
+    summary: String,
+}
+
+/// Fails on any non-success status, keeping the body for the error message.
+async fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(TrackerError::Status {
+        status: status.as_u16(),
+        body,
+    })
+}
+
+#[async_trait]
+impl Tracker for JiraTracker {
+    async fn fetch_stories(&self, query: &str) -> Result<Vec<NewStory>> {
+        let url = self.url(&["rest", "api", "2", "search"]);
+        let mut stories = Vec::new();
+        loop {
+            let start_at = stories.len().to_string();
+            let max_results = self.config.page_size.to_string();
+            let request = self.client.get(url.clone()).query(&[
+                ("jql", query),
+                ("fields", "summary"),
+                ("startAt", &start_at),
+                ("maxResults", &max_results),
+            ]);
+            let page: SearchPage = check(self.authorize(request).send().await?)
+                .await?
+                .json()
+                .await?;
+            let fetched = page.issues.len();
+            stories.extend(
+                page.issues
+                    .into_iter()
+                    .map(|issue| NewStory::new(issue.fields.summary).with_key(issue.key)),
+            );
+            if fetched == 0 || stories.len() >= page.total || stories.len() >= MAX_STORIES {
+                break;
+            }
+        }
+        stories.truncate(MAX_STORIES);
+        Ok(stories)
+    }
+
+    async fn write_estimate(&self, key: &str, estimate: &Card) -> Result<()> {
+        // Numeric cards go in as numbers so story-point fields accept them.
+        let value = estimate
+            .value()
+            .and_then(serde_json::Number::from_f64)
+            .map_or_else(|| Value::String(estimate.label().to_owned()), Value::Number);
+        let request = self
+            .client
+            .put(self.url(&["rest", "api", "2", "issue", key]))
+            .json(&json!({ "fields": { &self.config.estimate_field: value } }));
+        check(self.authorize(request).send().await?).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! Issue-tracker integration for OpenPlanningPoker.
+//!
+//! A [`Tracker`] fetches stories into a room's backlog and writes the agreed
+//! estimates back once they are recorded. [`JiraTracker`] speaks the Jira
+//! REST API (version 2), which Jira Server, Data Center and Cloud all serve.
+
+mod jira;
+
+use async_trait::async_trait;
+use poker_core::{Card, NewStory};
+
+pub use jira::{JiraAuth, JiraConfig, JiraTracker};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrackerError {
+    #[error("tracker request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("tracker answered {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("invalid tracker URL: {0}")]
+    InvalidUrl(String),
+}
+
+pub type Result<T, E = TrackerError> = std::result::Result<T, E>;
+
+/// An issue tracker that holds the stories being estimated.
+#[async_trait]
+pub trait Tracker: Send + Sync {
+    /// The stories matching `query`, in the tracker's order, each with its
+    /// issue key. The query is in the tracker's own language, e.g. JQL.
+    async fn fetch_stories(&self, query: &str) -> Result<Vec<NewStory>>;
+
+    /// Writes `estimate` to the configured estimate field of issue `key`.
+    async fn write_estimate(&self, key: &str, estimate: &Card) -> Result<()>;
+}
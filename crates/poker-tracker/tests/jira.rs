@@ -0,0 +1,187 @@
+//! Exercises the Jira adapter against an in-process mock of the REST API.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use poker_core::{Card, NewStory};
+use poker_tracker::{JiraAuth, JiraConfig, JiraTracker, Tracker, TrackerError};
+use serde_json::{json, Value};
+
+#[derive(Default)]
+struct Mock {
+    /// Issue keys and summaries served by the search endpoint.
+    issues: Vec<(String, String)>,
+    searches: Vec<HashMap<String, String>>,
+    /// Issue key and body of every update.
+    updates: Vec<(String, Value)>,
+    authorization: Vec<Option<String>>,
+}
+
+type Shared = Arc<Mutex<Mock>>;
+
+async fn search(
+    State(mock): State<Shared>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut mock = mock.lock().unwrap();
+    mock.authorization.push(
+        headers
+            .get("authorization")
+            .map(|v| v.to_str().unwrap().to_owned()),
+    );
+    let start: usize = params["startAt"].parse().unwrap();
+    let max: usize = params["maxResults"].parse().unwrap();
+    let issues: Vec<_> = mock
+        .issues
+        .iter()
+        .skip(start)
+        .take(max)
+        .map(|(key, summary)| json!({"key": key, "fields": {"summary": summary}}))
+        .collect();
+    let total = mock.issues.len();
+    mock.searches.push(params);
+    Json(json!({"startAt": start, "maxResults": max, "total": total, "issues": issues}))
+}
+
+async fn update(
+    State(mock): State<Shared>,
+    Path(key): Path<String>,
+    Json(body): Json<Value>,
+) -> StatusCode {
+    let mut mock = mock.lock().unwrap();
+    if !mock.issues.iter().any(|(k, _)| *k == key) {
+        return StatusCode::NOT_FOUND;
+    }
+    mock.updates.push((key, body));
+    StatusCode::NO_CONTENT
+}
+
+async fn start(issues: &[(&str, &str)]) -> (SocketAddr, Shared) {
+    let mock = Shared::default();
+    mock.lock().unwrap().issues = issues
+        .iter()
+        .map(|(k, s)| (k.to_string(), s.to_string()))
+        .collect();
+    let app = Router::new()
+        .route("/jira/rest/api/2/search", get(search))
+        .route("/jira/rest/api/2/issue/{key}", put(update))
+        .with_state(mock.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr, mock)
+}
+
+fn tracker(addr: SocketAddr, auth: JiraAuth) -> JiraTracker {
+    let mut config = JiraConfig::new(format!("http://{addr}/jira"), "customfield_10016");
+    config.auth = auth;
+    config.page_size = 2;
+    JiraTracker::new(config).unwrap()
+}
+
+#[tokio::test]
+async fn fetches_every_page_of_a_search() {
+    let (addr, mock) = start(&[("WEB-1", "Login"), ("WEB-2", "Search"), ("WEB-3", "Cart")]).await;
+    let jira = tracker(
+        addr,
+        JiraAuth::Basic {
+            user: "ann@example.com".into(),
+            token: "secret".into(),
+        },
+    );
+
+    let stories = jira
+        .fetch_stories("project = WEB AND sprint in openSprints()")
+        .await
+        .unwrap();
+    assert_eq!(
+        stories,
+        [
+            NewStory::new("Login").with_key("WEB-1"),
+            NewStory::new("Search").with_key("WEB-2"),
+            NewStory::new("Cart").with_key("WEB-3"),
+        ]
+    );
+
+    let mock = mock.lock().unwrap();
+    assert_eq!(mock.searches.len(), 2);
+    assert_eq!(
+        mock.searches[0]["jql"],
+        "project = WEB AND sprint in openSprints()"
+    );
+    assert_eq!(mock.searches[1]["startAt"], "2");
+    // "ann@example.com:secret" in base64.
+    assert_eq!(
+        mock.authorization[0].as_deref(),
+        Some("Basic YW5uQGV4YW1wbGUuY29tOnNlY3JldA==")
+    );
+}
+
+#[tokio::test]
+async fn writes_numeric_estimates_as_numbers() {
+    let (addr, mock) = start(&[("WEB-1", "Login"), ("WEB-2", "Search")]).await;
+    let jira = tracker(addr, JiraAuth::Bearer("pat".into()));
+
+    jira.write_estimate("WEB-1", &Card::new("5")).await.unwrap();
+    jira.write_estimate("WEB-2", &Card::new("½")).await.unwrap();
+    jira.write_estimate("WEB-2", &Card::new("XL"))
+        .await
+        .unwrap();
+
+    let mock = mock.lock().unwrap();
+    assert_eq!(
+        mock.updates,
+        [
+            (
+                "WEB-1".into(),
+                json!({"fields": {"customfield_10016": 5.0}})
+            ),
+            (
+                "WEB-2".into(),
+                json!({"fields": {"customfield_10016": 0.5}})
+            ),
+            (
+                "WEB-2".into(),
+                json!({"fields": {"customfield_10016": "XL"}})
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn error_statuses_are_reported() {
+    let (addr, _mock) = start(&[]).await;
+    let jira = tracker(addr, JiraAuth::None);
+    let err = jira
+        .write_estimate("WEB-404", &Card::new("3"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TrackerError::Status { status: 404, .. }));
+}
+
+#[tokio::test]
+async fn unreachable_tracker_is_an_error() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    let jira = tracker(addr, JiraAuth::None);
+    assert!(matches!(
+        jira.fetch_stories("project = WEB").await,
+        Err(TrackerError::Http(_))
+    ));
+}
+
+#[test]
+fn rejects_a_malformed_base_url() {
+    let config = JiraConfig::new("not a url", "customfield_10016");
+    assert!(matches!(
+        JiraTracker::new(config),
+        Err(TrackerError::InvalidUrl(_))
+    ));
+}
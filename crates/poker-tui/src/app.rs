@@ -0,0 +1,478 @@
+use std::time::{Duration, Instant};
+
+use poker_core::{Card, ParticipantId, Phase, Role, Timestamp, VotingWindow};
+use poker_protocol::{ClientMessage, ParticipantView, RoomSnapshot, ServerMessage};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What the event loop should do after a key press.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    Send(ClientMessage),
+    Quit,
+}
+
+/// Everything the terminal client knows about its room.
+///
+/// The state is rebuilt from the server's messages alone: a `welcome`
+/// snapshot followed by the events that change it.
+#[derive(Debug, Default)]
+pub struct App {
+    room: Option<RoomSnapshot>,
+    you: Option<ParticipantId>,
+    your_vote: Option<Card>,
+    /// A vote sent but not yet confirmed by the server.
+    pending_vote: Option<Card>,
+    /// Index of the highlighted card.
+    selected: usize,
+    /// When the round timer runs out, on this machine's monotonic clock.
+    timer: Option<Instant>,
+    /// The last error or notice from the server.
+    status: Option<String>,
+}
+
+impl App {
+    pub fn room(&self) -> Option<&RoomSnapshot> {
+        self.room.as_ref()
+    }
+
+    pub fn you(&self) -> Option<ParticipantId> {
+        self.you
+    }
+
+    pub fn your_vote(&self) -> Option<&Card> {
+        self.your_vote.as_ref()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn is_facilitator(&self) -> bool {
+        self.you.is_some() && self.room.as_ref().and_then(|r| r.facilitator) == self.you
+    }
+
+    /// Time left on the round timer at `now`.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.timer.map(|end| end.saturating_duration_since(now))
+    }
+
+    fn me(&self) -> Option<&ParticipantView> {
+        let you = self.you?;
+        self.room
+            .as_ref()?
+            .participants
+            .iter()
+            .find(|p| p.id == you)
+    }
+
+    fn participant_mut(&mut self, id: ParticipantId) -> Option<&mut ParticipantView> {
+        self.room
+            .as_mut()?
+            .participants
+            .iter_mut()
+            .find(|p| p.id == id)
+    }
+
+    /// Applies a message from the server, received at `now`.
+    pub fn handle(&mut self, msg: ServerMessage, now: Instant) {
+        match msg {
+            ServerMessage::Welcome {
+                you,
+                your_vote,
+                room,
+                ..
+            } => {
+                // A late joiner only has the server's wall-clock deadline to go by.
+                self.timer = room
+                    .timer
+                    .map(|t| now + t.deadline.saturating_duration_since(Timestamp::now()));
+                self.you = Some(you);
+                self.your_vote = your_vote;
+                self.selected = 0;
+                self.room = Some(*room);
+                self.status = None;
+            }
+            ServerMessage::Left => self.leave_room("You left the room."),
+            ServerMessage::ParticipantJoined { participant } => {
+                if let Some(room) = &mut self.room {
+                    room.participants.push(ParticipantView {
+                        id: participant.id,
+                        name: participant.name,
+                        role: participant.role,
+                        presence: participant.presence,
+                        voted: false,
+                    });
+                }
+            }
+            ServerMessage::ParticipantLeft { participant } => self.remove(participant),
+            ServerMessage::ParticipantKicked { participant } => {
+                if Some(participant) == self.you {
+                    self.leave_room("The facilitator removed you from the room.");
+                } else {
+                    self.remove(participant);
+                }
+            }
+            ServerMessage::FacilitatorChanged { participant } => {
+                if let Some(room) = &mut self.room {
+                    room.facilitator = Some(participant);
+                }
+            }
+            ServerMessage::RoleChanged { participant, role } => {
+                if let Some(p) = self.participant_mut(participant) {
+                    p.role = role;
+                    if role == Role::Observer {
+                        p.voted = false;
+                    }
+                }
+                if Some(participant) == self.you && role == Role::Observer {
+                    self.your_vote = None;
+                }
+            }
+            ServerMessage::PresenceChanged {
+                participant,
+                presence,
+            } => {
+                if let Some(p) = self.participant_mut(participant) {
+                    p.presence = presence;
+                }
+            }
+            ServerMessage::Voted { participant } => {
+                if let Some(p) = self.participant_mut(participant) {
+                    p.voted = true;
+                }
+                if Some(participant) == self.you {
+                    self.your_vote = self.pending_vote.take().or(self.your_vote.take());
+                }
+            }
+            ServerMessage::VoteRetracted { participant } => {
+                if let Some(p) = self.participant_mut(participant) {
+                    p.voted = false;
+                }
+                if Some(participant) == self.you {
+                    self.your_vote = None;
+                }
+            }
+            ServerMessage::Revealed { votes, summary } => {
+                if let Some(room) = &mut self.room {
+                    room.phase = Phase::Revealed;
+                    room.votes = Some(votes);
+                    room.summary = Some(summary);
+                }
+            }
+            ServerMessage::RoundReset => self.new_round(),
+            ServerMessage::RoundStarted { story, story_id } => {
+                self.new_round();
+                if let Some(room) = &mut self.room {
+                    room.story = story;
+                    room.current_story = story_id;
+                }
+            }
+            ServerMessage::StoriesAdded { stories } => {
+                if let Some(room) = &mut self.room {
+                    room.stories.extend(stories);
+                }
+            }
+            ServerMessage::StoryRemoved { story } => {
+                if let Some(room) = &mut self.room {
+                    room.stories.retain(|s| s.id != story);
+                    if room.current_story == Some(story) {
+                        room.current_story = None;
+                    }
+                }
+            }
+            ServerMessage::EstimateRecorded { story, estimate } => {
+                if let Some(s) = self
+                    .room
+                    .as_mut()
+                    .and_then(|r| r.stories.iter_mut().find(|s| s.id == story))
+                {
+                    s.estimate = Some(estimate.clone());
+                }
+                self.status = Some(format!("Estimate recorded: {estimate}"));
+            }
+            ServerMessage::TimerStarted {
+                started_at,
+                deadline,
+            } => self.timer = Some(now + deadline.saturating_duration_since(started_at)),
+            ServerMessage::TimerStopped => self.timer = None,
+            ServerMessage::TimerExpired => {
+                self.timer = None;
+                self.status = Some("Time is up!".into());
+            }
+            ServerMessage::DeckChanged { deck, cards } => {
+                self.new_round();
+                self.selected = 0;
+                if let Some(room) = &mut self.room {
+                    room.deck = deck;
+                    room.cards = cards;
+                }
+            }
+            ServerMessage::SettingsChanged { settings } => {
+                if let Some(room) = &mut self.room {
+                    room.settings = settings;
+                }
+            }
+            ServerMessage::VotingOpened {
+                opened_at,
+                deadline,
+                quorum,
+            } => {
+                if let Some(room) = &mut self.room {
+                    room.voting = Some(VotingWindow {
+                        opened_at,
+                        deadline,
+                        quorum,
+                    });
+                }
+                self.status = Some(format!("Voting is open until {}", deadline.to_rfc3339()));
+            }
+            ServerMessage::VotingClosed { .. } => {
+                if let Some(room) = &mut self.room {
+                    room.voting = None;
+                }
+            }
+            ServerMessage::Error { message, .. } => {
+                self.pending_vote = None;
+                self.status = Some(message);
+            }
+            ServerMessage::EstimateSyncFailed { message, .. } => self.status = Some(message),
+            ServerMessage::WebhookFailed { webhook, failure } => {
+                self.status = Some(format!("Webhook {webhook} failed: {}", failure.message));
+            }
+            ServerMessage::RoomCreated { .. }
+            | ServerMessage::Authenticated { .. }
+            | ServerMessage::Pong
+            | ServerMessage::WebhookAdded { .. }
+            | ServerMessage::WebhookRemoved { .. }
+            | ServerMessage::Webhooks { .. }
+            | ServerMessage::TemplateSaved { .. }
+            | ServerMessage::TemplateDeleted { .. }
+            | ServerMessage::Templates { .. } => {}
+        }
+    }
+
+    fn remove(&mut self, id: ParticipantId) {
+        if let Some(room) = &mut self.room {
+            room.participants.retain(|p| p.id != id);
+        }
+    }
+
+    fn leave_room(&mut self, status: &str) {
+        *self = App {
+            status: Some(status.to_owned()),
+            ..App::default()
+        };
+    }
+
+    fn new_round(&mut self) {
+        self.your_vote = None;
+        self.pending_vote = None;
+        self.timer = None;
+        if let Some(room) = &mut self.room {
+            room.phase = Phase::Voting;
+            room.votes = None;
+            room.summary = None;
+            for p in &mut room.participants {
+                p.voted = false;
+            }
+        }
+    }
+
+    /// Maps a key press to what the client should do about it.
+    pub fn on_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Effect::Quit);
+        }
+        let cards = self.room.as_ref().map_or(0, |r| r.cards.len());
+        let send = |msg| Some(Effect::Send(msg));
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Effect::Quit),
+            KeyCode::Left | KeyCode::Char('h') if cards > 0 => {
+                self.selected = (self.selected + cards - 1) % cards;
+                None
+            }
+            KeyCode::Right | KeyCode::Char('l') if cards > 0 => {
+                self.selected = (self.selected + 1) % cards;
+                None
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                let index = digit as usize - '1' as usize;
+                (index < cards).then(|| {
+                    self.selected = index;
+                    self.vote()
+                })?
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => self.vote(),
+            KeyCode::Backspace | KeyCode::Char('x') if self.your_vote.is_some() => {
+                send(ClientMessage::RetractVote)
+            }
+            KeyCode::Char('o') => {
+                let role = match self.me()?.role {
+                    Role::Voter => Role::Observer,
+                    Role::Observer => Role::Voter,
+                };
+                send(ClientMessage::SetRole { role })
+            }
+            KeyCode::Char('r') => send(ClientMessage::Reveal),
+            KeyCode::Char('n') => send(ClientMessage::Reset),
+            KeyCode::Char('s') => send(ClientMessage::NextStory),
+            _ => None,
+        }
+    }
+
+    fn vote(&mut self) -> Option<Effect> {
+        let card = self.room.as_ref()?.cards.get(self.selected)?.clone();
+        self.pending_vote = Some(card.clone());
+        Some(Effect::Send(ClientMessage::Vote { card }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poker_core::{Deck, Presence, Room, RoomId, Summary};
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn welcomed() -> (App, ParticipantId, Instant) {
+        let mut room = Room::new(RoomId::new("r"), "Sprint 42", Deck::fibonacci());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let mut app = App::default();
+        let now = Instant::now();
+        app.handle(
+            ServerMessage::Welcome {
+                version: 1,
+                you: alice,
+                token: "t".into(),
+                your_vote: None,
+                room: Box::new(RoomSnapshot::of(&room)),
+            },
+            now,
+        );
+        (app, alice, now)
+    }
+
+    #[test]
+    fn keys_pick_and_play_a_card() {
+        let (mut app, alice, now) = welcomed();
+        assert_eq!(app.on_key(key(KeyCode::Right)), None);
+        assert_eq!(app.on_key(key(KeyCode::Right)), None);
+        let vote = ClientMessage::Vote {
+            card: Card::new("1"),
+        };
+        assert_eq!(app.on_key(key(KeyCode::Enter)), Some(Effect::Send(vote)));
+        assert_eq!(app.your_vote(), None);
+        app.handle(ServerMessage::Voted { participant: alice }, now);
+        assert_eq!(app.your_vote(), Some(&Card::new("1")));
+        assert!(app.room().unwrap().participants[0].voted);
+
+        assert_eq!(
+            app.on_key(key(KeyCode::Char('x'))),
+            Some(Effect::Send(ClientMessage::RetractVote))
+        );
+        assert_eq!(app.on_key(key(KeyCode::Left)), None);
+        assert_eq!(app.selected(), 1);
+        assert_eq!(app.on_key(key(KeyCode::Char('q'))), Some(Effect::Quit));
+    }
+
+    #[test]
+    fn selection_wraps_and_digits_vote_directly() {
+        let (mut app, _, _) = welcomed();
+        app.on_key(key(KeyCode::Left));
+        let cards = app.room().unwrap().cards.len();
+        assert_eq!(app.selected(), cards - 1);
+        assert_eq!(
+            app.on_key(key(KeyCode::Char('5'))),
+            Some(Effect::Send(ClientMessage::Vote {
+                card: Card::new("3")
+            }))
+        );
+        assert_eq!(app.selected(), 4);
+    }
+
+    #[test]
+    fn events_keep_the_room_up_to_date() {
+        let (mut app, alice, now) = welcomed();
+        let bob = ParticipantId(2);
+        app.handle(
+            ServerMessage::ParticipantJoined {
+                participant: poker_core::Participant {
+                    id: bob,
+                    name: "Bob".into(),
+                    role: Role::Voter,
+                    presence: Presence::Connected,
+                },
+            },
+            now,
+        );
+        app.handle(ServerMessage::Voted { participant: bob }, now);
+        app.handle(
+            ServerMessage::PresenceChanged {
+                participant: bob,
+                presence: Presence::Idle,
+            },
+            now,
+        );
+        let room = app.room().unwrap();
+        assert_eq!(room.participants.len(), 2);
+        assert!(room.participants[1].voted);
+        assert_eq!(room.participants[1].presence, Presence::Idle);
+        assert!(app.is_facilitator());
+
+        app.handle(
+            ServerMessage::Revealed {
+                votes: Vec::new(),
+                summary: Summary::new(&Deck::fibonacci(), [&Card::new("5")]),
+            },
+            now,
+        );
+        assert_eq!(app.room().unwrap().phase, Phase::Revealed);
+        app.handle(ServerMessage::RoundReset, now);
+        let room = app.room().unwrap();
+        assert_eq!(room.phase, Phase::Voting);
+        assert!(room.participants.iter().all(|p| !p.voted));
+
+        app.handle(ServerMessage::ParticipantKicked { participant: alice }, now);
+        assert!(app.room().is_none());
+        assert!(app.status().unwrap().contains("removed"));
+    }
+
+    #[test]
+    fn timer_counts_down_from_when_it_was_received() {
+        let (mut app, _, now) = welcomed();
+        app.handle(
+            ServerMessage::TimerStarted {
+                started_at: Timestamp(1_000),
+                deadline: Timestamp(61_000),
+            },
+            now,
+        );
+        assert_eq!(
+            app.time_left(now + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        app.handle(ServerMessage::TimerExpired, now);
+        assert_eq!(app.time_left(now), None);
+        assert_eq!(app.status(), Some("Time is up!"));
+    }
+
+    #[test]
+    fn errors_are_shown_and_drop_the_pending_vote() {
+        let (mut app, alice, now) = welcomed();
+        app.on_key(key(KeyCode::Enter));
+        app.handle(
+            ServerMessage::error(poker_protocol::ErrorCode::RoundRevealed, "too late"),
+            now,
+        );
+        assert_eq!(app.status(), Some("too late"));
+        app.handle(ServerMessage::Voted { participant: alice }, now);
+        assert_eq!(app.your_vote(), None);
+    }
+}
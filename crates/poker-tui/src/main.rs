@@ -0,0 +1,145 @@
+//! Terminal client for OpenPlanningPoker.
+//!
+//! Joins a room over the same WebSocket protocol as any other client, shows
+//! who is seated and who has voted, and lets you pick cards with the keyboard.
+
+mod app;
+mod ui;
+
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use futures_util::{SinkExt, StreamExt};
+use poker_core::{Role, RoomId};
+use poker_protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::app::{App, Effect};
+
+/// Heartbeat that keeps the seat from being shown as idle.
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the screen is redrawn without input, for the round timer.
+const TICK: Duration = Duration::from_millis(500);
+
+/// Joins an OpenPlanningPoker room from the terminal.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Room code to join.
+    room: String,
+    /// Name shown to the other participants. Logged-in users go by the name
+    /// their identity provider knows them by instead.
+    #[arg(
+        long,
+        env = "POKER_NAME",
+        required_unless_present_any = ["session", "invite"]
+    )]
+    name: Option<String>,
+    /// WebSocket endpoint of the server.
+    #[arg(long, env = "POKER_SERVER", default_value = "ws://127.0.0.1:8080/ws")]
+    server: String,
+    /// Join as an observer who does not vote.
+    #[arg(long)]
+    observer: bool,
+    /// Passphrase of a private room.
+    #[arg(long, env = "POKER_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// Session token from the server's `/auth/login`, for servers that
+    /// require login.
+    #[arg(long, env = "POKER_SESSION", hide_env_values = true)]
+    session: Option<String>,
+    /// Invite to the room handed out to a member of its team, which seats
+    /// you under your name and role on the team.
+    #[arg(long, env = "POKER_INVITE", hide_env_values = true)]
+    invite: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let (mut ws, _) = tokio_tungstenite::connect_async(cli.server.as_str())
+        .await
+        .map_err(std::io::Error::other)?;
+    // The server handles messages in order, so the join is only looked at
+    // once the session was.
+    if let Some(session) = cli.session {
+        ws.send(encode(&ClientMessage::Authenticate { session }))
+            .await
+            .map_err(std::io::Error::other)?;
+    }
+    let join = ClientMessage::Join {
+        version: PROTOCOL_VERSION,
+        room: RoomId::new(cli.room),
+        name: cli.name.unwrap_or_default(),
+        role: if cli.observer {
+            Role::Observer
+        } else {
+            Role::Voter
+        },
+        passphrase: cli.passphrase,
+        invite: cli.invite,
+    };
+    ws.send(encode(&join))
+        .await
+        .map_err(std::io::Error::other)?;
+
+    let mut terminal = ratatui::init();
+    let mut app = App::default();
+    let mut keys = EventStream::new();
+    let mut pings = tokio::time::interval(PING_INTERVAL);
+    let mut ticks = tokio::time::interval(TICK);
+    let result = loop {
+        if let Err(err) = terminal.draw(|frame| ui::draw(frame, &app, Instant::now())) {
+            break Err(err);
+        }
+        let outgoing = tokio::select! {
+            event = keys.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    match app.on_key(key) {
+                        Some(Effect::Send(msg)) => Some(msg),
+                        Some(Effect::Quit) => {
+                            let _ = ws.send(encode(&ClientMessage::Leave)).await;
+                            break Ok(());
+                        }
+                        None => None,
+                    }
+                }
+                Some(Ok(_)) => None,
+                Some(Err(err)) => break Err(err),
+                None => break Ok(()),
+            },
+            frame = ws.next() => match frame {
+                Some(Ok(Message::Text(text))) => {
+                    if let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) {
+                        app.handle(msg, Instant::now());
+                    }
+                    None
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    break Err(std::io::Error::other("the server closed the connection"));
+                }
+                Some(Ok(_)) => None,
+                Some(Err(err)) => break Err(std::io::Error::other(err)),
+            },
+            _ = pings.tick() => Some(ClientMessage::Ping),
+            _ = ticks.tick() => None,
+        };
+        if let Some(msg) = outgoing {
+            if let Err(err) = ws.send(encode(&msg)).await {
+                break Err(std::io::Error::other(err));
+            }
+        }
+    };
+    ratatui::restore();
+    let _ = ws.close(None).await;
+    result
+}
+
+fn encode(msg: &ClientMessage) -> Message {
+    Message::Text(
+        serde_json::to_string(msg)
+            .expect("client messages serialize")
+            .into(),
+    )
+}
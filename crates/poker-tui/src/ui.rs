@@ -0,0 +1,185 @@
+use std::time::Instant;
+
+use poker_core::{Phase, Presence, Role};
+use poker_protocol::RoomSnapshot;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::app::App;
+
+const HELP: &str = "←/→ pick · enter vote · 1-9 vote · x retract · o observe · q quit";
+const FACILITATOR_HELP: &str = " · r reveal · n re-vote · s next story";
+
+/// Renders the whole screen.
+pub fn draw(frame: &mut Frame, app: &App, now: Instant) {
+    let [header, body, cards, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(4),
+        Constraint::Length(3),
+        Constraint::Length(2),
+    ])
+    .areas(frame.area());
+
+    let Some(room) = app.room() else {
+        let text = app.status().unwrap_or("Joining…");
+        frame.render_widget(Paragraph::new(text).block(Block::bordered()), body);
+        return;
+    };
+    draw_header(frame, header, app, room, now);
+    draw_participants(frame, body, app, room);
+    draw_cards(frame, cards, app, room);
+
+    let footer_text = match app.status() {
+        Some(status) => Line::from(status.to_owned()).yellow(),
+        None if app.is_facilitator() => Line::from(format!("{HELP}{FACILITATOR_HELP}")).dark_gray(),
+        None => Line::from(HELP).dark_gray(),
+    };
+    frame.render_widget(
+        Paragraph::new(footer_text).wrap(Wrap { trim: true }),
+        footer,
+    );
+}
+
+fn draw_header(frame: &mut Frame, area: Rect, app: &App, room: &RoomSnapshot, now: Instant) {
+    let mut spans = vec![
+        Span::from(room.story.as_deref().unwrap_or("No story")).bold(),
+        Span::from("  "),
+        match room.phase {
+            Phase::Voting => Span::from("[voting]").cyan(),
+            Phase::Revealed => Span::from("[revealed]").green(),
+        },
+    ];
+    if let Some(left) = app.time_left(now) {
+        let secs = left.as_secs();
+        spans.push(Span::from(format!("  ⏱ {}:{:02}", secs / 60, secs % 60)).magenta());
+    }
+    if let Some(numeric) = room.summary.as_ref().and_then(|s| s.numeric.as_ref()) {
+        let mut text = format!("  avg {:.1} · median {}", numeric.average, numeric.median);
+        if numeric.consensus {
+            text.push_str(" · consensus");
+        }
+        spans.push(Span::from(text));
+    }
+    let title = format!(" {} ({}) ", room.name, room.id);
+    frame.render_widget(
+        Paragraph::new(Line::from(spans)).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn draw_participants(frame: &mut Frame, area: Rect, app: &App, room: &RoomSnapshot) {
+    let items: Vec<ListItem> = room
+        .participants
+        .iter()
+        .map(|p| {
+            let marker = if room.facilitator == Some(p.id) {
+                "★ "
+            } else {
+                "  "
+            };
+            let revealed = room
+                .votes
+                .as_ref()
+                .and_then(|votes| votes.iter().find(|v| v.participant == p.id));
+            let status = match (p.role, revealed) {
+                (Role::Observer, _) => Span::from("observing").dark_gray(),
+                (_, Some(vote)) => Span::from(vote.card.to_string()).bold().green(),
+                (_, None) if p.voted => Span::from("✓ voted").green(),
+                (_, None) => Span::from("… thinking").dark_gray(),
+            };
+            let mut name = Span::from(format!("{marker}{:<20}", p.name));
+            if Some(p.id) == app.you() {
+                name = name.bold();
+            }
+            let presence = match p.presence {
+                Presence::Connected => Span::from(""),
+                Presence::Idle => Span::from(" (idle)").dark_gray(),
+                Presence::Gone => Span::from(" (away)").dark_gray(),
+            };
+            ListItem::new(Line::from(vec![name, status, presence]))
+        })
+        .collect();
+    let title = format!(" Participants ({}) ", room.participants.len());
+    frame.render_widget(List::new(items).block(Block::bordered().title(title)), area);
+}
+
+fn draw_cards(frame: &mut Frame, area: Rect, app: &App, room: &RoomSnapshot) {
+    let mut spans = Vec::with_capacity(room.cards.len() * 2);
+    for (i, card) in room.cards.iter().enumerate() {
+        let mut style = Style::default();
+        if app.your_vote() == Some(card) {
+            style = style.fg(Color::Green).add_modifier(Modifier::BOLD);
+        }
+        if i == app.selected() {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        spans.push(Span::styled(format!(" {card} "), style));
+        spans.push(Span::from(" "));
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(spans)).block(Block::bordered().title(" Cards ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use poker_core::{Card, Deck, Room, RoomId, Timestamp};
+    use poker_protocol::ServerMessage;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::*;
+
+    fn render(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 14)).unwrap();
+        terminal
+            .draw(|frame| draw(frame, app, Instant::now()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content()
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn shows_participants_voting_status_and_cards() {
+        let mut room = Room::new(RoomId::new("k3m9"), "Sprint 42", Deck::t_shirt());
+        let alice = room.join("Alice", Role::Voter).unwrap();
+        let bob = room.join("Bob", Role::Voter).unwrap();
+        room.join("Olga", Role::Observer).unwrap();
+        room.start_round(alice, Some("Login page".into())).unwrap();
+        room.vote(bob, Card::new("M"), Timestamp(0)).unwrap();
+
+        let mut app = App::default();
+        assert!(render(&app).contains("Joining"));
+        app.handle(
+            ServerMessage::Welcome {
+                version: 1,
+                you: alice,
+                token: "t".into(),
+                your_vote: None,
+                room: Box::new(poker_protocol::RoomSnapshot::of(&room)),
+            },
+            Instant::now(),
+        );
+        let screen = render(&app);
+        assert!(screen.contains("Sprint 42 (k3m9)"));
+        assert!(screen.contains("Login page"));
+        assert!(screen.contains("[voting]"));
+        assert!(screen.contains("★ Alice"));
+        assert!(screen.contains("… thinking"));
+        assert!(screen.contains("✓ voted"));
+        assert!(screen.contains("observing"));
+        assert!(screen.contains(" XS "));
+        assert!(screen.contains(" XL "));
+        assert!(screen.contains("r reveal"));
+    }
+}
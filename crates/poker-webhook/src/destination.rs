@@ -0,0 +1,192 @@
+//! Keeping webhooks that users set up from reaching into the network the
+//! server runs in.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+
+use crate::{Result, WebhookError};
+
+/// A host that resolved to no public address.
+#[derive(Debug, thiserror::Error)]
+#[error("{0} does not point to a public address")]
+pub(crate) struct Blocked(pub String);
+
+impl From<Blocked> for WebhookError {
+    fn from(blocked: Blocked) -> Self {
+        WebhookError::InvalidUrl(blocked.to_string())
+    }
+}
+
+/// Resolves host names to their public addresses only, so a connection never
+/// reaches anything else, even if the name resolves differently by the time
+/// it is used than when it was checked.
+pub(crate) struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(Blocked(host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Checks that `url` may be posted to from [`HttpWebhook::public`]: its host
+/// is in `allowed`, or it resolves, and only to public addresses.
+///
+/// [`HttpWebhook::public`]: crate::HttpWebhook::public
+pub async fn check_public(url: &str, allowed: &[String]) -> Result<()> {
+    let url = crate::http::parse_url(url)?;
+    if is_allowed(&url, allowed) {
+        return Ok(());
+    }
+    let host = host(&url);
+    if let Ok(ip) = host.parse() {
+        if !is_public(ip) {
+            return Err(Blocked(host.to_owned()).into());
+        }
+        return Ok(());
+    }
+    let port = url.port_or_known_default().unwrap_or(0);
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|err| WebhookError::InvalidUrl(format!("{host}: {err}")))?
+        .collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+        return Err(Blocked(host.to_owned()).into());
+    }
+    Ok(())
+}
+
+/// The host of `url`, without the brackets around an IPv6 address.
+pub(crate) fn host(url: &Url) -> &str {
+    let host = url.host_str().unwrap_or_default();
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Whether the operator allowed the host of `url` to be anywhere.
+pub(crate) fn is_allowed(url: &Url, allowed: &[String]) -> bool {
+    let host = host(url);
+    allowed.iter().any(|entry| {
+        let entry = entry.trim();
+        match (entry.parse::<IpAddr>(), host.parse::<IpAddr>()) {
+            (Ok(entry), Ok(host)) => entry == host,
+            _ => entry.eq_ignore_ascii_case(host),
+        }
+    })
+}
+
+/// Whether `ip` is reachable on the internet, as opposed to this machine,
+/// a private or link-local network (which holds cloud metadata endpoints),
+/// or a reserved range.
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4-mapped ::ffff:a.b.c.d and IPv4-compatible ::a.b.c.d.
+            if let Some(v4) = ip.to_ipv4() {
+                return is_public_v4(v4);
+            }
+            let segments = ip.segments();
+            let octets = ip.octets();
+            // NAT64 addresses carry an IPv4 address in their last 32 bits.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = octets;
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            // 6to4 addresses, 2002::/16, carry one in the 32 bits after that.
+            if segments[0] == 0x2002 {
+                return is_public_v4(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]));
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local, fc00::/7.
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10.
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation, 2001:db8::/32.
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network", 0.0.0.0/8.
+        || a == 0
+        // Shared address space for carrier-grade NAT, 100.64.0.0/10.
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments, 192.0.0.0/24.
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15.
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved, 240.0.0.0/4.
+        || a >= 240)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for public in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700::1111",
+            "2002:808:808::1",
+        ] {
+            assert!(is_public(public.parse().unwrap()), "{public}");
+        }
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "::127.0.0.1",
+            "::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2002:a9fe:a9fe::1",
+        ] {
+            assert!(!is_public(internal.parse().unwrap()), "{internal}");
+        }
+    }
+
+    #[test]
+    fn allowed_hosts_match_by_name_or_address() {
+        let allowed = ["Chat.Internal".to_owned(), "::1".to_owned()];
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(is_allowed(&url("https://chat.internal/hook"), &allowed));
+        assert!(is_allowed(&url("http://[0:0::1]:8080/hook"), &allowed));
+        assert!(!is_allowed(&url("http://127.0.0.1/hook"), &allowed));
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+
+use crate::destination::{self, Blocked, PublicResolver};
+use crate::signature::{sign, SIGNATURE_HEADER};
+use crate::{Notification, Result, Webhook, WebhookError};
+
+/// How long a receiver may take to answer before the attempt counts as
+/// failed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a refusal's body is kept.
+const MAX_ERROR_BODY: usize = 200;
+
+/// Posts notifications as JSON to a fixed URL.
+pub struct HttpWebhook {
+    url: Url,
+    secret: Option<String>,
+    client: Client,
+}
+
+impl HttpWebhook {
+    /// Posts to `url`, wherever it points. Meant for URLs the operator
+    /// chose; see [`HttpWebhook::public`] for those users hand in.
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(HttpWebhook {
+            url: parse_url(url)?,
+            secret: None,
+            client: Client::builder().timeout(TIMEOUT).build()?,
+        })
+    }
+
+    /// Posts to `url` only while its host resolves to public addresses, so
+    /// that it cannot be aimed at the server's own network, such as a cloud
+    /// metadata endpoint. Hosts in `allowed`, by name or address, may be
+    /// anywhere. Redirects are not followed.
+    pub fn public(url: &str, allowed: &[String]) -> Result<Self> {
+        let url = parse_url(url)?;
+        let mut client = Client::builder().timeout(TIMEOUT).redirect(Policy::none());
+        if !destination::is_allowed(&url, allowed) {
+            // Addresses in the URL itself are never resolved.
+            let host = destination::host(&url);
+            if host.parse().is_ok_and(|ip| !destination::is_public(ip)) {
+                return Err(Blocked(host.to_owned()).into());
+            }
+            client = client.dns_resolver(PublicResolver);
+        }
+        Ok(HttpWebhook {
+            url,
+            secret: None,
+            client: client.build()?,
+        })
+    }
+
+    /// Signs every payload with `secret`; see [`sign`](crate::sign).
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+}
+
+/// Parses a webhook URL, which must be `http` or `https`.
+pub(crate) fn parse_url(url: &str) -> Result<Url> {
+    let url = Url::parse(url).map_err(|err| WebhookError::InvalidUrl(format!("{url}: {err}")))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(WebhookError::InvalidUrl(url.into()));
+    }
+    Ok(url)
+}
+
+#[async_trait]
+impl Webhook for HttpWebhook {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let body = serde_json::to_vec(notification).expect("notifications serialize");
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+        let response = request.body(body).send().await.map_err(|err| {
+            // A host resolving to no public address won't get better.
+            match blocked(&err) {
+                Some(blocked) => WebhookError::InvalidUrl(blocked.to_string()),
+                None => WebhookError::Http(err),
+            }
+        })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let mut body = response.text().await.unwrap_or_default();
+        if body.len() > MAX_ERROR_BODY {
+            let mut end = MAX_ERROR_BODY;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+        }
+        Err(WebhookError::Status {
+            status: status.as_u16(),
+            body,
+        })
+    }
+}
+
+/// The refusal to resolve a host behind `err`, if that is what it was.
+fn blocked(err: &reqwest::Error) -> Option<&Blocked> {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(blocked) = err.downcast_ref::<Blocked>() {
+            return Some(blocked);
+        }
+        source = err.source();
+    }
+    None
+}
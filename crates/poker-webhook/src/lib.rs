@@ -0,0 +1,145 @@
+//! Notifications about OpenPlanningPoker rooms.
+//!
+//! A [`Webhook`] is told about what happens in a room that people outside it
+//! may want to hear about, such as a round being revealed or an estimate
+//! being recorded. [`HttpWebhook`] posts each [`Notification`] as JSON to a
+//! URL, e.g. a chat tool's incoming webhook, signing it when given a secret,
+//! and keeping URLs that users hand in from reaching non-public addresses;
+//! [`deliver`] retries it with backoff until it gets through or the
+//! [`RetryPolicy`] gives up.
+//!
+//! Facilitators subscribe receivers to their own room; a [`Subscription`]
+//! remembers what it wants to hear about and how its last delivery failed.
+//! Everything that sends requests is behind the `http` feature.
+
+#[cfg(feature = "http")]
+mod destination;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+mod retry;
+#[cfg(feature = "http")]
+mod signature;
+mod subscription;
+
+use async_trait::async_trait;
+use poker_core::{Card, RecordedVote, RoomId, Summary, Timestamp, VotingClosed};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "http")]
+pub use destination::check_public;
+#[cfg(feature = "http")]
+pub use http::HttpWebhook;
+#[cfg(feature = "http")]
+pub use retry::{deliver, RetryPolicy, Undelivered};
+#[cfg(feature = "http")]
+pub use signature::{sign, verify, SIGNATURE_HEADER};
+pub use subscription::{DeliveryFailure, Subscription, WebhookId};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[cfg(feature = "http")]
+    #[error("webhook request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The receiver refused the notification. `body` holds the start of
+    /// what it said; it is left out of the message, which may be shown to
+    /// whoever set the webhook up.
+    #[error("webhook answered with status {status}")]
+    Status { status: u16, body: String },
+    #[error("invalid webhook URL: {0}")]
+    InvalidUrl(String),
+}
+
+impl WebhookError {
+    /// Whether trying again later may succeed: the receiver could not be
+    /// reached, timed out, or said it is busy or broken.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(feature = "http")]
+            WebhookError::Http(_) => true,
+            WebhookError::Status { status, .. } => matches!(status, 408 | 429 | 500..),
+            WebhookError::InvalidUrl(_) => false,
+        }
+    }
+}
+
+pub type Result<T, E = WebhookError> = std::result::Result<T, E>;
+
+/// Something that happened in a room, as told to a [`Webhook`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub room: RoomId,
+    pub room_name: String,
+    /// When it happened, by the server's clock.
+    pub at: Timestamp,
+    #[serde(flatten)]
+    pub event: NotificationEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A round is open for votes until `deadline`, or until `quorum` votes
+    /// are in; without a quorum it waits for every voter.
+    VotingOpened {
+        story: Option<String>,
+        deadline: Timestamp,
+        quorum: Option<u32>,
+    },
+    /// A voting window closed. `summary` is set when the round was revealed
+    /// with it.
+    VotingClosed {
+        story: Option<String>,
+        reason: VotingClosed,
+        summary: Option<Summary>,
+    },
+    /// A round's votes were revealed. `votes` is empty in an anonymous room.
+    RoundRevealed {
+        story: Option<String>,
+        votes: Vec<RecordedVote>,
+        summary: Summary,
+    },
+    /// The facilitator recorded the estimate the team agreed on for a
+    /// backlog story.
+    EstimateFinalized {
+        story: String,
+        /// The story's key in an issue tracker, if it came from one.
+        key: Option<String>,
+        estimate: Card,
+    },
+    /// The last participant left the room.
+    SessionEnded {
+        /// Stories in the backlog, and how many of them were estimated.
+        stories: usize,
+        estimated: usize,
+    },
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            NotificationEvent::VotingOpened { .. } => EventKind::VotingOpened,
+            NotificationEvent::VotingClosed { .. } => EventKind::VotingClosed,
+            NotificationEvent::RoundRevealed { .. } => EventKind::RoundRevealed,
+            NotificationEvent::EstimateFinalized { .. } => EventKind::EstimateFinalized,
+            NotificationEvent::SessionEnded { .. } => EventKind::SessionEnded,
+        }
+    }
+}
+
+/// The kinds of [`NotificationEvent`], for subscribing to some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    VotingOpened,
+    VotingClosed,
+    RoundRevealed,
+    EstimateFinalized,
+    SessionEnded,
+}
+
+/// Somewhere notifications are sent to.
+#[async_trait]
+pub trait Webhook: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use crate::{Notification, Webhook, WebhookError};
+
+/// How persistently a notification is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries in total, the first one included.
+    pub attempts: u32,
+    /// Wait before the first retry. It doubles with every retry after that.
+    pub backoff: Duration,
+    /// Longest wait between two tries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// A notification that could not be delivered.
+#[derive(Debug, thiserror::Error)]
+#[error("gave up after {attempts} attempt(s): {error}")]
+pub struct Undelivered {
+    pub attempts: u32,
+    pub error: WebhookError,
+}
+
+/// Sends a notification, retrying failures that may pass with growing waits
+/// in between. Errors that would only repeat, such as the receiver refusing
+/// the payload, are not retried.
+pub async fn deliver(
+    webhook: &dyn Webhook,
+    notification: &Notification,
+    policy: &RetryPolicy,
+) -> Result<(), Undelivered> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match webhook.send(notification).await {
+            Ok(()) => return Ok(()),
+            Err(error) if !error.is_transient() || attempts >= policy.attempts => {
+                return Err(Undelivered { attempts, error });
+            }
+            Err(_) => tokio::time::sleep(policy.delay(attempts)).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            attempts: 10,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+        };
+        let delays: Vec<_> = (1..=6).map(|retry| policy.delay(retry)).collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 5000, 5000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
+    }
+}
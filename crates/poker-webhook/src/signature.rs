@@ -0,0 +1,66 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Header carrying the signature of a payload sent with a secret.
+pub const SIGNATURE_HEADER: &str = "X-Poker-Signature";
+
+const PREFIX: &str = "sha256=";
+
+/// Signs a payload the way [`HttpWebhook`](crate::HttpWebhook) does:
+/// `sha256=` followed by the hex-encoded HMAC-SHA256 of the body.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    format!(
+        "{PREFIX}{}",
+        hex::encode(mac(secret, body).finalize().into_bytes())
+    )
+}
+
+/// Checks a [`SIGNATURE_HEADER`] value against the body it came with, in
+/// constant time. Receivers use this to tell real notifications from forged
+/// ones.
+pub fn verify(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature
+        .strip_prefix(PREFIX)
+        .and_then(|hex| hex::decode(hex).ok())
+    else {
+        return false;
+    };
+    mac(secret, body).verify_slice(&digest).is_ok()
+}
+
+fn mac(secret: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_with_their_secret_and_body() {
+        let body = br#"{"event":"session_ended"}"#;
+        let signature = sign("s3cret", body);
+        assert!(signature.starts_with("sha256="));
+        assert!(verify("s3cret", body, &signature));
+        assert!(!verify("other", body, &signature));
+        assert!(!verify(
+            "s3cret",
+            br#"{"event":"round_revealed"}"#,
+            &signature
+        ));
+        assert!(!verify("s3cret", body, "sha256=not-hex"));
+        assert!(!verify("s3cret", body, &signature["sha256=".len()..]));
+    }
+
+    #[test]
+    fn matches_the_reference_hmac() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+
+use poker_core::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::EventKind;
+
+/// Identifies a subscription within its room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct WebhookId(pub u64);
+
+impl fmt::Display for WebhookId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "w{}", self.0)
+    }
+}
+
+/// A receiver a facilitator subscribed to their room's notifications.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: WebhookId,
+    pub url: String,
+    /// Key every payload is signed with. Handed to the facilitator once,
+    /// when they subscribe.
+    pub secret: String,
+    /// What the receiver wants to hear about; empty means everything.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// The last notification that could not be delivered, if any.
+    #[serde(default)]
+    pub last_failure: Option<DeliveryFailure>,
+}
+
+impl Subscription {
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+/// A notification that was given up on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryFailure {
+    /// When it was given up on.
+    pub at: Timestamp,
+    pub event: EventKind,
+    /// How many times it was tried.
+    pub attempts: u32,
+    /// Why the last attempt failed.
+    pub message: String,
+}
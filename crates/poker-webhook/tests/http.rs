@@ -0,0 +1,217 @@
+//! Exercises the HTTP webhook against an in-process receiver.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::{Json, Router};
+use poker_core::{RoomId, Timestamp};
+use poker_webhook::{
+    check_public, deliver, verify, HttpWebhook, Notification, NotificationEvent, RetryPolicy,
+    Webhook, WebhookError, SIGNATURE_HEADER,
+};
+use serde_json::{json, Value};
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+/// Accepts posts to `/hook`, refuses those to `/broken`, and refuses the
+/// first two posts to `/flaky`. `/signed` keeps the signature header with the
+/// raw body.
+async fn receiver() -> (SocketAddr, Received) {
+    let received = Received::default();
+    let flaky = Arc::new(AtomicU32::new(0));
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(received): State<Received>, Json(body): Json<Value>| async move {
+                    received.lock().unwrap().push(body);
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .route(
+            "/signed",
+            post(
+                |State(received): State<Received>, headers: HeaderMap, body: Bytes| async move {
+                    let signature = headers.get(SIGNATURE_HEADER).map(|v| v.to_str().unwrap());
+                    received.lock().unwrap().push(json!({
+                        "signature": signature,
+                        "body": String::from_utf8(body.to_vec()).unwrap(),
+                    }));
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .route(
+            "/broken",
+            post(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "down for maintenance") }),
+        )
+        .route(
+            "/flaky",
+            post(
+                move |State(received): State<Received>, Json(body): Json<Value>| {
+                    let flaky = flaky.clone();
+                    async move {
+                        if flaky.fetch_add(1, Ordering::SeqCst) < 2 {
+                            return StatusCode::SERVICE_UNAVAILABLE;
+                        }
+                        received.lock().unwrap().push(body);
+                        StatusCode::OK
+                    }
+                },
+            ),
+        )
+        .route(
+            "/gone",
+            post(|| async { (StatusCode::GONE, "no such hook") }),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr, received)
+}
+
+fn opened() -> Notification {
+    Notification {
+        room: RoomId::new("k3m9xq2a"),
+        room_name: "Sprint 42".into(),
+        at: Timestamp(1_000),
+        event: NotificationEvent::VotingOpened {
+            story: Some("Login".into()),
+            deadline: Timestamp(86_401_000),
+            quorum: Some(3),
+        },
+    }
+}
+
+#[tokio::test]
+async fn notifications_are_posted_as_json() {
+    let (addr, received) = receiver().await;
+    let webhook = HttpWebhook::new(&format!("http://{addr}/hook")).unwrap();
+    webhook.send(&opened()).await.unwrap();
+    assert_eq!(
+        received.lock().unwrap().as_slice(),
+        [json!({
+            "room": "k3m9xq2a",
+            "room_name": "Sprint 42",
+            "at": 1000,
+            "event": "voting_opened",
+            "story": "Login",
+            "deadline": 86401000,
+            "quorum": 3
+        })]
+    );
+}
+
+#[tokio::test]
+async fn refused_deliveries_are_errors() {
+    let (addr, _) = receiver().await;
+    let webhook = HttpWebhook::new(&format!("http://{addr}/broken")).unwrap();
+    match webhook.send(&opened()).await {
+        Err(err @ WebhookError::Status { .. }) => {
+            // What the receiver said is kept, but not passed on.
+            assert_eq!(err.to_string(), "webhook answered with status 500");
+            let WebhookError::Status { status, body } = err else {
+                unreachable!()
+            };
+            assert_eq!(status, 500);
+            assert_eq!(body, "down for maintenance");
+        }
+        other => panic!("expected a status error, got {other:?}"),
+    }
+    assert!(matches!(
+        HttpWebhook::new("ftp://example.com/hook"),
+        Err(WebhookError::InvalidUrl(_))
+    ));
+}
+
+#[tokio::test]
+async fn payloads_are_signed_with_the_secret() {
+    let (addr, received) = receiver().await;
+    let webhook = HttpWebhook::new(&format!("http://{addr}/signed"))
+        .unwrap()
+        .with_secret("s3cret");
+    webhook.send(&opened()).await.unwrap();
+    let received = received.lock().unwrap()[0].clone();
+    let body = received["body"].as_str().unwrap();
+    let signature = received["signature"].as_str().unwrap();
+    assert!(verify("s3cret", body.as_bytes(), signature));
+    assert!(!verify("guess", body.as_bytes(), signature));
+    assert_eq!(
+        serde_json::from_str::<Notification>(body).unwrap(),
+        opened()
+    );
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_with_backoff() {
+    let (addr, received) = receiver().await;
+    let policy = RetryPolicy {
+        attempts: 3,
+        backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(20),
+    };
+    let flaky = HttpWebhook::new(&format!("http://{addr}/flaky")).unwrap();
+    deliver(&flaky, &opened(), &policy).await.unwrap();
+    assert_eq!(received.lock().unwrap().len(), 1);
+
+    let broken = HttpWebhook::new(&format!("http://{addr}/broken")).unwrap();
+    let undelivered = deliver(&broken, &opened(), &policy).await.unwrap_err();
+    assert_eq!(undelivered.attempts, 3);
+
+    // Retrying would not change the answer.
+    let gone = HttpWebhook::new(&format!("http://{addr}/gone")).unwrap();
+    let undelivered = deliver(&gone, &opened(), &policy).await.unwrap_err();
+    assert_eq!(undelivered.attempts, 1);
+    assert!(matches!(
+        undelivered.error,
+        WebhookError::Status { status: 410, .. }
+    ));
+}
+
+#[tokio::test]
+async fn public_webhooks_only_reach_public_addresses() {
+    let (addr, received) = receiver().await;
+    for internal in [
+        "http://169.254.169.254/latest/meta-data",
+        "http://10.0.0.1/hook",
+        "http://[::1]/hook",
+    ] {
+        assert!(
+            matches!(
+                HttpWebhook::public(internal, &[]),
+                Err(WebhookError::InvalidUrl(_))
+            ),
+            "{internal}"
+        );
+        assert!(check_public(internal, &[]).await.is_err(), "{internal}");
+    }
+
+    // Names are resolved when posting, and every attempt is refused.
+    let url = format!("http://localhost:{}/hook", addr.port());
+    assert!(check_public(&url, &[]).await.is_err());
+    let named = HttpWebhook::public(&url, &[]).unwrap();
+    let policy = RetryPolicy {
+        attempts: 3,
+        backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(20),
+    };
+    let undelivered = deliver(&named, &opened(), &policy).await.unwrap_err();
+    assert_eq!(undelivered.attempts, 1);
+    assert!(matches!(undelivered.error, WebhookError::InvalidUrl(_)));
+    assert!(received.lock().unwrap().is_empty());
+
+    // Unless the operator allowed the host.
+    let allowed = ["localhost".to_owned()];
+    check_public(&url, &allowed).await.unwrap();
+    let webhook = HttpWebhook::public(&url, &allowed).unwrap();
+    webhook.send(&opened()).await.unwrap();
+    assert_eq!(received.lock().unwrap().len(), 1);
+}